@@ -0,0 +1,256 @@
+use crate::format::ImageFormat;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+//container formats for a sequence of RGBA frames.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum AnimationFormat {
+    //animated PNG: lossless, with full alpha.
+    Apng,
+    //GIF: each frame quantized to 256 colors, with fully transparent pixels kept transparent.
+    Gif,
+    //uncompressed YUV4MPEG2 video, 4:2:0 with BT.601 limited range, for piping into encoders.
+    //there is no alpha channel: frames are composited over black.
+    Y4m,
+}
+
+impl AnimationFormat {
+    pub(crate) fn from_name(name: &str) -> Option<AnimationFormat> {
+        match name {
+            "apng" | "png" => Some(AnimationFormat::Apng),
+            "gif" => Some(AnimationFormat::Gif),
+            "y4m" => Some(AnimationFormat::Y4m),
+            _ => None,
+        }
+    }
+
+    //the format named by a file's extension, if it is an animation format.
+    pub(crate) fn from_path(path: &str) -> Option<AnimationFormat> {
+        let extension = Path::new(path).extension()?.to_str()?;
+        AnimationFormat::from_name(&extension.to_ascii_lowercase())
+    }
+
+    //encodes row-major RGBA frames as they are rendered, showing each for `delay` milliseconds.
+    //APNGs and GIFs loop forever.
+    pub(crate) fn write<W: Write>(
+        self,
+        w: W,
+        width: u32,
+        height: u32,
+        frames: impl ExactSizeIterator<Item = Vec<u8>>,
+        delay: u16,
+    ) -> io::Result<()> {
+        match self {
+            AnimationFormat::Apng => write_apng(w, width, height, frames, delay),
+            AnimationFormat::Gif => write_gif(w, width, height, frames, delay),
+            AnimationFormat::Y4m => write_y4m(w, width, height, frames, delay),
+        }
+    }
+}
+
+//whether a file name is a printf-style pattern for numbered frames, like "out_%04d.png".
+pub(crate) fn is_sequence(pattern: &str) -> bool {
+    frame_path(pattern, 0).is_some()
+}
+
+//substitutes the frame number for the first "%d" or "%0Nd" in the pattern.
+fn frame_path(pattern: &str, index: usize) -> Option<String> {
+    let start = pattern.find('%')?;
+    let rest = &pattern[start + 1..];
+    let end = rest.find('d')?;
+    let spec = &rest[..end];
+    let width: usize = if spec.is_empty() {
+        0
+    } else if spec.starts_with('0') {
+        spec.parse().ok()?
+    } else {
+        return None;
+    };
+    Some(format!(
+        "{}{index:0width$}{}",
+        &pattern[..start],
+        &rest[end + 1..]
+    ))
+}
+
+//writes every frame to its own numbered file, in the image format of the pattern's extension.
+pub(crate) fn write_sequence(
+    pattern: &str,
+    format: ImageFormat,
+    width: u32,
+    height: u32,
+    frames: impl Iterator<Item = Vec<u8>>,
+) -> io::Result<()> {
+    for (index, frame) in frames.enumerate() {
+        let path = frame_path(pattern, index)
+            .ok_or_else(|| io::Error::other(format!("{pattern} is not a frame pattern")))?;
+        format.write_rgba(BufWriter::new(File::create(path)?), width, height, &frame)?;
+    }
+    Ok(())
+}
+
+fn write_apng<W: Write>(
+    w: W,
+    width: u32,
+    height: u32,
+    frames: impl ExactSizeIterator<Item = Vec<u8>>,
+    delay: u16,
+) -> io::Result<()> {
+    let mut encoder = png::Encoder::new(w, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let count = u32::try_from(frames.len()).map_err(io::Error::other)?;
+    //0 plays loops forever.
+    encoder.set_animated(count, 0)?;
+    encoder.set_frame_delay(delay, 1000)?;
+    //every frame replaces the whole canvas, so transparent pixels do not show the previous frame.
+    encoder.set_blend_op(png::BlendOp::Source)?;
+    let mut writer = encoder.write_header()?;
+    for frame in frames {
+        writer.write_image_data(&frame)?;
+    }
+    writer.finish()?;
+    Ok(())
+}
+
+fn write_gif<W: Write>(
+    w: W,
+    width: u32,
+    height: u32,
+    frames: impl Iterator<Item = Vec<u8>>,
+    delay: u16,
+) -> io::Result<()> {
+    let width = u16::try_from(width).map_err(io::Error::other)?;
+    let height = u16::try_from(height).map_err(io::Error::other)?;
+    let mut encoder = gif::Encoder::new(w, width, height, &[]).map_err(io::Error::other)?;
+    encoder.set_repeat(gif::Repeat::Infinite).map_err(io::Error::other)?;
+    for mut data in frames {
+        //speed 10 is the quantizer's recommended balance of quality and time.
+        let mut frame = gif::Frame::from_rgba_speed(width, height, &mut data, 10);
+        //gif delays are in hundredths of a second.
+        frame.delay = delay.div_ceil(10);
+        frame.dispose = gif::DisposalMethod::Background;
+        encoder.write_frame(&frame).map_err(io::Error::other)?;
+    }
+    Ok(())
+}
+
+fn write_y4m<W: Write>(
+    mut w: W,
+    width: u32,
+    height: u32,
+    frames: impl Iterator<Item = Vec<u8>>,
+    delay: u16,
+) -> io::Result<()> {
+    let (width, height) = (width as usize, height as usize);
+    //progressive frames of square pixels, at 1000 / delay frames per second.
+    writeln!(
+        w,
+        "YUV4MPEG2 W{width} H{height} F1000:{} Ip A1:1 C420jpeg",
+        delay.max(1)
+    )?;
+    let (chroma_width, chroma_height) = (width.div_ceil(2), height.div_ceil(2));
+    for frame in frames {
+        let rgb: Vec<[f32; 3]> = frame
+            .chunks_exact(4)
+            .map(|p| [0, 1, 2].map(|i| f32::from(p[i]) * f32::from(p[3]) / 255.0))
+            .collect();
+        let luma = rgb.iter().map(|&[r, g, b]| 16.0 + (65.481 * r + 128.553 * g + 24.966 * b) / 255.0);
+        //each chroma sample averages the pixels of a 2x2 block, fewer at odd edges.
+        let mut cb = Vec::with_capacity(chroma_width * chroma_height);
+        let mut cr = Vec::with_capacity(chroma_width * chroma_height);
+        for y in 0..chroma_height {
+            for x in 0..chroma_width {
+                let block: Vec<[f32; 3]> = [(0, 0), (1, 0), (0, 1), (1, 1)]
+                    .iter()
+                    .filter(|&&(dx, dy)| x * 2 + dx < width && y * 2 + dy < height)
+                    .map(|&(dx, dy)| rgb[(y * 2 + dy) * width + x * 2 + dx])
+                    .collect();
+                let [r, g, b] = [0, 1, 2].map(|i| block.iter().map(|p| p[i]).sum::<f32>() / block.len() as f32);
+                cb.push(128.0 + (-37.797 * r - 74.203 * g + 112.0 * b) / 255.0);
+                cr.push(128.0 + (112.0 * r - 93.786 * g - 18.214 * b) / 255.0);
+            }
+        }
+        #[allow(clippy::cast_possible_truncation)]
+        #[allow(clippy::cast_sign_loss)]
+        let planes: Vec<u8> = luma
+            .chain(cb)
+            .chain(cr)
+            .map(|v| v.round().clamp(0.0, 255.0) as u8)
+            .collect();
+        w.write_all(b"FRAME\n")?;
+        w.write_all(&planes)?;
+    }
+    w.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::animation::{frame_path, is_sequence, AnimationFormat};
+
+    fn frames() -> Vec<Vec<u8>> {
+        vec![[255, 0, 0, 255].repeat(4), [0, 0, 255, 0].repeat(4)]
+    }
+
+    #[test]
+    fn formats_from_paths() {
+        assert_eq!(AnimationFormat::from_path("spin.GIF"), Some(AnimationFormat::Gif));
+        assert_eq!(AnimationFormat::from_path("spin.png"), Some(AnimationFormat::Apng));
+        assert_eq!(AnimationFormat::from_path("spin.tga"), None);
+    }
+
+    #[test]
+    fn apng_frames() {
+        let mut out = vec![];
+        AnimationFormat::Apng.write(&mut out, 2, 2, frames().into_iter(), 40).unwrap();
+        let decoder = png::Decoder::new(out.as_slice());
+        let mut reader = decoder.read_info().unwrap();
+        let control = reader.info().animation_control.unwrap();
+        assert_eq!((control.num_frames, control.num_plays), (2, 0));
+        let mut buf = vec![0; reader.output_buffer_size()];
+        reader.next_frame(&mut buf).unwrap();
+        reader.next_frame(&mut buf).unwrap();
+        assert_eq!(buf, frames()[1]);
+        assert_eq!(reader.info().frame_control.unwrap().delay_num, 40);
+    }
+
+    #[test]
+    fn gif_frames() {
+        let mut out = vec![];
+        AnimationFormat::Gif.write(&mut out, 2, 2, frames().into_iter(), 40).unwrap();
+        let mut options = gif::DecodeOptions::new();
+        options.set_color_output(gif::ColorOutput::RGBA);
+        let mut decoder = options.read_info(out.as_slice()).unwrap();
+        let first = decoder.read_next_frame().unwrap().unwrap();
+        assert_eq!(first.delay, 4);
+        assert_eq!(&first.buffer[..4], &[255, 0, 0, 255]);
+        let second = decoder.read_next_frame().unwrap().unwrap();
+        assert_eq!(second.buffer[3], 0);
+        assert!(decoder.read_next_frame().unwrap().is_none());
+    }
+
+    #[test]
+    fn y4m_planes() {
+        let mut out = vec![];
+        AnimationFormat::Y4m.write(&mut out, 2, 2, frames().into_iter(), 40).unwrap();
+        let header = b"YUV4MPEG2 W2 H2 F1000:40 Ip A1:1 C420jpeg\n";
+        assert!(out.starts_with(header));
+        //per frame: the marker, 4 luma samples and one sample of each chroma plane.
+        assert_eq!(out.len(), header.len() + 2 * (6 + 4 + 2));
+        let first = &out[header.len() + 6..header.len() + 12];
+        assert_eq!(first, &[81, 81, 81, 81, 90, 240]);
+        //transparent blue is black.
+        let second = &out[header.len() + 18..];
+        assert_eq!(second, &[16, 16, 16, 16, 128, 128]);
+    }
+
+    #[test]
+    fn frame_patterns() {
+        assert_eq!(frame_path("out_%04d.png", 7).as_deref(), Some("out_0007.png"));
+        assert_eq!(frame_path("%d.qoi", 12).as_deref(), Some("12.qoi"));
+        assert!(is_sequence("frames/%03d.tga"));
+        assert!(!is_sequence("out.png"));
+        assert!(!is_sequence("100%.png"));
+    }
+}
@@ -0,0 +1,228 @@
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::ops::Range;
+
+//what uncovered pixels of the RGBA image show.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Background {
+    Transparent,
+    Solid([u8; 4]),
+    //from the top row of the image to the bottom row.
+    Gradient([u8; 4], [u8; 4]),
+    //a PNG file, stretched to the output size.
+    Image(String),
+}
+
+impl Background {
+    //parses "transparent", a color "#rrggbb[aa]", "gradient:<top color>:<bottom color>"
+    //or "image:<file.png>".
+    pub(crate) fn from_spec(spec: &str) -> Option<Background> {
+        if spec == "transparent" {
+            Some(Background::Transparent)
+        } else if let Some(colors) = spec.strip_prefix("gradient:") {
+            let (top, bottom) = colors.split_once(':')?;
+            Some(Background::Gradient(parse_color(top)?, parse_color(bottom)?))
+        } else if let Some(file) = spec.strip_prefix("image:") {
+            (!file.is_empty()).then(|| Background::Image(String::from(file)))
+        } else {
+            parse_color(spec).map(Background::Solid)
+        }
+    }
+
+    //fills a width * height RGBA buffer, row by row, with the background.
+    pub(crate) fn render(&self, width: usize, height: usize) -> io::Result<Vec<u8>> {
+        Ok(self.decode()?.render_rows(width, height, 0..height))
+    }
+
+    //reads any image once, for backgrounds rendered a band at a time.
+    pub(crate) fn decode(&self) -> io::Result<Backdrop<'_>> {
+        let image = match self {
+            Background::Image(file) => Some(read_png_rgba(file)?),
+            _ => None,
+        };
+        Ok(Backdrop {
+            background: self,
+            image,
+        })
+    }
+}
+
+//a background with its image, if it has one, already decoded into its width, height and pixels.
+pub(crate) struct Backdrop<'a> {
+    background: &'a Background,
+    image: Option<(usize, usize, Vec<u8>)>,
+}
+
+impl Backdrop<'_> {
+    //fills the given rows of a width * height RGBA image, so that a band of a large image needs
+    //no more memory than the band.
+    pub(crate) fn render_rows(&self, width: usize, height: usize, rows: Range<usize>) -> Vec<u8> {
+        match self.background {
+            Background::Transparent => vec![0; width * rows.len() * 4],
+            Background::Solid(color) => color.repeat(width * rows.len()),
+            Background::Gradient(top, bottom) => rows
+                .flat_map(|y| {
+                    let t = if height > 1 {
+                        y as f32 / (height - 1) as f32
+                    } else {
+                        0.0
+                    };
+                    mix(*top, *bottom, t).repeat(width)
+                })
+                .collect(),
+            Background::Image(_) => match &self.image {
+                Some((image_width, image_height, pixels)) => {
+                    scale_rows(pixels, *image_width, *image_height, width, height, rows)
+                }
+                None => unreachable!("image backgrounds are decoded with their image"),
+            },
+        }
+    }
+}
+
+//the background before any was configurable: opaque black.
+impl Default for Background {
+    fn default() -> Self {
+        Background::Solid([0, 0, 0, 255])
+    }
+}
+
+pub(crate) fn parse_color(hex: &str) -> Option<[u8; 4]> {
+    let hex = hex.strip_prefix('#')?;
+    if !(hex.len() == 6 || hex.len() == 8) || !hex.is_ascii() {
+        return None;
+    }
+    let mut color = [255u8; 4];
+    for (i, channel) in color.iter_mut().enumerate().take(hex.len() / 2) {
+        *channel = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).ok()?;
+    }
+    Some(color)
+}
+
+#[allow(clippy::cast_possible_truncation)]
+#[allow(clippy::cast_sign_loss)]
+fn mix(from: [u8; 4], to: [u8; 4], t: f32) -> [u8; 4] {
+    [0, 1, 2, 3].map(|i| (f32::from(from[i]) * (1.0 - t) + f32::from(to[i]) * t).round() as u8)
+}
+
+fn read_png_rgba(file: &str) -> io::Result<(usize, usize, Vec<u8>)> {
+    decode_png_rgba(BufReader::new(File::open(file)?))
+}
+
+//decodes any PNG into 8-bit RGBA, returning its width, height and row-major pixels.
+pub(crate) fn decode_png_rgba(reader: impl Read) -> io::Result<(usize, usize, Vec<u8>)> {
+    let mut decoder = png::Decoder::new(reader);
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let mut reader = decoder.read_info().map_err(io::Error::other)?;
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf).map_err(io::Error::other)?;
+    buf.truncate(info.buffer_size());
+    let rgba = match info.color_type {
+        png::ColorType::Rgba => buf,
+        png::ColorType::Rgb => buf.chunks_exact(3).flat_map(|p| [p[0], p[1], p[2], 255]).collect(),
+        png::ColorType::GrayscaleAlpha => buf.chunks_exact(2).flat_map(|p| [p[0], p[0], p[0], p[1]]).collect(),
+        png::ColorType::Grayscale => buf.iter().flat_map(|&g| [g, g, g, 255]).collect(),
+        png::ColorType::Indexed => {
+            return Err(io::Error::other("indexed PNGs should have been expanded"))
+        }
+    };
+    Ok((info.width as usize, info.height as usize, rgba))
+}
+
+//bilinear resampling of an RGBA image, sampling at pixel centers.
+pub(crate) fn scale(pixels: &[u8], width: usize, height: usize, to_width: usize, to_height: usize) -> Vec<u8> {
+    scale_rows(pixels, width, height, to_width, to_height, 0..to_height)
+}
+
+//like scale, but only the given rows of the resampled image.
+fn scale_rows(
+    pixels: &[u8],
+    width: usize,
+    height: usize,
+    to_width: usize,
+    to_height: usize,
+    rows: Range<usize>,
+) -> Vec<u8> {
+    let texel = |x: usize, y: usize| {
+        let i = (y * width + x) * 4;
+        [pixels[i], pixels[i + 1], pixels[i + 2], pixels[i + 3]]
+    };
+    //the source coordinate that a destination pixel center maps onto, split into texel and fraction.
+    let source = |to: usize, to_extent: usize, extent: usize| {
+        let s = ((to as f32 + 0.5) * extent as f32 / to_extent as f32 - 0.5).max(0.0);
+        let low = (s as usize).min(extent - 1);
+        (low, (low + 1).min(extent - 1), s - low as f32)
+    };
+    let mut scaled = Vec::with_capacity(to_width * rows.len() * 4);
+    for y in rows {
+        let (y0, y1, ty) = source(y, to_height, height);
+        for x in 0..to_width {
+            let (x0, x1, tx) = source(x, to_width, width);
+            let top = mix(texel(x0, y0), texel(x1, y0), tx);
+            let bottom = mix(texel(x0, y1), texel(x1, y1), tx);
+            scaled.extend_from_slice(&mix(top, bottom, ty));
+        }
+    }
+    scaled
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::background::{parse_color, scale, Background};
+
+    #[test]
+    fn specs() {
+        assert_eq!(Background::from_spec("transparent"), Some(Background::Transparent));
+        assert_eq!(
+            Background::from_spec("#ff8000"),
+            Some(Background::Solid([255, 128, 0, 255]))
+        );
+        assert_eq!(
+            Background::from_spec("gradient:#000000:#ffffff80"),
+            Some(Background::Gradient([0, 0, 0, 255], [255, 255, 255, 128]))
+        );
+        assert_eq!(
+            Background::from_spec("image:sky.png"),
+            Some(Background::Image(String::from("sky.png")))
+        );
+        assert_eq!(Background::from_spec("image:"), None);
+        assert_eq!(parse_color("ff8000"), None);
+        assert_eq!(parse_color("#ff80"), None);
+        assert_eq!(parse_color("#gg8000"), None);
+    }
+
+    #[test]
+    fn gradient_rows() {
+        let background = Background::Gradient([0, 0, 0, 255], [200, 100, 50, 255]);
+        let pixels = background.render(2, 3).unwrap();
+        assert_eq!(&pixels[0..8], &[0, 0, 0, 255, 0, 0, 0, 255]);
+        assert_eq!(&pixels[8..12], &[100, 50, 25, 255]);
+        assert_eq!(&pixels[16..20], &[200, 100, 50, 255]);
+        assert_eq!(background.decode().unwrap().render_rows(2, 3, 1..3), &pixels[8..]);
+    }
+
+    #[test]
+    fn images_are_read_once() {
+        let file = std::env::temp_dir().join(format!("rusterizer-backdrop-{}.png", std::process::id()));
+        let mut encoder = png::Encoder::new(std::fs::File::create(&file).unwrap(), 1, 2);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.write_header().unwrap().write_image_data(&[0, 0, 0, 255, 200, 100, 50, 255]).unwrap();
+        let background = Background::Image(file.to_string_lossy().into_owned());
+        let backdrop = background.decode().unwrap();
+        //later bands come from the decoded pixels, not the file.
+        std::fs::remove_file(&file).unwrap();
+        assert_eq!(backdrop.render_rows(1, 4, 0..1), [0, 0, 0, 255]);
+        assert_eq!(backdrop.render_rows(1, 4, 3..4), [200, 100, 50, 255]);
+        assert!(background.decode().is_err());
+    }
+
+    #[test]
+    fn scaling_keeps_corners() {
+        let pixels = [[0, 0, 0, 255], [255, 255, 255, 255]].concat();
+        let scaled = scale(&pixels, 2, 1, 4, 2);
+        assert_eq!(scaled.len(), 4 * 2 * 4);
+        assert_eq!(&scaled[0..4], &[0, 0, 0, 255]);
+        assert_eq!(&scaled[12..16], &[255, 255, 255, 255]);
+        assert_eq!(&scaled[16..20], &[0, 0, 0, 255]);
+    }
+}
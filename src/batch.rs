@@ -0,0 +1,362 @@
+use crate::format::ImageFormat;
+use crate::mesh::{MeshFormat, Primitive};
+use crate::material::Material;
+use crate::obj::load_mesh_data;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::num::NonZeroUsize;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, PoisonError};
+use tobj::Model;
+
+//the listing written next to a batch's thumbnails.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum IndexFormat {
+    Html,
+    Json,
+}
+
+impl IndexFormat {
+    pub(crate) fn from_name(name: &str) -> Option<IndexFormat> {
+        match name {
+            "html" => Some(IndexFormat::Html),
+            "json" => Some(IndexFormat::Json),
+            _ => None,
+        }
+    }
+
+    fn file_name(self) -> &'static str {
+        match self {
+            IndexFormat::Html => "index.html",
+            IndexFormat::Json => "index.json",
+        }
+    }
+}
+
+//a mesh that was rendered, and its thumbnail's file name within the output directory.
+#[derive(Debug, PartialEq, Serialize)]
+pub(crate) struct Thumbnail {
+    pub(crate) mesh: String,
+    pub(crate) image: String,
+    pub(crate) triangles: usize,
+}
+
+//a mesh that could not be loaded, or whose thumbnail could not be written.
+#[derive(Debug, PartialEq, Serialize)]
+pub(crate) struct Failure {
+    pub(crate) mesh: String,
+    pub(crate) error: String,
+}
+
+#[derive(Serialize)]
+struct Index<'a> {
+    width: u32,
+    height: u32,
+    thumbnails: &'a [Thumbnail],
+    failures: &'a [Failure],
+}
+
+//the meshes a batch renders, sorted: the mesh files in a directory, or the files matching a
+//pattern whose last component may hold the wildcards * and ?, such as "assets/chair_*.obj".
+pub(crate) fn find_meshes(source: &str) -> io::Result<Vec<PathBuf>> {
+    let path = Path::new(source);
+    let (directory, pattern) = if path.is_dir() {
+        (path, None)
+    } else {
+        let pattern = path.file_name().and_then(|name| name.to_str()).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, format!("{source} is not a directory or pattern"))
+        })?;
+        let parent = path.parent().filter(|parent| !parent.as_os_str().is_empty());
+        (parent.unwrap_or(Path::new(".")), Some(pattern))
+    };
+    let mut meshes = vec![];
+    for entry in fs::read_dir(directory)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        let Some(name) = name.to_str() else {
+            continue;
+        };
+        let matches = match pattern {
+            Some(pattern) => wildcard(pattern, name),
+            None => MeshFormat::from_path(name).is_some(),
+        };
+        if matches && entry.file_type()?.is_file() {
+            meshes.push(entry.path());
+        }
+    }
+    meshes.sort();
+    Ok(meshes)
+}
+
+//whether `name` matches `pattern`, where * stands for any run of characters and ? for one.
+fn wildcard(pattern: &str, name: &str) -> bool {
+    match pattern.chars().next() {
+        None => name.is_empty(),
+        Some('*') => name
+            .char_indices()
+            .map(|(i, _)| i)
+            .chain([name.len()])
+            .any(|i| wildcard(&pattern[1..], &name[i..])),
+        Some(p) => match name.chars().next() {
+            Some(n) if p == '?' || p == n => wildcard(&pattern[p.len_utf8()..], &name[n.len_utf8()..]),
+            _ => false,
+        },
+    }
+}
+
+//a thumbnail file name for each mesh, after its file name, numbered when names repeat.
+fn thumbnail_names(meshes: &[PathBuf], format: ImageFormat) -> Vec<String> {
+    let mut taken = HashSet::new();
+    meshes
+        .iter()
+        .map(|mesh| {
+            let stem = mesh.file_stem().map_or(String::from("mesh"), |stem| stem.to_string_lossy().into_owned());
+            let mut name = format!("{stem}.{}", format.extension());
+            let mut count = 1;
+            while !taken.insert(name.clone()) {
+                count += 1;
+                name = format!("{stem}-{count}.{}", format.extension());
+            }
+            name
+        })
+        .collect()
+}
+
+//renders every mesh with `render` on up to `jobs` threads, writing each width * height
+//thumbnail into `output` as soon as it is shaded. Meshes that fail, even by panicking while they
+//load or render, are returned with the reason, rather than stopping the batch. Both lists keep
+//the order of `meshes`.
+pub(crate) fn render_batch(
+    meshes: &[PathBuf],
+    output: &Path,
+    format: ImageFormat,
+    (width, height): (u32, u32),
+    jobs: NonZeroUsize,
+    render: impl Fn(&[Model], &[Material]) -> Vec<u8> + Sync,
+) -> (Vec<Thumbnail>, Vec<Failure>) {
+    let names = thumbnail_names(meshes, format);
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(vec![]);
+    std::thread::scope(|scope| {
+        for _ in 0..jobs.get().min(meshes.len()) {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let (Some(mesh), Some(name)) = (meshes.get(index), names.get(index)) else {
+                    break;
+                };
+                let mesh = mesh.to_string_lossy().into_owned();
+                //nothing the job shares is left half written by a panic, as results are only
+                //pushed after it.
+                let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                    load_mesh_data(&mesh).map_err(|e| e.to_string()).and_then(|(models, materials)| {
+                        let data = render(&models, &materials);
+                        File::create(output.join(name))
+                            .and_then(|file| format.write_rgba(BufWriter::new(file), width, height, &data))
+                            .map_err(|e| format!("could not write {name}: {e}"))?;
+                        let triangles = models.iter().filter(|model| Primitive::of(&model.mesh) == Primitive::Triangles);
+                        Ok(triangles.map(|model| model.mesh.indices.len() / 3).sum())
+                    })
+                }))
+                .unwrap_or_else(|payload| Err(panic_message(payload.as_ref())));
+                let result = match result {
+                    Ok(triangles) => Ok(Thumbnail {
+                        mesh,
+                        image: name.clone(),
+                        triangles,
+                    }),
+                    Err(error) => Err(Failure { mesh, error }),
+                };
+                results.lock().unwrap_or_else(PoisonError::into_inner).push((index, result));
+            });
+        }
+    });
+    let mut results = results.into_inner().unwrap_or_else(PoisonError::into_inner);
+    results.sort_by_key(|(index, _)| *index);
+    let mut thumbnails = vec![];
+    let mut failures = vec![];
+    for (_, result) in results {
+        match result {
+            Ok(thumbnail) => thumbnails.push(thumbnail),
+            Err(failure) => failures.push(failure),
+        }
+    }
+    (thumbnails, failures)
+}
+
+//the message a job panicked with, which panic!() and assert!() give as a &str or a String.
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    let message = payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("an unknown error");
+    format!("panicked: {message}")
+}
+
+//writes the index of a batch into its output directory, returning the index's path.
+pub(crate) fn write_index(
+    output: &Path,
+    format: IndexFormat,
+    (width, height): (u32, u32),
+    thumbnails: &[Thumbnail],
+    failures: &[Failure],
+) -> io::Result<PathBuf> {
+    let path = output.join(format.file_name());
+    let mut w = BufWriter::new(File::create(&path)?);
+    match format {
+        IndexFormat::Json => {
+            let index = Index {
+                width,
+                height,
+                thumbnails,
+                failures,
+            };
+            serde_json::to_writer_pretty(&mut w, &index)?;
+            writeln!(w)?;
+        }
+        IndexFormat::Html => write_html(&mut w, (width, height), thumbnails, failures)?,
+    }
+    w.flush()?;
+    Ok(path)
+}
+
+fn write_html<W: Write>(
+    w: &mut W,
+    (width, height): (u32, u32),
+    thumbnails: &[Thumbnail],
+    failures: &[Failure],
+) -> io::Result<()> {
+    writeln!(w, "<!DOCTYPE html>")?;
+    writeln!(w, "<html><head><meta charset=\"utf-8\"><title>Thumbnails</title>")?;
+    writeln!(
+        w,
+        "<style>body {{ font-family: sans-serif; }} figure {{ display: inline-block; margin: 8px; }} \
+         figcaption {{ text-align: center; }}</style>"
+    )?;
+    writeln!(w, "</head><body>")?;
+    for thumbnail in thumbnails {
+        writeln!(
+            w,
+            "<figure><img src=\"{}\" width=\"{width}\" height=\"{height}\" alt=\"{}\"><figcaption>{} ({} triangles)</figcaption></figure>",
+            percent_encode(&thumbnail.image),
+            escape(&thumbnail.mesh),
+            escape(&thumbnail.mesh),
+            thumbnail.triangles
+        )?;
+    }
+    if !failures.is_empty() {
+        writeln!(w, "<h2>Failures</h2><ul>")?;
+        for failure in failures {
+            writeln!(w, "<li>{}: {}</li>", escape(&failure.mesh), escape(&failure.error))?;
+        }
+        writeln!(w, "</ul>")?;
+    }
+    writeln!(w, "</body></html>")
+}
+
+//a file name as a relative URL, whose '#', '?' and '%' would otherwise start a fragment, a query
+//or an escape. Everything but unreserved characters is encoded, which leaves nothing to escape
+//for HTML either.
+fn percent_encode(name: &str) -> String {
+    name.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => char::from(byte).to_string(),
+            _ => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::batch::{find_meshes, percent_encode, render_batch, thumbnail_names, wildcard, write_index, IndexFormat};
+    use crate::format::ImageFormat;
+    use std::num::NonZeroUsize;
+    use std::path::PathBuf;
+
+    #[test]
+    fn wildcards() {
+        assert!(wildcard("*.obj", "teapot.obj"));
+        assert!(wildcard("te?ra*", "tetra.obj"));
+        assert!(wildcard("*", ""));
+        assert!(!wildcard("*.obj", "teapot.obj.bak"));
+        assert!(!wildcard("?", ""));
+    }
+
+    #[test]
+    fn finds_meshes_in_directories_and_patterns() {
+        let all = find_meshes("tests/resources").unwrap();
+        assert!(all.len() >= 8);
+        assert!(all.windows(2).all(|pair| pair[0] < pair[1]));
+        let some = find_meshes("tests/resources/t*.obj").unwrap();
+        let names: Vec<_> = some.iter().filter_map(|mesh| mesh.file_name()?.to_str()).collect();
+        assert_eq!(names, ["teapot.obj", "test.obj", "tetra.obj", "tri.obj"]);
+    }
+
+    #[test]
+    fn repeated_names_are_numbered() {
+        let meshes = [PathBuf::from("a/cube.obj"), PathBuf::from("b/cube.obj"), PathBuf::from("tri.obj")];
+        assert_eq!(thumbnail_names(&meshes, ImageFormat::Qoi), ["cube.qoi", "cube-2.qoi", "tri.qoi"]);
+    }
+
+    #[test]
+    fn batch_continues_past_failures() {
+        let output = std::env::temp_dir().join(format!("rusterizer-batch-{}", std::process::id()));
+        std::fs::create_dir_all(&output).unwrap();
+        let meshes = [
+            PathBuf::from("tests/resources/missing.obj"),
+            PathBuf::from("tests/resources/tri.obj"),
+            PathBuf::from("tests/resources/tetra.obj"),
+        ];
+        let jobs = NonZeroUsize::new(2).unwrap();
+        let (thumbnails, failures) = render_batch(&meshes, &output, ImageFormat::Png, (2, 2), jobs, |_, _| vec![0; 16]);
+        assert_eq!(thumbnails.len(), 2);
+        assert_eq!(thumbnails[0].image, "tri.png");
+        assert_eq!(thumbnails[0].triangles, 1);
+        assert!(output.join("tetra.png").is_file());
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].mesh, "tests/resources/missing.obj");
+        let index = write_index(&output, IndexFormat::Json, (2, 2), &thumbnails, &failures).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(index).unwrap()).unwrap();
+        assert_eq!(json["thumbnails"][1]["image"], "tetra.png");
+        assert!(json["failures"][0]["error"].as_str().unwrap().contains("No such file"));
+        let index = write_index(&output, IndexFormat::Html, (2, 2), &thumbnails, &failures).unwrap();
+        assert!(std::fs::read_to_string(index).unwrap().contains("<img src=\"tri.png\""));
+        std::fs::remove_dir_all(output).unwrap();
+    }
+
+    #[test]
+    fn panics_are_failures() {
+        let output = std::env::temp_dir().join(format!("rusterizer-panics-{}", std::process::id()));
+        std::fs::create_dir_all(&output).unwrap();
+        let meshes = [PathBuf::from("tests/resources/tetra.obj"), PathBuf::from("tests/resources/tri.obj")];
+        let jobs = NonZeroUsize::new(1).unwrap();
+        let render = |models: &[tobj::Model], _: &[crate::material::Material]| {
+            assert_eq!(models[0].mesh.indices.len(), 3, "more than a triangle");
+            vec![0; 16]
+        };
+        let (thumbnails, failures) = render_batch(&meshes, &output, ImageFormat::Png, (2, 2), jobs, render);
+        assert_eq!(thumbnails.len(), 1);
+        assert_eq!(thumbnails[0].image, "tri.png");
+        assert_eq!(failures[0].mesh, "tests/resources/tetra.obj");
+        assert!(failures[0].error.starts_with("panicked: "));
+        std::fs::remove_dir_all(output).unwrap();
+    }
+
+    #[test]
+    fn names_are_percent_encoded() {
+        assert_eq!(percent_encode("tri.png"), "tri.png");
+        assert_eq!(percent_encode("a #1?50%.png"), "a%20%231%3F50%25.png");
+        assert_eq!(percent_encode("é\"<.png"), "%C3%A9%22%3C.png");
+    }
+}
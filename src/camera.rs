@@ -0,0 +1,456 @@
+use std::ops::Mul;
+
+pub(crate) type Vec3 = [f32; 3];
+
+fn add(a: Vec3, b: Vec3) -> Vec3 {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+fn sub(a: Vec3, b: Vec3) -> Vec3 {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn scale(a: Vec3, s: f32) -> Vec3 {
+    a.map(|c| c * s)
+}
+
+fn dot(a: Vec3, b: Vec3) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: Vec3, b: Vec3) -> Vec3 {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+pub(crate) fn normalize(a: Vec3) -> Vec3 {
+    let length = dot(a, a).sqrt();
+    if length > 0.0 {
+        scale(a, 1.0 / length)
+    } else {
+        a
+    }
+}
+
+pub(crate) fn lerp(a: Vec3, b: Vec3, t: f32) -> Vec3 {
+    add(a, scale(sub(b, a), t))
+}
+
+//a rotation, as a unit quaternion w + xi + yj + zk.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub(crate) struct Quat {
+    pub(crate) w: f32,
+    pub(crate) x: f32,
+    pub(crate) y: f32,
+    pub(crate) z: f32,
+}
+
+impl Quat {
+    pub(crate) const IDENTITY: Quat = Quat {
+        w: 1.0,
+        x: 0.0,
+        y: 0.0,
+        z: 0.0,
+    };
+
+    pub(crate) fn new(w: f32, x: f32, y: f32, z: f32) -> Quat {
+        Quat { w, x, y, z }.normalize()
+    }
+
+    //the rotation of a camera at `eye`, which looks down its -z axis, that faces `target`
+    //with its y axis as close to world up (+y) as possible.
+    pub(crate) fn look_at(eye: Vec3, target: Vec3) -> Quat {
+        let forward = normalize(sub(target, eye));
+        //looking straight up or down, +z stands in for up.
+        let up = if cross(forward, [0.0, 1.0, 0.0]) == [0.0; 3] {
+            [0.0, 0.0, 1.0]
+        } else {
+            [0.0, 1.0, 0.0]
+        };
+        let right = normalize(cross(forward, up));
+        let up = cross(right, forward);
+        Quat::from_basis(right, up, scale(forward, -1.0))
+    }
+
+    //the rotation that takes the x, y and z axes to the given orthonormal columns.
+    fn from_basis(x: Vec3, y: Vec3, z: Vec3) -> Quat {
+        let trace = x[0] + y[1] + z[2];
+        //branch on the largest diagonal term to keep the square root well away from zero.
+        let quat = if trace > 0.0 {
+            let s = (trace + 1.0).sqrt() * 2.0;
+            Quat { w: s / 4.0, x: (y[2] - z[1]) / s, y: (z[0] - x[2]) / s, z: (x[1] - y[0]) / s }
+        } else if x[0] > y[1] && x[0] > z[2] {
+            let s = (1.0 + x[0] - y[1] - z[2]).sqrt() * 2.0;
+            Quat { w: (y[2] - z[1]) / s, x: s / 4.0, y: (y[0] + x[1]) / s, z: (z[0] + x[2]) / s }
+        } else if y[1] > z[2] {
+            let s = (1.0 + y[1] - x[0] - z[2]).sqrt() * 2.0;
+            Quat { w: (z[0] - x[2]) / s, x: (y[0] + x[1]) / s, y: s / 4.0, z: (z[1] + y[2]) / s }
+        } else {
+            let s = (1.0 + z[2] - x[0] - y[1]).sqrt() * 2.0;
+            Quat { w: (x[1] - y[0]) / s, x: (z[0] + x[2]) / s, y: (z[1] + y[2]) / s, z: s / 4.0 }
+        };
+        quat.normalize()
+    }
+
+    fn dot(self, rhs: Quat) -> f32 {
+        self.w * rhs.w + self.x * rhs.x + self.y * rhs.y + self.z * rhs.z
+    }
+
+    fn normalize(self) -> Quat {
+        let length = self.dot(self).sqrt();
+        if length > 0.0 {
+            Quat {
+                w: self.w / length,
+                x: self.x / length,
+                y: self.y / length,
+                z: self.z / length,
+            }
+        } else {
+            Quat::IDENTITY
+        }
+    }
+
+    pub(crate) fn conjugate(self) -> Quat {
+        Quat {
+            w: self.w,
+            x: -self.x,
+            y: -self.y,
+            z: -self.z,
+        }
+    }
+
+    //q and -q are the same rotation; blending towards the nearer one takes the short way around.
+    fn nearest(self, rhs: Quat) -> Quat {
+        if self.dot(rhs) < 0.0 {
+            Quat {
+                w: -rhs.w,
+                x: -rhs.x,
+                y: -rhs.y,
+                z: -rhs.z,
+            }
+        } else {
+            rhs
+        }
+    }
+
+    //normalized linear interpolation: cheap, but not at constant angular speed.
+    pub(crate) fn nlerp(self, rhs: Quat, t: f32) -> Quat {
+        let rhs = self.nearest(rhs);
+        Quat {
+            w: self.w + (rhs.w - self.w) * t,
+            x: self.x + (rhs.x - self.x) * t,
+            y: self.y + (rhs.y - self.y) * t,
+            z: self.z + (rhs.z - self.z) * t,
+        }
+        .normalize()
+    }
+
+    //spherical linear interpolation, at constant angular speed along the shortest arc.
+    pub(crate) fn slerp(self, rhs: Quat, t: f32) -> Quat {
+        let rhs = self.nearest(rhs);
+        let cos = self.dot(rhs).min(1.0);
+        //nearly identical rotations divide by almost zero below.
+        if cos > 0.9995 {
+            return self.nlerp(rhs, t);
+        }
+        let angle = cos.acos();
+        let (a, b) = (((1.0 - t) * angle).sin(), (t * angle).sin());
+        let sin = angle.sin();
+        Quat {
+            w: (self.w * a + rhs.w * b) / sin,
+            x: (self.x * a + rhs.x * b) / sin,
+            y: (self.y * a + rhs.y * b) / sin,
+            z: (self.z * a + rhs.z * b) / sin,
+        }
+        .normalize()
+    }
+
+    //counter-clockwise by `angle` radians around the unit vector `axis`.
+    pub(crate) fn from_axis_angle(axis: Vec3, angle: f32) -> Quat {
+        let (sin, cos) = (angle / 2.0).sin_cos();
+        Quat::new(cos, axis[0] * sin, axis[1] * sin, axis[2] * sin)
+    }
+
+    pub(crate) fn rotate(self, v: Vec3) -> Vec3 {
+        //v + 2w(q x v) + 2q x (q x v), for the vector part q.
+        let q = [self.x, self.y, self.z];
+        let t = scale(cross(q, v), 2.0);
+        add(add(v, scale(t, self.w)), cross(q, t))
+    }
+}
+
+//the rotation by rhs, then by self.
+impl Mul for Quat {
+    type Output = Quat;
+
+    fn mul(self, rhs: Quat) -> Quat {
+        Quat {
+            w: self.w * rhs.w - self.x * rhs.x - self.y * rhs.y - self.z * rhs.z,
+            x: self.w * rhs.x + self.x * rhs.w + self.y * rhs.z - self.z * rhs.y,
+            y: self.w * rhs.y - self.x * rhs.z + self.y * rhs.w + self.z * rhs.x,
+            z: self.w * rhs.z + self.x * rhs.y - self.y * rhs.x + self.z * rhs.w,
+        }
+        .normalize()
+    }
+}
+
+//where something is and which way it faces: scaled first, then rotated, then moved.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub(crate) struct Pose {
+    pub(crate) position: Vec3,
+    pub(crate) rotation: Quat,
+    pub(crate) scale: f32,
+}
+
+impl Pose {
+    pub(crate) const IDENTITY: Pose = Pose {
+        position: [0.0; 3],
+        rotation: Quat::IDENTITY,
+        scale: 1.0,
+    };
+
+    pub(crate) fn apply(&self, v: Vec3) -> Vec3 {
+        add(self.rotation.rotate(scale(v, self.scale)), self.position)
+    }
+
+    //the pose of `child` placed relative to this one: the child's transform, then this one's.
+    pub(crate) fn compose(&self, child: &Pose) -> Pose {
+        Pose {
+            position: self.apply(child.position),
+            rotation: self.rotation * child.rotation,
+            scale: self.scale * child.scale,
+        }
+    }
+
+    //the pose that undoes this one.
+    fn inverse(&self) -> Pose {
+        let rotation = self.rotation.conjugate();
+        Pose {
+            position: scale(rotation.rotate(self.position), -1.0 / self.scale),
+            rotation,
+            scale: 1.0 / self.scale,
+        }
+    }
+
+    //world space into the space of a camera with this pose.
+    fn inverse_apply(&self, v: Vec3) -> Vec3 {
+        self.rotation.conjugate().rotate(sub(v, self.position))
+    }
+}
+
+//how view space is flattened onto the window.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Projection {
+    //the half height of the view, in world units.
+    Orthographic(f32),
+    //the vertical field of view, in radians.
+    Perspective(f32),
+}
+
+//a camera that looks down its -z axis. The default one sees exactly what is rendered without a
+//camera: the [-1,1] cube, orthographically, from +z.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub(crate) struct Camera {
+    pub(crate) pose: Pose,
+    pub(crate) projection: Projection,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Camera {
+            pose: Pose {
+                position: [0.0, 0.0, 2.0],
+                ..Pose::IDENTITY
+            },
+            projection: Projection::Orthographic(1.0),
+        }
+    }
+}
+
+//in perspective, triangles closer to the camera than this fraction of the distance to the world
+//origin are dropped rather than clipped.
+const NEAR: f32 = 0.01;
+
+//the vertex stage of one placed instance: from the mesh's own space, through its model pose and
+//the camera, into the window coordinates that rasterize() consumes. Instances of a mesh share its
+//vertex data and differ only in their ModelView. Depth is relative to the plane through the
+//world origin facing the camera, which lies at z = 0, in the units that the window's height
+//spans 2 of, or in perspective, 2 of at the camera's distance from the origin.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub(crate) struct ModelView {
+    //mesh space into the camera's view space.
+    model_view: Pose,
+    projection: Projection,
+    //the distance, along the view direction, to the origin; models are centered there.
+    focus: f32,
+    //the camera's distance from the origin, or 1, the extent of the fitted models, for cameras
+    //at the origin. Perspective measures depth and the near plane by it, as the focus is 0 for
+    //cameras looking past the origin.
+    reach: f32,
+    half_height: f32,
+}
+
+impl ModelView {
+    pub(crate) fn new(camera: &Camera, model: &Pose) -> ModelView {
+        let origin = camera.pose.inverse_apply([0.0; 3]);
+        let reach = match dot(origin, origin).sqrt() {
+            distance if distance > 0.0 => distance,
+            _ => 1.0,
+        };
+        let half_height = match camera.projection {
+            Projection::Orthographic(half_height) => half_height,
+            Projection::Perspective(fov) => reach * (fov / 2.0).tan(),
+        };
+        ModelView {
+            model_view: camera.pose.inverse().compose(model),
+            projection: camera.projection,
+            focus: -origin[2],
+            reach,
+            half_height,
+        }
+    }
+
+    //the window coordinates of a vertex, or None when perspective puts it too close to, or
+    //behind, the camera.
+    pub(crate) fn position(&self, v: Vec3) -> Option<Vec3> {
+        let view = self.model_view.apply(v);
+        let z = (view[2] + self.focus) / self.half_height;
+        match self.projection {
+            Projection::Orthographic(_) => Some([view[0] / self.half_height, view[1] / self.half_height, z]),
+            Projection::Perspective(_) if -view[2] <= NEAR * self.reach => None,
+            Projection::Perspective(fov) => {
+                let extent = -view[2] * (fov / 2.0).tan();
+                Some([view[0] / extent, view[1] / extent, z])
+            }
+        }
+    }
+
+    //how many window units a length of the mesh's spans at vertex `v`, or None where position()
+    //is None. Perspective shrinks lengths with their distance.
+    pub(crate) fn scale_at(&self, v: Vec3) -> Option<f32> {
+        let view = self.model_view.apply(v);
+        let scale = self.model_view.scale;
+        match self.projection {
+            Projection::Orthographic(_) => Some(scale / self.half_height),
+            Projection::Perspective(_) if -view[2] <= NEAR * self.reach => None,
+            Projection::Perspective(fov) => Some(scale / (-view[2] * (fov / 2.0).tan())),
+        }
+    }
+
+    //what perspective divides vertex `v` by, its distance in front of the camera, or 1 when
+    //orthographic. Weights found in the window are turned back into weights on the mesh with it.
+    pub(crate) fn distance(&self, v: Vec3) -> f32 {
+        match self.projection {
+            Projection::Orthographic(_) => 1.0,
+            Projection::Perspective(_) => -self.model_view.apply(v)[2],
+        }
+    }
+
+    //a normal, turned into view space.
+    pub(crate) fn normal(&self, n: Vec3) -> Vec3 {
+        self.model_view.rotation.rotate(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::camera::{Camera, ModelView, Pose, Projection, Quat};
+    use std::f32::consts::{FRAC_1_SQRT_2, FRAC_PI_2, FRAC_PI_4};
+
+    fn close(l: &[f32], r: &[f32]) -> bool {
+        l.iter().zip(r).all(|(l, r)| (l - r).abs() < 1e-5)
+    }
+
+    #[test]
+    fn look_at_faces_target() {
+        let rotation = Quat::look_at([2.0, 0.0, 0.0], [0.0; 3]);
+        //the camera's -z axis points back at the origin, and its y axis stays up.
+        assert!(close(&rotation.rotate([0.0, 0.0, -1.0]), &[-1.0, 0.0, 0.0]));
+        assert!(close(&rotation.rotate([0.0, 1.0, 0.0]), &[0.0, 1.0, 0.0]));
+        let down = Quat::look_at([0.0, 3.0, 0.0], [0.0; 3]);
+        assert!(close(&down.rotate([0.0, 0.0, -1.0]), &[0.0, -1.0, 0.0]));
+    }
+
+    #[test]
+    fn slerp_halfway() {
+        let a = Quat::IDENTITY;
+        let b = Quat::new(FRAC_PI_4.cos(), 0.0, FRAC_PI_4.sin(), 0.0); //90 degrees about y
+        let half = a.slerp(b, 0.5);
+        assert!(close(&half.rotate([1.0, 0.0, 0.0]), &[FRAC_1_SQRT_2, 0.0, -FRAC_1_SQRT_2]));
+        assert_eq!(a.slerp(b, 0.0), a);
+    }
+
+    #[test]
+    fn default_camera_changes_nothing() {
+        let view = ModelView::new(&Camera::default(), &Pose::IDENTITY);
+        for v in [[0.5, -0.25, 1.0], [-1.0, 0.0, -0.5], [0.0, 1.0, 0.0]] {
+            assert!(close(&view.position(v).unwrap(), &v));
+        }
+    }
+
+    #[test]
+    fn perspective_shrinks_and_culls() {
+        let camera = Camera {
+            projection: Projection::Perspective(FRAC_PI_2),
+            ..Camera::default()
+        };
+        let view = ModelView::new(&camera, &Pose::IDENTITY);
+        //the origin plane spans twice the focus distance; a point twice as far away is half as big.
+        assert!(close(&view.position([1.0, 0.0, 0.0]).unwrap(), &[0.5, 0.0, 0.0]));
+        assert!(close(&view.position([1.0, 0.0, -2.0]).unwrap(), &[0.25, 0.0, -1.0]));
+        assert_eq!(view.position([0.0, 0.0, 3.0]), None);
+        //and lengths there shrink alike.
+        assert!(close(&[view.scale_at([1.0, 0.0, 0.0]).unwrap()], &[0.5]));
+        assert!(close(&[view.scale_at([1.0, 0.0, -2.0]).unwrap()], &[0.25]));
+        assert_eq!(view.scale_at([0.0, 0.0, 3.0]), None);
+        assert!(close(&[view.distance([1.0, 0.0, -2.0])], &[4.0]));
+        assert_eq!(ModelView::new(&Camera::default(), &Pose::IDENTITY).distance([1.0, 0.0, -2.0]), 1.0);
+    }
+
+    #[test]
+    fn perspective_depth_past_the_origin() {
+        //looking along x from above the origin, whose plane then runs through the camera.
+        let camera = Camera {
+            pose: Pose {
+                position: [0.0, 2.0, 0.0],
+                rotation: Quat::look_at([0.0, 2.0, 0.0], [1.0, 2.0, 0.0]),
+                ..Pose::IDENTITY
+            },
+            projection: Projection::Perspective(FRAC_PI_2),
+        };
+        let view = ModelView::new(&camera, &Pose::IDENTITY);
+        let near = view.position([1.0, 2.0, 0.0]).unwrap();
+        let far = view.position([3.0, 2.0, 0.0]).unwrap();
+        assert!(near.iter().chain(&far).all(|c| c.is_finite()));
+        assert!(close(&[near[2], far[2]], &[-0.5, -1.5]));
+        //and cameras at the origin measure depth by the fitted models' extent.
+        let camera = Camera {
+            pose: Pose::IDENTITY,
+            ..camera
+        };
+        let view = ModelView::new(&camera, &Pose::IDENTITY);
+        assert!(close(&view.position([0.0, 0.0, -2.0]).unwrap(), &[0.0, 0.0, -2.0]));
+    }
+
+    #[test]
+    fn poses_compose_parent_last() {
+        let parent = Pose {
+            position: [1.0, 0.0, 0.0],
+            rotation: Quat::from_axis_angle([0.0, 0.0, 1.0], FRAC_PI_2),
+            scale: 2.0,
+        };
+        let child = Pose {
+            position: [1.0, 0.0, 0.0],
+            ..Pose::IDENTITY
+        };
+        let world = parent.compose(&child);
+        let v = [0.0, 1.0, 0.0];
+        assert!(close(&world.apply(v), &parent.apply(child.apply(v))));
+        assert!(close(&world.apply(v), &[-1.0, 2.0, 0.0]));
+        let view = ModelView::new(&Camera::default(), &world);
+        assert!(close(&view.normal([1.0, 0.0, 0.0]), &[0.0, 1.0, 0.0]));
+    }
+}
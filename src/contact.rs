@@ -0,0 +1,155 @@
+use crate::camera::{Camera, Pose, Projection, Quat};
+use std::f32::consts::{FRAC_PI_2, PI};
+
+//the views of a contact sheet, in reading order: three across, two down.
+pub(crate) const COLUMNS: u64 = 3;
+pub(crate) const ROWS: u64 = 2;
+
+//a little room around the [-1,1] cube in the axis views, and around its silhouette from a corner.
+const MARGIN: f32 = 1.1;
+const ISOMETRIC_HALF_HEIGHT: f32 = 1.75;
+
+//the labelled orthographic views of an engineering review, each looking at the origin.
+pub(crate) fn views() -> [(&'static str, Camera); 6] {
+    let axis_view = |position: [f32; 3], axis: [f32; 3], angle: f32| Camera {
+        pose: Pose {
+            position,
+            rotation: Quat::from_axis_angle(axis, angle),
+            scale: 1.0,
+        },
+        projection: Projection::Orthographic(MARGIN),
+    };
+    let corner = [3.0f32.sqrt(); 3];
+    [
+        ("FRONT", axis_view([0.0, 0.0, 3.0], [0.0, 1.0, 0.0], 0.0)),
+        ("BACK", axis_view([0.0, 0.0, -3.0], [0.0, 1.0, 0.0], PI)),
+        ("LEFT", axis_view([-3.0, 0.0, 0.0], [0.0, 1.0, 0.0], -FRAC_PI_2)),
+        ("RIGHT", axis_view([3.0, 0.0, 0.0], [0.0, 1.0, 0.0], FRAC_PI_2)),
+        ("TOP", axis_view([0.0, 3.0, 0.0], [1.0, 0.0, 0.0], -FRAC_PI_2)),
+        (
+            "ISOMETRIC",
+            Camera {
+                pose: Pose {
+                    position: corner,
+                    rotation: Quat::look_at(corner, [0.0; 3]),
+                    scale: 1.0,
+                },
+                projection: Projection::Orthographic(ISOMETRIC_HALF_HEIGHT),
+            },
+        ),
+    ]
+}
+
+//the (x, y, width, height) pixel rectangles of the views, which tile the whole image.
+pub(crate) fn cells(width: u64, height: u64) -> Vec<(u64, u64, u64, u64)> {
+    (0..ROWS)
+        .flat_map(|row| (0..COLUMNS).map(move |column| (column, row)))
+        .map(|(column, row)| {
+            let (x, y) = (column * width / COLUMNS, row * height / ROWS);
+            let (right, bottom) = ((column + 1) * width / COLUMNS, (row + 1) * height / ROWS);
+            (x, y, right - x, bottom - y)
+        })
+        .collect()
+}
+
+//draws `text` into a row-major RGBA image with its top left corner at (x, y), in a 5x7 pixel
+//font magnified `scale` times, over a shadow that keeps it legible on any background.
+pub(crate) fn draw_label(data: &mut [u8], width: usize, (x, y): (usize, usize), text: &str, scale: usize) {
+    let height = data.len() / 4 / width;
+    let mut plot = |px: usize, py: usize, color: [u8; 4]| {
+        if px < width && py < height {
+            data[(py * width + px) * 4..][..4].copy_from_slice(&color);
+        }
+    };
+    for (shadow, color) in [(scale, [0, 0, 0, 255]), (0, [255, 255, 255, 255])] {
+        for (i, c) in text.chars().enumerate() {
+            for (row, bits) in glyph(c).iter().enumerate() {
+                for column in (0..5).filter(|column| bits & (0b10000 >> column) != 0) {
+                    let left = x + (i * 6 + column) * scale + shadow;
+                    let top = y + row * scale + shadow;
+                    for (dx, dy) in (0..scale).flat_map(|dx| (0..scale).map(move |dy| (dx, dy))) {
+                        plot(left + dx, top + dy, color);
+                    }
+                }
+            }
+        }
+    }
+}
+
+//the rows of an upper case letter, top first, with the leftmost of 5 columns in bit 4.
+//anything else is blank.
+fn glyph(c: char) -> [u8; 7] {
+    match c.to_ascii_uppercase() {
+        'A' => [0b01110, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001],
+        'B' => [0b11110, 0b10001, 0b10001, 0b11110, 0b10001, 0b10001, 0b11110],
+        'C' => [0b01110, 0b10001, 0b10000, 0b10000, 0b10000, 0b10001, 0b01110],
+        'D' => [0b11100, 0b10010, 0b10001, 0b10001, 0b10001, 0b10010, 0b11100],
+        'E' => [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b11111],
+        'F' => [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b10000],
+        'G' => [0b01110, 0b10001, 0b10000, 0b10111, 0b10001, 0b10001, 0b01111],
+        'H' => [0b10001, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001],
+        'I' => [0b01110, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110],
+        'J' => [0b00111, 0b00010, 0b00010, 0b00010, 0b00010, 0b10010, 0b01100],
+        'K' => [0b10001, 0b10010, 0b10100, 0b11000, 0b10100, 0b10010, 0b10001],
+        'L' => [0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b11111],
+        'M' => [0b10001, 0b11011, 0b10101, 0b10101, 0b10001, 0b10001, 0b10001],
+        'N' => [0b10001, 0b10001, 0b11001, 0b10101, 0b10011, 0b10001, 0b10001],
+        'O' => [0b01110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110],
+        'P' => [0b11110, 0b10001, 0b10001, 0b11110, 0b10000, 0b10000, 0b10000],
+        'Q' => [0b01110, 0b10001, 0b10001, 0b10001, 0b10101, 0b10010, 0b01101],
+        'R' => [0b11110, 0b10001, 0b10001, 0b11110, 0b10100, 0b10010, 0b10001],
+        'S' => [0b01111, 0b10000, 0b10000, 0b01110, 0b00001, 0b00001, 0b11110],
+        'T' => [0b11111, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100],
+        'U' => [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110],
+        'V' => [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01010, 0b00100],
+        'W' => [0b10001, 0b10001, 0b10001, 0b10101, 0b10101, 0b10101, 0b01010],
+        'X' => [0b10001, 0b10001, 0b01010, 0b00100, 0b01010, 0b10001, 0b10001],
+        'Y' => [0b10001, 0b10001, 0b01010, 0b00100, 0b00100, 0b00100, 0b00100],
+        'Z' => [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b11111],
+        _ => [0; 7],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::camera::{Camera, ModelView, Pose};
+    use crate::contact::{cells, draw_label, views};
+
+    fn close(l: &[f32], r: &[f32]) -> bool {
+        l.iter().zip(r).all(|(l, r)| (l - r).abs() < 1e-5)
+    }
+
+    #[test]
+    fn views_face_the_origin() {
+        for (label, camera) in views() {
+            let forward = camera.pose.rotation.rotate([0.0, 0.0, -1.0]);
+            let to_origin = camera.pose.position.map(|p| -p / 3.0);
+            assert!(close(&forward, &to_origin), "{label}");
+        }
+        //the top view keeps +x to the right, and the front of the model at the bottom.
+        let top = ModelView::new(&views()[4].1, &Pose::IDENTITY);
+        let [x, y, _] = top.position([1.0, 0.0, 1.0]).unwrap();
+        assert!(x > 0.0 && y < 0.0);
+        assert_eq!(views()[0].1.pose.rotation, Camera::default().pose.rotation);
+    }
+
+    #[test]
+    fn cells_tile_the_image() {
+        let cells = cells(100, 51);
+        assert_eq!(cells[0], (0, 0, 33, 25));
+        assert_eq!(cells[5], (66, 25, 34, 26));
+        let area: u64 = cells.iter().map(|(_, _, w, h)| w * h).sum();
+        assert_eq!(area, 100 * 51);
+    }
+
+    #[test]
+    fn labels_are_drawn_with_shadows() {
+        let mut data = vec![0; 8 * 9 * 4];
+        draw_label(&mut data, 8, (1, 1), "i", 1);
+        let pixel = |x: usize, y: usize| data[(y * 8 + x) * 4];
+        //the top bar of the I, from its second to fourth column, then its stem.
+        assert_eq!((pixel(1, 1), pixel(2, 1), pixel(4, 1), pixel(5, 1)), (0, 255, 255, 0));
+        assert_eq!(pixel(3, 4), 255);
+        assert_eq!(data[(8 * 8 + 4) * 4 + 3], 255, "shadow below the stem");
+    }
+}
@@ -0,0 +1,174 @@
+use crate::encode::write_png;
+use crate::framebuffer::FrameBuffer;
+use std::io::{self, Write};
+
+//how the z-buffer is turned into the output image.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum DepthFormat {
+    Rgba,
+    Gray16,
+    Colormap(Colormap),
+}
+
+impl DepthFormat {
+    pub(crate) fn from_name(name: &str) -> Option<DepthFormat> {
+        match name {
+            "rgba" => Some(DepthFormat::Rgba),
+            "gray16" => Some(DepthFormat::Gray16),
+            "viridis" => Some(DepthFormat::Colormap(Colormap::Viridis)),
+            "turbo" => Some(DepthFormat::Colormap(Colormap::Turbo)),
+            "magma" => Some(DepthFormat::Colormap(Colormap::Magma)),
+            _ => None,
+        }
+    }
+}
+
+//perceptual colormaps, evaluated with polynomial fits of the reference tables.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Colormap {
+    Viridis,
+    Turbo,
+    Magma,
+}
+
+impl Colormap {
+    //maps t in [0,1] to a linear RGB color in [0,1].
+    pub(crate) fn sample(self, t: f32) -> [f32; 3] {
+        let t = t.clamp(0.0, 1.0);
+        let rgb = match self {
+            Colormap::Viridis => sextic(&VIRIDIS, t),
+            Colormap::Magma => sextic(&MAGMA, t),
+            Colormap::Turbo => {
+                let powers = [1.0, t, t * t, t * t * t, t.powi(4), t.powi(5)];
+                TURBO.map(|channel| channel.iter().zip(powers).map(|(c, p)| c * p).sum())
+            }
+        };
+        rgb.map(|c: f32| c.clamp(0.0, 1.0))
+    }
+}
+
+//coefficients c0..c6 of a polynomial per channel, by ascending power of t.
+const VIRIDIS: [[f32; 3]; 7] = [
+    [0.277_727_33, 0.005_407_344_5, 0.334_099_8],
+    [0.105_093_04, 1.404_613_5, 1.384_590_2],
+    [-0.330_861_83, 0.214_847_56, 0.095_095_16],
+    [-4.634_230_6, -5.799_101, -19.332_441],
+    [6.228_27, 14.179_933, 56.690_55],
+    [4.776_385, -13.745_145, -65.353_035],
+    [-5.435_456, 4.645_852_6, 26.312_435],
+];
+const MAGMA: [[f32; 3]; 7] = [
+    [-0.002_136_485, -0.000_749_655_05, -0.005_386_128],
+    [0.251_660_54, 0.677_523_2, 2.494_026_6],
+    [8.353_717, -3.577_719_5, 0.314_467_9],
+    [-27.668_733, 14.264_731, -13.649_213],
+    [52.176_14, -27.943_606, 12.944_169],
+    [-50.768_524, 29.046_583, 4.234_153],
+    [18.655_705, -11.489_774, -5.601_961_5],
+];
+//per channel, coefficients of t^0..t^5.
+const TURBO: [[f32; 6]; 3] = [
+    [0.135_721_38, 4.615_392_6, -42.660_324, 132.131_08, -152.942_4, 59.286_38],
+    [0.091_402_61, 2.194_188_4, 4.842_966_6, -14.185_033, 4.277_298_6, 2.829_566],
+    [0.106_673_3, 12.641_946, -60.582_047, 110.362_77, -89.903_11, 27.348_25],
+];
+
+fn sextic(coefficients: &[[f32; 3]; 7], t: f32) -> [f32; 3] {
+    //Horner's method, from the highest power down.
+    coefficients
+        .iter()
+        .rev()
+        .fold([0.0; 3], |acc, c| [0, 1, 2].map(|i| acc[i] * t + c[i]))
+}
+
+//maps every z-buffer value to [0,1] between near and far, which default to the closest and
+//farthest covered pixels. Uncovered pixels count as far. Inverting makes near pixels bright.
+pub(crate) fn normalize(frame: &FrameBuffer, range: Option<(f32, f32)>, invert: bool) -> Vec<f32> {
+    let (near, far) = range.unwrap_or_else(|| {
+        frame
+            .covered_depth()
+            .flatten()
+            .fold((f32::MAX, -f32::MAX), |(near, far), z| (near.min(z), far.max(z)))
+    });
+    let extent = far - near;
+    frame
+        .covered_depth()
+        .map(|z| {
+            let t = match z {
+                None => 1.0,
+                Some(z) if extent > 0.0 => ((z - near) / extent).clamp(0.0, 1.0),
+                Some(_) => 0.0, //a flat surface, or a single covered pixel.
+            };
+            if invert {
+                1.0 - t
+            } else {
+                t
+            }
+        })
+        .collect()
+}
+
+//writes normalized depth as a 16-bit grayscale PNG, or as an 8-bit RGB PNG through a colormap.
+pub(crate) fn write_depth_map<W: Write>(
+    w: W,
+    format: DepthFormat,
+    frame: &FrameBuffer,
+    range: Option<(f32, f32)>,
+    invert: bool,
+) -> io::Result<()> {
+    let width = u32::try_from(frame.depth.num_rows()).map_err(io::Error::other)?;
+    let height = u32::try_from(frame.depth.num_columns()).map_err(io::Error::other)?;
+    let samples = normalize(frame, range, invert);
+    match format {
+        DepthFormat::Gray16 => write_png(w, width, height, 1, 16, &samples),
+        DepthFormat::Colormap(colormap) => {
+            let rgb: Vec<f32> = samples.iter().flat_map(|&t| colormap.sample(t)).collect();
+            write_png(w, width, height, 3, 8, &rgb)
+        }
+        DepthFormat::Rgba => Err(io::Error::other("rgba is not a depth map format")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::depthmap::{normalize, Colormap};
+    use crate::framebuffer::FrameBuffer;
+
+    fn frame(depth: &[Option<f32>]) -> FrameBuffer {
+        let mut frame = FrameBuffer::new(depth.len(), 1, false);
+        for (x, z) in depth.iter().enumerate() {
+            if let Some(z) = z {
+                frame.depth[(x, 0)] = *z;
+                frame.coverage[(x, 0)] = true;
+            }
+        }
+        frame
+    }
+
+    fn close(l: [f32; 3], r: [f32; 3]) -> bool {
+        l.iter().zip(r).all(|(l, r)| (l - r).abs() < 0.02)
+    }
+
+    #[test]
+    fn colormap_endpoints() {
+        assert!(close(Colormap::Viridis.sample(0.0), [0.267, 0.005, 0.329]));
+        assert!(close(Colormap::Viridis.sample(1.0), [0.993, 0.906, 0.144]));
+        assert!(close(Colormap::Magma.sample(0.0), [0.0, 0.0, 0.016]));
+        assert!(close(Colormap::Magma.sample(1.0), [0.987, 0.991, 0.749]));
+        assert!(close(Colormap::Turbo.sample(0.0), [0.136, 0.091, 0.107]));
+        assert!(close(Colormap::Turbo.sample(2.0), Colormap::Turbo.sample(1.0)));
+    }
+
+    #[test]
+    fn automatic_range() {
+        let frame = frame(&[Some(-0.5), Some(0.0), Some(0.5), None]);
+        assert_eq!(normalize(&frame, None, false), vec![0.0, 0.5, 1.0, 1.0]);
+        assert_eq!(normalize(&frame, None, true), vec![1.0, 0.5, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn explicit_range_clamps() {
+        let frame = frame(&[Some(-1.0), Some(0.0), Some(1.0)]);
+        assert_eq!(normalize(&frame, Some((-0.5, 0.5)), false), vec![0.0, 0.5, 1.0]);
+    }
+}
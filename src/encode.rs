@@ -0,0 +1,181 @@
+use std::io::{self, Write};
+
+//writes row-major samples, `channels` (1 or 3) per pixel and each in the range [0,1],
+//as a grayscale or RGB PNG with 8 or 16 bits per channel.
+pub(crate) fn write_png<W: Write>(
+    w: W,
+    width: u32,
+    height: u32,
+    channels: usize,
+    bits: u8,
+    samples: &[f32],
+) -> io::Result<()> {
+    let mut encoder = png::Encoder::new(w, width, height);
+    encoder.set_color(if channels == 1 {
+        png::ColorType::Grayscale
+    } else {
+        png::ColorType::Rgb
+    });
+    #[allow(clippy::cast_possible_truncation)]
+    #[allow(clippy::cast_sign_loss)]
+    let data: Vec<u8> = if bits == 16 {
+        encoder.set_depth(png::BitDepth::Sixteen);
+        samples
+            .iter()
+            .flat_map(|s| ((s.clamp(0.0, 1.0) * 65535.0).round() as u16).to_be_bytes())
+            .collect()
+    } else {
+        encoder.set_depth(png::BitDepth::Eight);
+        samples
+            .iter()
+            .map(|s| (s.clamp(0.0, 1.0) * 255.0).round() as u8)
+            .collect()
+    };
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&data)?;
+    Ok(())
+}
+
+//writes row-major samples, `channels` (1 or 3) per pixel, as a little-endian portable float map.
+//PFM stores rows bottom to top, so rows are reversed to keep the orientation of the PNG outputs.
+pub(crate) fn write_pfm<W: Write>(
+    mut w: W,
+    width: u32,
+    height: u32,
+    channels: usize,
+    samples: &[f32],
+) -> io::Result<()> {
+    let magic = if channels == 1 { "Pf" } else { "PF" };
+    //a negative scale marks little-endian data.
+    write!(w, "{magic}\n{width} {height}\n-1.0\n")?;
+    let row_len = width as usize * channels;
+    for row in samples.chunks_exact(row_len).rev() {
+        let bytes: Vec<u8> = row.iter().flat_map(|s| s.to_le_bytes()).collect();
+        w.write_all(&bytes)?;
+    }
+    w.flush()
+}
+
+//writes row-major samples, `channels` (1 or 3) per pixel, as a single-part, uncompressed,
+//scanline OpenEXR image with 32-bit float channels "Z" or "R", "G" and "B".
+pub(crate) fn write_exr<W: Write>(
+    mut w: W,
+    width: u32,
+    height: u32,
+    channels: usize,
+    samples: &[f32],
+) -> io::Result<()> {
+    //channels are stored in alphabetical order, both in the header and in every scanline.
+    let names: &[(&str, usize)] = if channels == 1 {
+        &[("Z", 0)]
+    } else {
+        &[("B", 2), ("G", 1), ("R", 0)]
+    };
+    let (max_x, max_y) = (width as i32 - 1, height as i32 - 1);
+
+    let mut header = vec![];
+    header.extend_from_slice(&[0x76, 0x2f, 0x31, 0x01]); //magic number
+    header.extend_from_slice(&2u32.to_le_bytes()); //version 2, single-part scanline
+    let mut channel_list = vec![];
+    for (name, _) in names {
+        channel_list.extend_from_slice(name.as_bytes());
+        channel_list.push(0);
+        channel_list.extend_from_slice(&2i32.to_le_bytes()); //pixel type FLOAT
+        channel_list.extend_from_slice(&[0, 0, 0, 0]); //pLinear and reserved
+        channel_list.extend_from_slice(&1i32.to_le_bytes()); //x sampling
+        channel_list.extend_from_slice(&1i32.to_le_bytes()); //y sampling
+    }
+    channel_list.push(0);
+    let window: Vec<u8> = [0, 0, max_x, max_y]
+        .iter()
+        .flat_map(|v| v.to_le_bytes())
+        .collect();
+    let mut attribute = |name: &str, kind: &str, value: &[u8]| {
+        header.extend_from_slice(name.as_bytes());
+        header.push(0);
+        header.extend_from_slice(kind.as_bytes());
+        header.push(0);
+        header.extend_from_slice(&(value.len() as u32).to_le_bytes());
+        header.extend_from_slice(value);
+    };
+    attribute("channels", "chlist", &channel_list);
+    attribute("compression", "compression", &[0]); //NO_COMPRESSION
+    attribute("dataWindow", "box2i", &window);
+    attribute("displayWindow", "box2i", &window);
+    attribute("lineOrder", "lineOrder", &[0]); //INCREASING_Y
+    attribute("pixelAspectRatio", "float", &1.0f32.to_le_bytes());
+    attribute("screenWindowCenter", "v2f", &[0; 8]);
+    attribute("screenWindowWidth", "float", &1.0f32.to_le_bytes());
+    header.push(0); //end of header
+
+    //uncompressed files hold one scanline per chunk, located through an offset table.
+    let row_len = width as usize * channels;
+    let chunk_len = 8 + width as u64 * channels as u64 * 4;
+    let table_end = header.len() as u64 + u64::from(height) * 8;
+    w.write_all(&header)?;
+    for y in 0..u64::from(height) {
+        w.write_all(&(table_end + y * chunk_len).to_le_bytes())?;
+    }
+    for (y, row) in samples.chunks_exact(row_len).enumerate() {
+        w.write_all(&(y as i32).to_le_bytes())?;
+        w.write_all(&((chunk_len - 8) as u32).to_le_bytes())?;
+        for &(_, offset) in names {
+            let bytes: Vec<u8> = row
+                .iter()
+                .skip(offset)
+                .step_by(channels)
+                .flat_map(|s| s.to_le_bytes())
+                .collect();
+            w.write_all(&bytes)?;
+        }
+    }
+    w.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::encode::{write_exr, write_pfm, write_png};
+
+    #[test]
+    fn pfm_header_and_row_order() {
+        let mut out = vec![];
+        write_pfm(&mut out, 1, 2, 1, &[1.0, 2.0]).unwrap();
+        let header = b"Pf\n1 2\n-1.0\n";
+        assert_eq!(&out[..header.len()], header);
+        assert_eq!(&out[header.len()..header.len() + 4], &2.0f32.to_le_bytes());
+        assert_eq!(&out[header.len() + 4..], &1.0f32.to_le_bytes());
+    }
+
+    #[test]
+    fn exr_chunks_follow_offset_table() {
+        let mut out = vec![];
+        write_exr(&mut out, 2, 2, 3, &(0..12).map(|i| i as f32).collect::<Vec<f32>>()).unwrap();
+        assert_eq!(&out[..4], &[0x76, 0x2f, 0x31, 0x01]);
+        //each chunk is y, a byte count, then B, G and R for both pixels of the scanline.
+        let chunk_len = 8 + 2 * 3 * 4;
+        let first = out.len() - 2 * chunk_len;
+        let table: Vec<usize> = out[first - 16..first]
+            .chunks_exact(8)
+            .map(|b| u64::from_le_bytes(b.try_into().unwrap()) as usize)
+            .collect();
+        assert_eq!(table, vec![first, first + chunk_len]);
+        let second = &out[first + chunk_len..];
+        assert_eq!(&second[..4], &1i32.to_le_bytes());
+        let floats: Vec<f32> = second[8..]
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes(b.try_into().unwrap()))
+            .collect();
+        assert_eq!(floats, vec![8.0, 11.0, 7.0, 10.0, 6.0, 9.0]);
+    }
+
+    #[test]
+    fn png_decodes_to_quantized_samples() {
+        let mut out = vec![];
+        write_png(&mut out, 2, 1, 1, 16, &[0.0, 1.0]).unwrap();
+        let decoder = png::Decoder::new(out.as_slice());
+        let mut reader = decoder.read_info().unwrap();
+        let mut buf = vec![0; reader.output_buffer_size()];
+        reader.next_frame(&mut buf).unwrap();
+        assert_eq!(buf, vec![0, 0, 255, 255]);
+    }
+}
@@ -0,0 +1,120 @@
+use crate::io::ArgsError;
+use std::fmt;
+use std::io;
+
+//everything that can stop a render, with the file it happened in. Errors in the text of scene and
+//camera path files name their line.
+#[derive(Debug)]
+pub enum RenderError {
+    //the command line could not be understood.
+    Arguments(ArgsError),
+    //a mesh file could not be opened or parsed.
+    Mesh { file: String, error: io::Error },
+    //a scene, camera path or background image could not be read.
+    Input { file: String, error: io::Error },
+    //the view volume could not be mapped onto the image.
+    Geometry(&'static str),
+    //an image, animation, index or directory could not be written.
+    Output { file: String, error: io::Error },
+    //some meshes of a batch failed, after the rest were rendered.
+    Batch { failed: usize, total: usize },
+    //--validate found problems in a mesh, and reported them.
+    Invalid { file: String, problems: usize },
+}
+
+impl RenderError {
+    //the process exit code of each class of error, so that scripts can tell them apart.
+    //panics, which are bugs, exit with Rust's 101; a hook exiting otherwise would also end the
+    //batches that catch them.
+    pub(crate) fn exit_code(&self) -> u8 {
+        match self {
+            RenderError::Arguments(_) => 2,
+            RenderError::Mesh { .. } => 3,
+            RenderError::Input { .. } => 4,
+            RenderError::Geometry(_) => 5,
+            RenderError::Output { .. } => 6,
+            RenderError::Batch { .. } => 7,
+            RenderError::Invalid { .. } => 8,
+        }
+    }
+
+    //an Input error for `file`.
+    pub(crate) fn input(file: &str) -> impl FnOnce(io::Error) -> RenderError + '_ {
+        move |error| RenderError::Input {
+            file: String::from(file),
+            error,
+        }
+    }
+
+    //an Output error for `file`.
+    pub(crate) fn output(file: &str) -> impl FnOnce(io::Error) -> RenderError + '_ {
+        move |error| RenderError::Output {
+            file: String::from(file),
+            error,
+        }
+    }
+}
+
+impl fmt::Display for RenderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RenderError::Arguments(e) => write!(f, "An error occurred during command line parsing: {e}"),
+            RenderError::Mesh { file, error } => write!(f, "could not load the mesh {file}: {error}"),
+            RenderError::Input { file, error } => write!(f, "could not read {file}: {error}"),
+            RenderError::Geometry(reason) => write!(f, "could not map the view onto the image: {reason}"),
+            RenderError::Output { file, error } => write!(f, "could not write {file}: {error}"),
+            RenderError::Batch { failed, total } => write!(f, "{failed} of {total} meshes failed"),
+            RenderError::Invalid { file, problems } => write!(f, "{file} has {problems} kinds of problems"),
+        }
+    }
+}
+
+impl std::error::Error for RenderError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RenderError::Arguments(e) => Some(e),
+            RenderError::Mesh { error, .. } | RenderError::Input { error, .. } | RenderError::Output { error, .. } => {
+                Some(error)
+            }
+            RenderError::Geometry(_) | RenderError::Batch { .. } | RenderError::Invalid { .. } => None,
+        }
+    }
+}
+
+impl From<ArgsError> for RenderError {
+    fn from(e: ArgsError) -> Self {
+        RenderError::Arguments(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::RenderError;
+    use crate::io::ArgsError;
+    use std::collections::HashSet;
+    use std::io;
+
+    #[test]
+    fn classes_exit_differently() {
+        let errors = [
+            RenderError::Arguments(ArgsError::MissingValue("--width")),
+            RenderError::Mesh {
+                file: String::from("a.obj"),
+                error: io::Error::new(io::ErrorKind::InvalidData, "line 2: expected x y z"),
+            },
+            RenderError::input("a.toml")(io::Error::other("line 3: bad")),
+            RenderError::Geometry("the viewport is empty"),
+            RenderError::output("a.png")(io::Error::other("disk full")),
+            RenderError::Batch { failed: 1, total: 2 },
+            RenderError::Invalid {
+                file: String::from("a.obj"),
+                problems: 3,
+            },
+        ];
+        let codes: HashSet<u8> = errors.iter().map(RenderError::exit_code).collect();
+        assert_eq!(codes.len(), errors.len());
+        assert!(!codes.contains(&0) && !codes.contains(&1));
+        assert_eq!(errors[2].to_string(), "could not read a.toml: line 3: bad");
+        assert_eq!(errors[1].to_string(), "could not load the mesh a.obj: line 2: expected x y z");
+    }
+}
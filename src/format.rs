@@ -0,0 +1,336 @@
+use std::io::{self, Write};
+use std::path::Path;
+
+//encoders for the 8-bit RGBA image. Every writer takes the same row-major RGBA bytes,
+//with the first row at the top of the written image.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum ImageFormat {
+    Png,
+    Ppm,
+    Pam,
+    Tga,
+    Bmp,
+    Qoi,
+}
+
+impl ImageFormat {
+    pub(crate) fn from_name(name: &str) -> Option<ImageFormat> {
+        match name.to_ascii_lowercase().as_str() {
+            "png" => Some(ImageFormat::Png),
+            "ppm" => Some(ImageFormat::Ppm),
+            "pam" => Some(ImageFormat::Pam),
+            "tga" => Some(ImageFormat::Tga),
+            "bmp" => Some(ImageFormat::Bmp),
+            "qoi" => Some(ImageFormat::Qoi),
+            _ => None,
+        }
+    }
+
+    //picks the format from a file's extension.
+    pub(crate) fn from_path(path: &str) -> Option<ImageFormat> {
+        Path::new(path)
+            .extension()
+            .and_then(|e| e.to_str())
+            .and_then(ImageFormat::from_name)
+    }
+
+    //the usual file extension, without the dot.
+    pub(crate) fn extension(self) -> &'static str {
+        match self {
+            ImageFormat::Png => "png",
+            ImageFormat::Ppm => "ppm",
+            ImageFormat::Pam => "pam",
+            ImageFormat::Tga => "tga",
+            ImageFormat::Bmp => "bmp",
+            ImageFormat::Qoi => "qoi",
+        }
+    }
+
+    pub(crate) fn write_rgba<W: Write>(
+        self,
+        w: W,
+        width: u32,
+        height: u32,
+        data: &[u8],
+    ) -> io::Result<()> {
+        match self {
+            ImageFormat::Png => write_png(w, width, height, data),
+            ImageFormat::Ppm => write_ppm(w, width, height, data),
+            ImageFormat::Pam => write_pam(w, width, height, data),
+            ImageFormat::Tga => write_tga(w, width, height, data),
+            ImageFormat::Bmp => write_bmp(w, width, height, data),
+            ImageFormat::Qoi => write_qoi(w, width, height, data),
+        }
+    }
+}
+
+fn write_png<W: Write>(w: W, width: u32, height: u32, data: &[u8]) -> io::Result<()> {
+    let mut writer = png_encoder(w, width, height).write_header()?;
+    writer.write_image_data(data)?;
+    Ok(())
+}
+
+//writes an RGBA PNG from bands of whole rows, top first, compressing each band as it arrives
+//so that the image is never held in memory at once.
+pub(crate) fn write_png_bands<W: Write>(
+    w: W,
+    width: u32,
+    height: u32,
+    bands: impl Iterator<Item = io::Result<Vec<u8>>>,
+) -> io::Result<()> {
+    let mut writer = png_encoder(w, width, height).write_header()?;
+    let mut stream = writer.stream_writer()?;
+    for band in bands {
+        stream.write_all(&band?)?;
+    }
+    //fails unless the bands added up to the whole image.
+    stream.finish()?;
+    Ok(())
+}
+
+fn png_encoder<W: Write>(w: W, width: u32, height: u32) -> png::Encoder<'static, W> {
+    let mut encoder = png::Encoder::new(w, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_source_gamma(png::ScaledFloat::from_scaled(45455)); // 1.0 / 2.2, scaled by 100000
+    encoder.set_source_gamma(png::ScaledFloat::new(1.0 / 2.2)); // 1.0 / 2.2, unscaled, but rounded
+    let source_chromaticities = png::SourceChromaticities::new(
+        // Using unscaled instantiation here
+        (0.31270, 0.32900),
+        (0.64000, 0.33000),
+        (0.30000, 0.60000),
+        (0.15000, 0.06000),
+    );
+    encoder.set_source_chromaticities(source_chromaticities);
+    encoder
+}
+
+//binary portable pixmap. It has no alpha channel, so alpha is dropped.
+fn write_ppm<W: Write>(mut w: W, width: u32, height: u32, data: &[u8]) -> io::Result<()> {
+    write!(w, "P6\n{width} {height}\n255\n")?;
+    let rgb: Vec<u8> = data.chunks_exact(4).flat_map(|p| [p[0], p[1], p[2]]).collect();
+    w.write_all(&rgb)?;
+    w.flush()
+}
+
+//portable arbitrary map, which keeps alpha.
+fn write_pam<W: Write>(mut w: W, width: u32, height: u32, data: &[u8]) -> io::Result<()> {
+    write!(
+        w,
+        "P7\nWIDTH {width}\nHEIGHT {height}\nDEPTH 4\nMAXVAL 255\nTUPLTYPE RGB_ALPHA\nENDHDR\n"
+    )?;
+    w.write_all(data)?;
+    w.flush()
+}
+
+fn to_u16(value: u32) -> io::Result<[u8; 2]> {
+    u16::try_from(value)
+        .map(u16::to_le_bytes)
+        .map_err(|_| io::Error::other(format!("{value} is too large for the image format")))
+}
+
+//uncompressed 32-bit truecolor Targa.
+fn write_tga<W: Write>(mut w: W, width: u32, height: u32, data: &[u8]) -> io::Result<()> {
+    let mut header = [0u8; 18];
+    header[2] = 2; //uncompressed truecolor
+    header[12..14].copy_from_slice(&to_u16(width)?);
+    header[14..16].copy_from_slice(&to_u16(height)?);
+    header[16] = 32; //bits per pixel
+    header[17] = 0x28; //top-left origin, 8 bits of alpha
+    w.write_all(&header)?;
+    let bgra: Vec<u8> = data
+        .chunks_exact(4)
+        .flat_map(|p| [p[2], p[1], p[0], p[3]])
+        .collect();
+    w.write_all(&bgra)?;
+    w.flush()
+}
+
+//32-bit bottom-up Windows bitmap with a BITMAPINFOHEADER. Readers that predate alpha treat
+//the fourth byte as padding.
+fn write_bmp<W: Write>(mut w: W, width: u32, height: u32, data: &[u8]) -> io::Result<()> {
+    const HEADERS_LEN: u32 = 14 + 40;
+    let image_len = width
+        .checked_mul(height)
+        .and_then(|pixels| pixels.checked_mul(4))
+        .ok_or_else(|| io::Error::other("the image is too large for a bitmap"))?;
+    let mut header = vec![];
+    header.extend_from_slice(b"BM");
+    header.extend_from_slice(&(HEADERS_LEN + image_len).to_le_bytes());
+    header.extend_from_slice(&[0; 4]); //reserved
+    header.extend_from_slice(&HEADERS_LEN.to_le_bytes()); //pixel data offset
+    header.extend_from_slice(&40u32.to_le_bytes()); //info header size
+    header.extend_from_slice(&width.to_le_bytes());
+    header.extend_from_slice(&height.to_le_bytes()); //positive: rows are stored bottom-up
+    header.extend_from_slice(&1u16.to_le_bytes()); //planes
+    header.extend_from_slice(&32u16.to_le_bytes()); //bits per pixel
+    header.extend_from_slice(&0u32.to_le_bytes()); //BI_RGB, uncompressed
+    header.extend_from_slice(&image_len.to_le_bytes());
+    header.extend_from_slice(&2835u32.to_le_bytes()); //72 dpi, in pixels per meter
+    header.extend_from_slice(&2835u32.to_le_bytes());
+    header.extend_from_slice(&[0; 8]); //palette sizes
+    w.write_all(&header)?;
+    //32-bit rows are always a multiple of 4 bytes, so no row padding is needed.
+    for row in data.chunks_exact(width as usize * 4).rev() {
+        let bgra: Vec<u8> = row
+            .chunks_exact(4)
+            .flat_map(|p| [p[2], p[1], p[0], p[3]])
+            .collect();
+        w.write_all(&bgra)?;
+    }
+    w.flush()
+}
+
+//the "Quite OK Image" format, https://qoiformat.org/qoi-specification.pdf
+fn write_qoi<W: Write>(mut w: W, width: u32, height: u32, data: &[u8]) -> io::Result<()> {
+    const OP_INDEX: u8 = 0x00;
+    const OP_DIFF: u8 = 0x40;
+    const OP_LUMA: u8 = 0x80;
+    const OP_RUN: u8 = 0xc0;
+    const OP_RGB: u8 = 0xfe;
+    const OP_RGBA: u8 = 0xff;
+
+    let mut out = Vec::with_capacity(14 + data.len() / 2);
+    out.extend_from_slice(b"qoif");
+    out.extend_from_slice(&width.to_be_bytes());
+    out.extend_from_slice(&height.to_be_bytes());
+    out.extend_from_slice(&[4, 0]); //RGBA, sRGB with linear alpha
+
+    let mut seen = [[0u8; 4]; 64];
+    let mut previous = [0u8, 0, 0, 255];
+    let mut run = 0u8;
+    for pixel in data.chunks_exact(4) {
+        let pixel = [pixel[0], pixel[1], pixel[2], pixel[3]];
+        if pixel == previous {
+            run += 1;
+            //runs of 63 and 64 would collide with OP_RGB and OP_RGBA.
+            if run == 62 {
+                out.push(OP_RUN | (run - 1));
+                run = 0;
+            }
+            continue;
+        }
+        if run > 0 {
+            out.push(OP_RUN | (run - 1));
+            run = 0;
+        }
+        let [r, g, b, a] = pixel;
+        let hash = (usize::from(r) * 3 + usize::from(g) * 5 + usize::from(b) * 7 + usize::from(a) * 11) % 64;
+        if seen[hash] == pixel {
+            out.push(OP_INDEX | hash as u8);
+        } else {
+            seen[hash] = pixel;
+            if a == previous[3] {
+                let dr = r.wrapping_sub(previous[0]) as i8;
+                let dg = g.wrapping_sub(previous[1]) as i8;
+                let db = b.wrapping_sub(previous[2]) as i8;
+                let dr_dg = dr.wrapping_sub(dg);
+                let db_dg = db.wrapping_sub(dg);
+                #[allow(clippy::cast_sign_loss)]
+                if [dr, dg, db].iter().all(|d| (-2..=1).contains(d)) {
+                    out.push(OP_DIFF | ((dr + 2) as u8) << 4 | ((dg + 2) as u8) << 2 | (db + 2) as u8);
+                } else if (-32..=31).contains(&dg)
+                    && (-8..=7).contains(&dr_dg)
+                    && (-8..=7).contains(&db_dg)
+                {
+                    out.push(OP_LUMA | (dg + 32) as u8);
+                    out.push(((dr_dg + 8) as u8) << 4 | (db_dg + 8) as u8);
+                } else {
+                    out.extend_from_slice(&[OP_RGB, r, g, b]);
+                }
+            } else {
+                out.extend_from_slice(&[OP_RGBA, r, g, b, a]);
+            }
+        }
+        previous = pixel;
+    }
+    if run > 0 {
+        out.push(OP_RUN | (run - 1));
+    }
+    out.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 1]); //end marker
+    w.write_all(&out)?;
+    w.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::format::{write_png_bands, ImageFormat};
+
+    //two rows: red, then transparent green.
+    const DATA: [u8; 8] = [255, 0, 0, 255, 0, 255, 0, 0];
+
+    fn encode(format: ImageFormat) -> Vec<u8> {
+        let mut out = vec![];
+        format.write_rgba(&mut out, 1, 2, &DATA).unwrap();
+        out
+    }
+
+    #[test]
+    fn format_from_path() {
+        assert_eq!(ImageFormat::from_path("out.TGA"), Some(ImageFormat::Tga));
+        assert_eq!(ImageFormat::from_path("dir.qoi/out"), None);
+        assert_eq!(ImageFormat::from_path("out.jpg"), None);
+    }
+
+    #[test]
+    fn ppm_drops_alpha() {
+        assert_eq!(encode(ImageFormat::Ppm), b"P6\n1 2\n255\n\xff\x00\x00\x00\xff\x00");
+    }
+
+    #[test]
+    fn pam_keeps_alpha() {
+        let out = encode(ImageFormat::Pam);
+        assert!(out.starts_with(b"P7\nWIDTH 1\nHEIGHT 2\nDEPTH 4\n"));
+        assert!(out.ends_with(&DATA));
+    }
+
+    #[test]
+    fn tga_is_top_down_bgra() {
+        let out = encode(ImageFormat::Tga);
+        assert_eq!(out.len(), 18 + 8);
+        assert_eq!(&out[18..], &[0, 0, 255, 255, 0, 255, 0, 0]);
+    }
+
+    #[test]
+    fn bmp_is_bottom_up_bgra() {
+        let out = encode(ImageFormat::Bmp);
+        assert_eq!(&out[..2], b"BM");
+        assert_eq!(u32::from_le_bytes(out[2..6].try_into().unwrap()) as usize, out.len());
+        assert_eq!(&out[54..], &[0, 255, 0, 0, 0, 0, 255, 255]);
+    }
+
+    #[test]
+    fn png_round_trip() {
+        let out = encode(ImageFormat::Png);
+        let mut reader = png::Decoder::new(out.as_slice()).read_info().unwrap();
+        let mut buf = vec![0; reader.output_buffer_size()];
+        reader.next_frame(&mut buf).unwrap();
+        assert_eq!(buf, DATA);
+    }
+
+    #[test]
+    fn png_bands_decode_as_one_image() {
+        let mut out = vec![];
+        let bands = [Ok(DATA[..4].to_vec()), Ok(DATA[4..].to_vec())];
+        write_png_bands(&mut out, 1, 2, bands.into_iter()).unwrap();
+        let mut reader = png::Decoder::new(out.as_slice()).read_info().unwrap();
+        let mut buf = vec![0; reader.output_buffer_size()];
+        reader.next_frame(&mut buf).unwrap();
+        assert_eq!(buf, DATA);
+        //a missing band leaves the image incomplete.
+        let bands = [Ok(DATA[..4].to_vec())];
+        assert!(write_png_bands(&mut vec![], 1, 2, bands.into_iter()).is_err());
+    }
+
+    #[test]
+    fn qoi_runs_and_end_marker() {
+        let mut out = vec![];
+        let data = [7u8, 8, 9, 255].repeat(100);
+        ImageFormat::Qoi.write_rgba(&mut out, 10, 10, &data).unwrap();
+        assert_eq!(&out[..4], b"qoif");
+        //the first pixel as a difference in luma from black, then runs of at most 62 for the other 99.
+        assert_eq!(&out[14..16], &[0x80 | (8 + 32), (7 << 4) | 9]);
+        assert_eq!(&out[16..18], &[0xc0 | 61, 0xc0 | 36]);
+        assert_eq!(&out[18..], &[0, 0, 0, 0, 0, 0, 0, 1]);
+    }
+}
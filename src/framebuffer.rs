@@ -0,0 +1,196 @@
+use crate::camera::{normalize, Pose};
+use crate::encode::{write_exr, write_pfm, write_png};
+use crate::gbuffer::{GBuffer, Layer};
+use array2d::Array2D;
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::path::Path;
+
+//everything rasterize() writes per pixel: the z-buffer, which pixels any fragment reached,
+//which object the closest fragment belongs to, and optionally the g-buffer.
+#[derive(Debug)]
+pub(crate) struct FrameBuffer {
+    pub(crate) depth: Array2D<f32>,
+    pub(crate) coverage: Array2D<bool>,
+    pub(crate) objects: Array2D<usize>,
+    //the object that rasterized fragments are attributed to.
+    pub(crate) object: usize,
+    pub(crate) gbuffer: Option<GBuffer>,
+}
+
+impl FrameBuffer {
+    pub(crate) fn new(width: usize, height: usize, with_gbuffer: bool) -> Self {
+        FrameBuffer {
+            depth: Array2D::filled_with(f32::MAX, width, height),
+            coverage: Array2D::filled_with(false, width, height),
+            objects: Array2D::filled_with(0, width, height),
+            object: 0,
+            gbuffer: with_gbuffer.then(|| GBuffer::new(width, height)),
+        }
+    }
+
+    //resets every pixel to the state new() leaves it in, reusing the buffers for another frame.
+    pub(crate) fn clear(&mut self) {
+        for x in 0..self.depth.num_rows() {
+            for y in 0..self.depth.num_columns() {
+                self.depth[(x, y)] = f32::MAX;
+                self.coverage[(x, y)] = false;
+                self.objects[(x, y)] = 0;
+            }
+        }
+        self.object = 0;
+        if let Some(gbuffer) = &mut self.gbuffer {
+            gbuffer.clear();
+        }
+    }
+
+    //row-major depth of every pixel, or None where no fragment was written.
+    pub(crate) fn covered_depth(&self) -> impl Iterator<Item = Option<f32>> + '_ {
+        self.depth
+            .elements_column_major_iter()
+            .zip(self.coverage.elements_column_major_iter())
+            .map(|(&z, &covered)| covered.then_some(z))
+    }
+
+    //row-major object of every pixel, or None where no fragment was written.
+    pub(crate) fn covered_objects(&self) -> impl Iterator<Item = Option<usize>> + '_ {
+        self.objects
+            .elements_column_major_iter()
+            .zip(self.coverage.elements_column_major_iter())
+            .map(|(&object, &covered)| covered.then_some(object))
+    }
+
+    //flattens a layer into row-major samples, `layer.channels()` per pixel, in the ranges
+    //[-1,1] for normals and window positions and [0,1] for texture coordinates. World layers
+    //take each object's mesh through its pose in `poses`, and are left at 0 where no fragment
+    //was written. Depth is the raw z-buffer value, with pixels that no fragment reached set to
+    //`background`.
+    pub(crate) fn samples(&self, layer: Layer, background: f32, poses: &[Pose]) -> io::Result<Vec<f32>> {
+        if layer == Layer::Depth {
+            return Ok(self
+                .covered_depth()
+                .map(|z| z.unwrap_or(background))
+                .collect());
+        }
+        let gbuffer = self
+            .gbuffer
+            .as_ref()
+            .ok_or_else(|| io::Error::other(format!("{layer:?} was not rasterized")))?;
+        Ok(match layer {
+            Layer::Normal => gbuffer.normal.elements_column_major_iter().flatten().copied().collect(),
+            Layer::Position => gbuffer
+                .position
+                .elements_column_major_iter()
+                .flatten()
+                .copied()
+                .collect(),
+            Layer::WorldNormal | Layer::WorldPosition => {
+                let normals = layer == Layer::WorldNormal;
+                let attribute = if normals { &gbuffer.mesh_normal } else { &gbuffer.mesh_position };
+                attribute
+                    .elements_column_major_iter()
+                    .zip(self.covered_objects())
+                    .flat_map(|(&value, object)| {
+                        let Some(object) = object else {
+                            return [0.0; 3];
+                        };
+                        let pose = poses.get(object).unwrap_or(&Pose::IDENTITY);
+                        if normals {
+                            normalize(pose.rotation.rotate(value))
+                        } else {
+                            pose.apply(value)
+                        }
+                    })
+                    .collect()
+            }
+            Layer::Uv => gbuffer
+                .uv
+                .elements_column_major_iter()
+                .flat_map(|&[u, v]| [u, v, 0.0])
+                .collect(),
+            Layer::Depth => unreachable!("depth does not live in the g-buffer"),
+        })
+    }
+
+    //writes a layer to path: exact floats for .pfm and .exr files, otherwise a PNG with `bits` per
+    //channel, remapping every layer except texture coordinates from [-1,1] to [0,1].
+    pub(crate) fn export(
+        &self,
+        layer: Layer,
+        path: &str,
+        bits: u8,
+        background: f32,
+        poses: &[Pose],
+    ) -> io::Result<()> {
+        let width = u32::try_from(self.depth.num_rows()).map_err(io::Error::other)?;
+        let height = u32::try_from(self.depth.num_columns()).map_err(io::Error::other)?;
+        let mut samples = self.samples(layer, background, poses)?;
+        let w = BufWriter::new(File::create(path)?);
+        match Path::new(path).extension().and_then(|e| e.to_str()) {
+            Some("pfm") => write_pfm(w, width, height, layer.channels(), &samples),
+            Some("exr") => write_exr(w, width, height, layer.channels(), &samples),
+            _ => {
+                if layer != Layer::Uv {
+                    samples.iter_mut().for_each(|s| *s = (*s + 1.0) / 2.0);
+                }
+                write_png(w, width, height, layer.channels(), bits, &samples)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::camera::{Pose, Quat};
+    use crate::framebuffer::FrameBuffer;
+    use crate::gbuffer::Layer;
+    use std::f32::consts::FRAC_PI_2;
+
+    #[test]
+    fn depth_background() {
+        let mut frame = FrameBuffer::new(2, 1, false);
+        frame.depth[(1, 0)] = 0.5;
+        frame.coverage[(1, 0)] = true;
+        let samples = frame.samples(Layer::Depth, f32::INFINITY, &[]).unwrap();
+        assert_eq!(samples, vec![f32::INFINITY, 0.5]);
+        assert!(frame.samples(Layer::Normal, 0.0, &[]).is_err());
+    }
+
+    #[test]
+    fn clear_resets_pixels() {
+        let mut frame = FrameBuffer::new(2, 1, false);
+        frame.depth[(1, 0)] = 0.5;
+        frame.coverage[(1, 0)] = true;
+        frame.clear();
+        assert!(frame.covered_depth().all(|z| z.is_none()));
+        assert_eq!(frame.depth[(1, 0)], f32::MAX);
+    }
+
+    #[test]
+    fn uv_samples_are_padded_to_rgb() {
+        let mut frame = FrameBuffer::new(2, 1, true);
+        frame.gbuffer.as_mut().unwrap().uv[(1, 0)] = [0.5, 0.25];
+        let samples = frame.samples(Layer::Uv, 0.0, &[]).unwrap();
+        assert_eq!(samples, vec![0.0, 0.0, 0.0, 0.5, 0.25, 0.0]);
+    }
+
+    #[test]
+    fn world_layers_take_each_object_through_its_pose() {
+        let mut frame = FrameBuffer::new(2, 1, true);
+        let gbuffer = frame.gbuffer.as_mut().unwrap();
+        gbuffer.mesh_position[(1, 0)] = [1.0, 0.0, 0.0];
+        gbuffer.mesh_normal[(1, 0)] = [0.0, 1.0, 0.0];
+        frame.coverage[(1, 0)] = true;
+        frame.objects[(1, 0)] = 1;
+        let pose = Pose {
+            position: [1.0, 2.0, 3.0],
+            rotation: Quat::from_axis_angle([0.0, 0.0, 1.0], FRAC_PI_2),
+            scale: 2.0,
+        };
+        let close = |l: &[f32], r: &[f32]| l.iter().zip(r).all(|(l, r)| (l - r).abs() < 1e-5);
+        let positions = frame.samples(Layer::WorldPosition, 0.0, &[Pose::IDENTITY, pose]).unwrap();
+        assert!(close(&positions, &[0.0, 0.0, 0.0, 1.0, 4.0, 3.0]), "{positions:?}");
+        let normals = frame.samples(Layer::WorldNormal, 0.0, &[Pose::IDENTITY, pose]).unwrap();
+        assert!(close(&normals, &[0.0, 0.0, 0.0, -1.0, 0.0, 0.0]), "{normals:?}");
+    }
+}
@@ -0,0 +1,251 @@
+use array2d::Array2D;
+use tobj::Mesh;
+
+//an auxiliary image that can be written alongside the beauty image.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Layer {
+    //in the camera's view space.
+    Normal,
+    //in window space.
+    Position,
+    //in the space the mesh file has its models in.
+    WorldNormal,
+    WorldPosition,
+    Uv,
+    Depth,
+}
+
+impl Layer {
+    pub(crate) fn from_name(name: &str) -> Option<Layer> {
+        match name {
+            "normal" => Some(Layer::Normal),
+            "position" => Some(Layer::Position),
+            "world-normal" => Some(Layer::WorldNormal),
+            "world-position" => Some(Layer::WorldPosition),
+            "uv" => Some(Layer::Uv),
+            "depth" => Some(Layer::Depth),
+            _ => None,
+        }
+    }
+    //number of channels per pixel when the layer is encoded.
+    pub(crate) fn channels(self) -> usize {
+        match self {
+            Layer::Normal | Layer::Position | Layer::WorldNormal | Layer::WorldPosition | Layer::Uv => 3,
+            Layer::Depth => 1,
+        }
+    }
+}
+
+//per-pixel surface attributes of the closest fragment, written alongside the z-buffer.
+//uncovered pixels keep zeroed attributes.
+#[derive(Debug)]
+pub(crate) struct GBuffer {
+    pub(crate) normal: Array2D<[f32; 3]>,
+    pub(crate) position: Array2D<[f32; 3]>,
+    //the normal and position in the mesh's own space, before the vertex stage.
+    pub(crate) mesh_normal: Array2D<[f32; 3]>,
+    pub(crate) mesh_position: Array2D<[f32; 3]>,
+    pub(crate) uv: Array2D<[f32; 2]>,
+    //RGB in [0, 1], for fragments of meshes with vertex colors.
+    pub(crate) color: Array2D<Option<[f32; 3]>>,
+    //how opaque the fragment is: below 1 towards the edges of Gaussian splats, 1 otherwise.
+    pub(crate) weight: Array2D<f32>,
+}
+
+impl GBuffer {
+    pub(crate) fn new(width: usize, height: usize) -> Self {
+        GBuffer {
+            normal: Array2D::filled_with([0.0; 3], width, height),
+            position: Array2D::filled_with([0.0; 3], width, height),
+            mesh_normal: Array2D::filled_with([0.0; 3], width, height),
+            mesh_position: Array2D::filled_with([0.0; 3], width, height),
+            uv: Array2D::filled_with([0.0; 2], width, height),
+            color: Array2D::filled_with(None, width, height),
+            weight: Array2D::filled_with(1.0, width, height),
+        }
+    }
+
+    //zeroes every attribute, as new() leaves them.
+    pub(crate) fn clear(&mut self) {
+        for x in 0..self.normal.num_rows() {
+            for y in 0..self.normal.num_columns() {
+                self.normal[(x, y)] = [0.0; 3];
+                self.position[(x, y)] = [0.0; 3];
+                self.mesh_normal[(x, y)] = [0.0; 3];
+                self.mesh_position[(x, y)] = [0.0; 3];
+                self.uv[(x, y)] = [0.0; 2];
+                self.color[(x, y)] = None;
+                self.weight[(x, y)] = 1.0;
+            }
+        }
+    }
+
+    //overwrites the attributes at (x, y) with those of surface, weighted by barycentric coordinates.
+    pub(crate) fn write(&mut self, x: usize, y: usize, surface: &Surface, bary: [f32; 3]) {
+        if let Some(normal) = self.normal.get_mut(x, y) {
+            *normal = normalize(interpolate(&surface.normals, bary));
+        }
+        if let Some(position) = self.position.get_mut(x, y) {
+            *position = interpolate(&surface.positions, bary);
+        }
+        if let Some(normal) = self.mesh_normal.get_mut(x, y) {
+            *normal = normalize(interpolate(&surface.mesh_normals, bary));
+        }
+        if let Some(position) = self.mesh_position.get_mut(x, y) {
+            *position = interpolate(&surface.mesh_positions, bary);
+        }
+        if let Some(uv) = self.uv.get_mut(x, y) {
+            *uv = interpolate(&surface.uvs, bary);
+        }
+        if let Some(color) = self.color.get_mut(x, y) {
+            *color = surface.colors.map(|colors| interpolate(&colors, bary));
+        }
+        if let Some(weight) = self.weight.get_mut(x, y) {
+            *weight = 1.0;
+        }
+    }
+}
+
+//vertex attributes of one triangle, gathered once and interpolated for each covered pixel.
+#[derive(Debug)]
+pub(crate) struct Surface {
+    positions: [[f32; 3]; 3],
+    normals: [[f32; 3]; 3],
+    //as the mesh has them, however the surface is placed.
+    mesh_positions: [[f32; 3]; 3],
+    mesh_normals: [[f32; 3]; 3],
+    uvs: [[f32; 2]; 3],
+    colors: Option<[[f32; 3]; 3]>,
+}
+
+impl Surface {
+    //index holds the triangle's three indices into the mesh's (single-indexed) vertex data.
+    pub(crate) fn new(mesh: &Mesh, index: &[u32]) -> Self {
+        let vec3 = |data: &[f32], i: u32| {
+            let i = i as usize * 3;
+            [data[i], data[i + 1], data[i + 2]]
+        };
+        let positions = [0, 1, 2].map(|v| vec3(&mesh.positions, index[v]));
+        //meshes without normals are shaded flat.
+        let normals = if mesh.normals.is_empty() {
+            [face_normal(&positions); 3]
+        } else {
+            [0, 1, 2].map(|v| vec3(&mesh.normals, index[v]))
+        };
+        let uvs = if mesh.texcoords.is_empty() {
+            [[0.0; 2]; 3]
+        } else {
+            [0, 1, 2].map(|v| {
+                let i = index[v] as usize * 2;
+                [mesh.texcoords[i], mesh.texcoords[i + 1]]
+            })
+        };
+        //colors are per vertex, like positions, or left out.
+        let colors = (mesh.vertex_color.len() == mesh.positions.len())
+            .then(|| [0, 1, 2].map(|v| vec3(&mesh.vertex_color, index[v])));
+        Surface {
+            positions,
+            normals,
+            mesh_positions: positions,
+            mesh_normals: normals,
+            uvs,
+            colors,
+        }
+    }
+
+    //the surface as the vertex stage placed it: at its window positions, with normals turned.
+    //Lines and points without normals have none to turn, and face the camera.
+    pub(crate) fn place(self, positions: [[f32; 3]; 3], turn: impl Fn([f32; 3]) -> [f32; 3]) -> Self {
+        Surface {
+            positions,
+            normals: self.normals.map(|n| if n == [0.0; 3] { [0.0, 0.0, 1.0] } else { turn(n) }),
+            ..self
+        }
+    }
+
+    //the surface in a single color, whether or not its mesh has vertex colors.
+    pub(crate) fn colored(self, color: [f32; 3]) -> Self {
+        Surface {
+            colors: Some([color; 3]),
+            ..self
+        }
+    }
+}
+
+fn interpolate<const N: usize>(values: &[[f32; N]; 3], bary: [f32; 3]) -> [f32; N] {
+    let mut result = [0.0; N];
+    for (value, weight) in values.iter().zip(bary) {
+        for (r, v) in result.iter_mut().zip(value) {
+            *r += v * weight;
+        }
+    }
+    result
+}
+
+fn normalize(v: [f32; 3]) -> [f32; 3] {
+    let length = v.iter().map(|c| c * c).sum::<f32>().sqrt();
+    if length == 0.0 {
+        v
+    } else {
+        v.map(|c| c / length)
+    }
+}
+
+//counter-clockwise winding faces towards the viewer.
+fn face_normal([a, b, c]: &[[f32; 3]; 3]) -> [f32; 3] {
+    let ab = [b[0] - a[0], b[1] - a[1], b[2] - a[2]];
+    let ac = [c[0] - a[0], c[1] - a[1], c[2] - a[2]];
+    normalize([
+        ab[1] * ac[2] - ab[2] * ac[1],
+        ab[2] * ac[0] - ab[0] * ac[2],
+        ab[0] * ac[1] - ab[1] * ac[0],
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::gbuffer::{face_normal, interpolate, GBuffer, Surface};
+    use tobj::Mesh;
+
+    fn triangle() -> Mesh {
+        Mesh {
+            positions: vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0],
+            texcoords: vec![0.0, 0.0, 1.0, 0.0, 0.0, 1.0],
+            indices: vec![0, 1, 2],
+            ..Mesh::default()
+        }
+    }
+
+    #[test]
+    fn flat_normal_without_mesh_normals() {
+        let mesh = triangle();
+        let surface = Surface::new(&mesh, &mesh.indices);
+        assert_eq!(surface.normals, [[0.0, 0.0, 1.0]; 3]);
+        assert_eq!(face_normal(&surface.positions), [0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn interpolated_attributes() {
+        let mesh = triangle();
+        let surface = Surface::new(&mesh, &mesh.indices);
+        assert_eq!(interpolate(&surface.uvs, [0.5, 0.25, 0.25]), [0.25, 0.25]);
+
+        let mut gbuffer = GBuffer::new(2, 1);
+        gbuffer.write(1, 0, &surface, [0.0, 0.5, 0.5]);
+        assert_eq!(gbuffer.position[(1, 0)], [0.5, 0.5, 0.0]);
+        assert_eq!(gbuffer.uv[(1, 0)], [0.5, 0.5]);
+        assert_eq!(gbuffer.uv[(0, 0)], [0.0, 0.0]);
+        assert_eq!(gbuffer.color[(1, 0)], None);
+    }
+
+    #[test]
+    fn interpolated_vertex_colors() {
+        let mesh = Mesh {
+            vertex_color: vec![1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0],
+            ..triangle()
+        };
+        let mut gbuffer = GBuffer::new(1, 1);
+        gbuffer.write(0, 0, &Surface::new(&mesh, &mesh.indices), [0.5, 0.25, 0.25]);
+        assert_eq!(gbuffer.color[(0, 0)], Some([0.5, 0.25, 0.25]));
+    }
+}
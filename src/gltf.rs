@@ -0,0 +1,716 @@
+use crate::material::{encode_srgb, Material, Texture};
+use crate::mesh::{invalid, OutOfRange};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+use tobj::{Mesh, Model};
+
+//the parts of a glTF 2.0 document that are rendered. Nodes place meshes, whose primitives read
+//their vertices through accessors, which are typed views into buffers.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct Document {
+    scene: Option<usize>,
+    scenes: Vec<Roots>,
+    nodes: Vec<Node>,
+    meshes: Vec<GltfMesh>,
+    accessors: Vec<Accessor>,
+    buffer_views: Vec<BufferView>,
+    buffers: Vec<Buffer>,
+    materials: Vec<GltfMaterial>,
+    textures: Vec<GltfTexture>,
+    images: Vec<Image>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Roots {
+    nodes: Vec<usize>,
+}
+
+//a node is placed by either a column-major matrix, or a translation, rotation and scale.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Node {
+    name: Option<String>,
+    children: Vec<usize>,
+    mesh: Option<usize>,
+    matrix: Option<[f32; 16]>,
+    translation: Option<[f32; 3]>,
+    //a unit quaternion, as x, y, z and w.
+    rotation: Option<[f32; 4]>,
+    scale: Option<[f32; 3]>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct GltfMesh {
+    name: Option<String>,
+    primitives: Vec<Primitive>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Primitive {
+    attributes: HashMap<String, usize>,
+    indices: Option<usize>,
+    material: Option<usize>,
+    //triangles when left out.
+    mode: Option<u32>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct Accessor {
+    buffer_view: Option<usize>,
+    byte_offset: usize,
+    component_type: u32,
+    normalized: bool,
+    count: usize,
+    #[serde(rename = "type")]
+    kind: String,
+    sparse: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct BufferView {
+    buffer: usize,
+    byte_offset: usize,
+    byte_length: usize,
+    byte_stride: Option<usize>,
+}
+
+//a buffer without a uri is the binary chunk of a GLB.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct Buffer {
+    uri: Option<String>,
+    byte_length: usize,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct GltfMaterial {
+    pbr_metallic_roughness: Pbr,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct Pbr {
+    base_color_factor: Option<[f32; 4]>,
+    base_color_texture: Option<TextureInfo>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct TextureInfo {
+    index: usize,
+    //which TEXCOORD_n attribute the texture is mapped by.
+    tex_coord: usize,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct GltfTexture {
+    source: Option<usize>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct Image {
+    uri: Option<String>,
+    buffer_view: Option<usize>,
+}
+
+const GLB_JSON: u32 = 0x4E4F_534A;
+const GLB_BIN: u32 = 0x004E_4942;
+//the most components an accessor without a buffer view may have, as nothing else bounds them.
+const MAX_ZEROS: usize = 1 << 24;
+
+//whether the file is a binary glTF.
+pub(crate) fn is_glb(bytes: &[u8]) -> bool {
+    bytes.starts_with(b"glTF")
+}
+
+//a .gltf document, or a .glb of the document and its binary buffer. Buffers and images may be
+//data URIs or files next to the document, which need a `directory`. Every primitive of every
+//mesh in the scene's node hierarchy becomes a model of triangles, lines or points, already placed by its nodes'
+//transforms, with any vertex colors, and materials become a base color and any PNG base color
+//texture. Faces with corners past their primitive's vertices are errors, or `dropped` with it,
+//numbered across all the primitives in the order they are placed.
+pub(crate) fn load(
+    bytes: &[u8],
+    directory: Option<&Path>,
+    mut dropped: Option<&mut Vec<OutOfRange>>,
+) -> io::Result<(Vec<Model>, Vec<Material>)> {
+    let (json, binary) = if is_glb(bytes) { glb_chunks(bytes)? } else { (bytes, None) };
+    let document: Document = serde_json::from_slice(json).map_err(|e| invalid(format!("not a glTF document: {e}")))?;
+    let buffers = document
+        .buffers
+        .iter()
+        .enumerate()
+        .map(|(i, buffer)| {
+            let data = match (&buffer.uri, binary) {
+                (Some(uri), _) => resolve(uri, directory)?,
+                (None, Some(binary)) if i == 0 => binary.to_vec(),
+                (None, _) => return Err(invalid(format!("buffer {i} has no data"))),
+            };
+            if data.len() < buffer.byte_length {
+                return Err(invalid(format!("buffer {i} is shorter than its {} bytes", buffer.byte_length)));
+            }
+            Ok(data)
+        })
+        .collect::<io::Result<Vec<_>>>()?;
+    let gltf = Gltf {
+        document: &document,
+        buffers,
+    };
+    let materials = document
+        .materials
+        .iter()
+        .map(|material| gltf.material(material, directory))
+        .collect::<io::Result<Vec<_>>>()?;
+    let mut models = vec![];
+    let mut faces = 0;
+    for (node, transform) in gltf.placed_nodes()? {
+        let Some(mesh) = document.nodes[node].mesh else {
+            continue;
+        };
+        let mesh = document.meshes.get(mesh).ok_or_else(|| invalid(format!("node {node} has no mesh {mesh}")))?;
+        let name = document.nodes[node].name.as_ref().or(mesh.name.as_ref());
+        for primitive in &mesh.primitives {
+            if let Some(model) = gltf.model(primitive, &transform, (&mut faces, dropped.as_deref_mut()))? {
+                let name = name.cloned().unwrap_or_else(|| String::from("unnamed_object"));
+                models.push(Model::new(model, name));
+            }
+        }
+    }
+    Ok((models, materials))
+}
+
+//the JSON chunk of a GLB and its binary chunk, if it has one: a header of "glTF", the version
+//and the length, then chunks of a length, a type and the data.
+fn glb_chunks(bytes: &[u8]) -> io::Result<(&[u8], Option<&[u8]>)> {
+    let word = |at: usize| {
+        bytes
+            .get(at..at + 4)
+            .map(|w| u32::from_le_bytes([w[0], w[1], w[2], w[3]]) as usize)
+            .ok_or_else(|| invalid(String::from("the GLB ends early")))
+    };
+    if word(4)? != 2 {
+        return Err(invalid(format!("GLB version {} is not 2", word(4)?)));
+    }
+    let end = word(8)?.min(bytes.len());
+    let mut chunks = vec![];
+    let mut at = 12;
+    while at + 8 <= end {
+        let (length, kind) = (word(at)?, word(at + 4)? as u32);
+        let data = bytes.get(at + 8..at + 8 + length).ok_or_else(|| invalid(String::from("the GLB ends early")))?;
+        chunks.push((kind, data));
+        at += 8 + length;
+    }
+    match chunks[..] {
+        [(GLB_JSON, json), (GLB_BIN, binary), ..] => Ok((json, Some(binary))),
+        [(GLB_JSON, json), ..] => Ok((json, None)),
+        _ => Err(invalid(String::from("the GLB does not start with a JSON chunk"))),
+    }
+}
+
+//the bytes of a data URI, or of a file relative to the document.
+fn resolve(uri: &str, directory: Option<&Path>) -> io::Result<Vec<u8>> {
+    if let Some(data) = uri.strip_prefix("data:") {
+        let (_, encoded) = data
+            .split_once(";base64,")
+            .ok_or_else(|| invalid(String::from("only base64 data URIs can be read")))?;
+        return decode_base64(encoded).ok_or_else(|| invalid(String::from("a data URI is not valid base64")));
+    }
+    match directory {
+        Some(directory) => std::fs::read(directory.join(uri.replace("%20", " ")))
+            .map_err(|e| io::Error::new(e.kind(), format!("{uri}: {e}"))),
+        None => Err(invalid(format!("{uri} cannot be found without the document's directory"))),
+    }
+}
+
+fn decode_base64(encoded: &str) -> Option<Vec<u8>> {
+    let value = |c: u8| match c {
+        b'A'..=b'Z' => Some(c - b'A'),
+        b'a'..=b'z' => Some(c - b'a' + 26),
+        b'0'..=b'9' => Some(c - b'0' + 52),
+        b'+' | b'-' => Some(62),
+        b'/' | b'_' => Some(63),
+        _ => None,
+    };
+    let digits = encoded.trim_end_matches('=').bytes().map(value).collect::<Option<Vec<_>>>()?;
+    let mut bytes = Vec::with_capacity(digits.len() * 3 / 4);
+    for group in digits.chunks(4) {
+        let bits = group.iter().enumerate().fold(0u32, |bits, (i, &d)| bits | u32::from(d) << (18 - 6 * i));
+        let [_, a, b, c] = bits.to_be_bytes();
+        bytes.extend_from_slice(&[a, b, c][..group.len().saturating_sub(1)]);
+    }
+    Some(bytes)
+}
+
+//a document with its buffers loaded.
+struct Gltf<'a> {
+    document: &'a Document,
+    buffers: Vec<Vec<u8>>,
+}
+
+impl Gltf<'_> {
+    //every node of the scene, or of every hierarchy when there are no scenes, with the transform
+    //that places it.
+    fn placed_nodes(&self) -> io::Result<Vec<(usize, [f32; 16])>> {
+        let nodes = &self.document.nodes;
+        let roots = match self.document.scenes.get(self.document.scene.unwrap_or(0)) {
+            Some(scene) => scene.nodes.clone(),
+            None => {
+                let children: Vec<usize> = nodes.iter().flat_map(|node| node.children.iter().copied()).collect();
+                (0..nodes.len()).filter(|node| !children.contains(node)).collect()
+            }
+        };
+        let mut placed = vec![];
+        let mut visited = vec![false; nodes.len()];
+        let mut stack: Vec<(usize, [f32; 16])> = roots.into_iter().rev().map(|root| (root, IDENTITY)).collect();
+        while let Some((index, parent)) = stack.pop() {
+            let node = nodes.get(index).ok_or_else(|| invalid(format!("there is no node {index}")))?;
+            if std::mem::replace(&mut visited[index], true) {
+                return Err(invalid(format!("node {index} has more than one parent")));
+            }
+            let transform = multiply(&parent, &local_transform(node));
+            stack.extend(node.children.iter().rev().map(|&child| (child, transform)));
+            placed.push((index, transform));
+        }
+        Ok(placed)
+    }
+
+    //the triangles, segments or points of a primitive, placed by `transform`, or None for modes
+    //that do not exist. `faces` counts the faces so far, and any out of range are errors, or
+    //left out and added to `dropped` with it.
+    fn model(
+        &self,
+        primitive: &Primitive,
+        transform: &[f32; 16],
+        (faces, dropped): (&mut usize, Option<&mut Vec<OutOfRange>>),
+    ) -> io::Result<Option<Mesh>> {
+        let attribute = |name: &str| primitive.attributes.get(name).map(|&accessor| self.read(accessor));
+        let positions = attribute("POSITION").ok_or_else(|| invalid(String::from("a primitive has no POSITION")))??;
+        let vertices = positions.len() / 3;
+        let corners: Vec<u32> = match primitive.indices {
+            #[allow(clippy::cast_possible_truncation)]
+            #[allow(clippy::cast_sign_loss)]
+            Some(accessor) => self.read(accessor)?.iter().map(|&i| i as u32).collect(),
+            None => (0..u32::try_from(vertices).map_err(|_| invalid(String::from("too many vertices")))?).collect(),
+        };
+        let mode = primitive.mode.unwrap_or(4);
+        let (mut indices, arity) = match mode {
+            0 => (corners, 1),
+            1 => (corners[..corners.len() / 2 * 2].to_vec(), 2),
+            //strips join their consecutive corners, and loops join the last to the first too.
+            2 | 3 => {
+                let closing = (mode == 2 && corners.len() > 2).then(|| [corners[corners.len() - 1], corners[0]]);
+                let segments = corners.windows(2).flatten().copied();
+                (segments.chain(closing.into_iter().flatten()).collect(), 2)
+            }
+            4 => (corners[..corners.len() / 3 * 3].to_vec(), 3),
+            //strips alternate their winding, and fans share their first corner.
+            5 => (
+                (2..corners.len())
+                    .flat_map(|i| match i % 2 {
+                        0 => [corners[i - 2], corners[i - 1], corners[i]],
+                        _ => [corners[i - 1], corners[i - 2], corners[i]],
+                    })
+                    .collect(),
+                3,
+            ),
+            6 => ((2..corners.len()).flat_map(|i| [corners[0], corners[i - 1], corners[i]]).collect(), 3),
+            _ => return Ok(None),
+        };
+        let (first, size) = (*faces, arity as usize);
+        *faces += indices.len() / size;
+        let out_of_range = |face: &[u32]| face.iter().copied().find(|&corner| corner as usize >= vertices);
+        if let Some(corner) = indices.chunks_exact(size).find_map(out_of_range) {
+            let Some(dropped) = dropped else {
+                return Err(invalid(format!("vertex {corner} is out of range, with {vertices} vertices")));
+            };
+            let mut kept = Vec::with_capacity(indices.len());
+            for (face, corners) in indices.chunks_exact(size).enumerate() {
+                match out_of_range(corners) {
+                    Some(corner) => dropped.push(OutOfRange {
+                        face: first + face,
+                        index: i64::from(corner),
+                    }),
+                    None => kept.extend_from_slice(corners),
+                }
+            }
+            indices = kept;
+        }
+        //mirroring transforms turn the winding around.
+        let normal_matrix = normal_matrix(transform);
+        if arity == 3 && determinant(transform) < 0.0 {
+            indices.chunks_exact_mut(3).for_each(|triangle| triangle.swap(1, 2));
+        }
+        let positions = positions.chunks_exact(3).flat_map(|p| transform_point(transform, [p[0], p[1], p[2]])).collect();
+        //attributes of every vertex, of `components` each, which must number as many as positions.
+        let per_vertex = |name: &str, components: usize| -> io::Result<Option<Vec<f32>>> {
+            match attribute(name).transpose()? {
+                Some(values) if values.len() != vertices * components => {
+                    Err(invalid(format!("{name} needs an element for each of the {vertices} positions")))
+                }
+                values => Ok(values),
+            }
+        };
+        let normals = match per_vertex("NORMAL", 3)? {
+            Some(normals) => normals.chunks_exact(3).flat_map(|n| transform_normal(&normal_matrix, [n[0], n[1], n[2]])).collect(),
+            None => vec![],
+        };
+        //linear colors of three or four channels, shown in sRGB without their alpha.
+        let vertex_color = match attribute("COLOR_0").transpose()? {
+            Some(colors) => {
+                let channels = if colors.len() == vertices * 4 { 4 } else { 3 };
+                colors.chunks_exact(channels).flat_map(|c| [c[0], c[1], c[2]].map(encode_srgb)).collect()
+            }
+            None => vec![],
+        };
+        //the texture coordinates that the material's texture is mapped by.
+        let set = primitive
+            .material
+            .and_then(|material| self.document.materials.get(material))
+            .and_then(|material| material.pbr_metallic_roughness.base_color_texture.as_ref())
+            .map_or(0, |texture| texture.tex_coord);
+        let texcoords = per_vertex(&format!("TEXCOORD_{set}"), 2)?.unwrap_or_default();
+        Ok(Some(Mesh {
+            positions,
+            normals,
+            texcoords,
+            vertex_color,
+            face_arities: if arity == 3 { vec![] } else { vec![arity; indices.len() / arity as usize] },
+            indices,
+            material_id: primitive.material,
+            ..Mesh::default()
+        }))
+    }
+
+    //every component of an accessor's elements, normalized integers scaled into [0, 1] or
+    //[-1, 1]. Accessors without a buffer view are all zeros.
+    fn read(&self, index: usize) -> io::Result<Vec<f32>> {
+        let error = |message: &str| invalid(format!("accessor {index}: {message}"));
+        let accessor = self.document.accessors.get(index).ok_or_else(|| error("does not exist"))?;
+        if accessor.sparse.is_some() {
+            return Err(error("sparse accessors are not supported"));
+        }
+        let components = match accessor.kind.as_str() {
+            "SCALAR" => 1,
+            "VEC2" => 2,
+            "VEC3" => 3,
+            "VEC4" | "MAT2" => 4,
+            "MAT3" => 9,
+            "MAT4" => 16,
+            kind => return Err(error(&format!("unknown type {kind}"))),
+        };
+        let size = match accessor.component_type {
+            5120 | 5121 => 1,
+            5122 | 5123 => 2,
+            5125 | 5126 => 4,
+            kind => return Err(error(&format!("unknown component type {kind}"))),
+        };
+        let too_many = || error("has too many elements");
+        let length = accessor.count.checked_mul(components).ok_or_else(too_many)?;
+        let Some(view) = accessor.buffer_view else {
+            if length > MAX_ZEROS {
+                return Err(too_many());
+            }
+            return Ok(vec![0.0; length]);
+        };
+        let view = self.document.buffer_views.get(view).ok_or_else(|| error("has no buffer view"))?;
+        let buffer = self.buffers.get(view.buffer).ok_or_else(|| error("has no buffer"))?;
+        let data = view
+            .byte_offset
+            .checked_add(view.byte_length)
+            .and_then(|end| buffer.get(view.byte_offset..end))
+            .ok_or_else(|| error("its buffer view is out of its buffer"))?;
+        let stride = view.byte_stride.unwrap_or(components * size);
+        if stride < components * size {
+            return Err(error("its buffer view's stride is smaller than its elements"));
+        }
+        //the last element must lie within the view, which bounds every element before it.
+        let end = match accessor.count.checked_sub(1) {
+            Some(last) => last
+                .checked_mul(stride)
+                .and_then(|start| start.checked_add(accessor.byte_offset))
+                .and_then(|start| start.checked_add(components * size)),
+            None => Some(0),
+        };
+        if end.is_none_or(|end| end > data.len()) {
+            return Err(error("is out of its buffer view"));
+        }
+        let mut values = Vec::with_capacity(length);
+        for element in 0..accessor.count {
+            let start = accessor.byte_offset + element * stride;
+            let bytes = data
+                .get(start..start + components * size)
+                .ok_or_else(|| error("is out of its buffer view"))?;
+            values.extend(bytes.chunks_exact(size).map(|b| decode(accessor.component_type, b, accessor.normalized)));
+        }
+        Ok(values)
+    }
+
+    fn material(&self, material: &GltfMaterial, directory: Option<&Path>) -> io::Result<Material> {
+        let pbr = &material.pbr_metallic_roughness;
+        let image = pbr
+            .base_color_texture
+            .as_ref()
+            .and_then(|texture| self.document.textures.get(texture.index))
+            .and_then(|texture| texture.source)
+            .and_then(|image| self.document.images.get(image));
+        let texture = match image {
+            Some(image) => {
+                let bytes = match (&image.uri, image.buffer_view) {
+                    (Some(uri), _) => resolve(uri, directory)?,
+                    (None, Some(view)) => self.view(view)?.to_vec(),
+                    (None, None) => return Err(invalid(String::from("an image has no data"))),
+                };
+                //textures that cannot be decoded leave the base color.
+                match Texture::decode(&bytes) {
+                    Ok(texture) => Some(texture),
+                    Err(e) => {
+                        eprintln!("ignoring a base color texture: {e}");
+                        None
+                    }
+                }
+            }
+            None => None,
+        };
+        Ok(Material {
+            color: pbr.base_color_factor.unwrap_or([1.0; 4]),
+            texture,
+        })
+    }
+
+    fn view(&self, index: usize) -> io::Result<&[u8]> {
+        self.document
+            .buffer_views
+            .get(index)
+            .and_then(|view| {
+                let buffer = self.buffers.get(view.buffer)?;
+                buffer.get(view.byte_offset..view.byte_offset.checked_add(view.byte_length)?)
+            })
+            .ok_or_else(|| invalid(format!("buffer view {index} is out of its buffer")))
+    }
+}
+
+//a little endian component, scaled as glTF normalizes each integer type.
+fn decode(component_type: u32, b: &[u8], normalized: bool) -> f32 {
+    let (value, full_scale) = match component_type {
+        5120 => (f32::from(b[0] as i8), 127.0),
+        5121 => (f32::from(b[0]), 255.0),
+        5122 => (f32::from(i16::from_le_bytes([b[0], b[1]])), 32767.0),
+        5123 => (f32::from(u16::from_le_bytes([b[0], b[1]])), 65535.0),
+        #[allow(clippy::cast_precision_loss)]
+        5125 => (u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f32, 4_294_967_295.0),
+        _ => (f32::from_le_bytes([b[0], b[1], b[2], b[3]]), 1.0),
+    };
+    if normalized {
+        (value / full_scale).max(-1.0)
+    } else {
+        value
+    }
+}
+
+const IDENTITY: [f32; 16] = [
+    1.0, 0.0, 0.0, 0.0, //
+    0.0, 1.0, 0.0, 0.0, //
+    0.0, 0.0, 1.0, 0.0, //
+    0.0, 0.0, 0.0, 1.0,
+];
+
+//the node's column-major transform relative to its parent.
+fn local_transform(node: &Node) -> [f32; 16] {
+    if let Some(matrix) = node.matrix {
+        return matrix;
+    }
+    let [tx, ty, tz] = node.translation.unwrap_or([0.0; 3]);
+    let [x, y, z, w] = node.rotation.unwrap_or([0.0, 0.0, 0.0, 1.0]);
+    let [sx, sy, sz] = node.scale.unwrap_or([1.0; 3]);
+    #[rustfmt::skip]
+    let transform = [
+        (1.0 - 2.0 * (y * y + z * z)) * sx, 2.0 * (x * y + z * w) * sx, 2.0 * (x * z - y * w) * sx, 0.0,
+        2.0 * (x * y - z * w) * sy, (1.0 - 2.0 * (x * x + z * z)) * sy, 2.0 * (y * z + x * w) * sy, 0.0,
+        2.0 * (x * z + y * w) * sz, 2.0 * (y * z - x * w) * sz, (1.0 - 2.0 * (x * x + y * y)) * sz, 0.0,
+        tx, ty, tz, 1.0,
+    ];
+    transform
+}
+
+fn multiply(a: &[f32; 16], b: &[f32; 16]) -> [f32; 16] {
+    let mut product = [0.0; 16];
+    for column in 0..4 {
+        for row in 0..4 {
+            product[column * 4 + row] = (0..4).map(|k| a[k * 4 + row] * b[column * 4 + k]).sum();
+        }
+    }
+    product
+}
+
+fn transform_point(m: &[f32; 16], [x, y, z]: [f32; 3]) -> [f32; 3] {
+    [0, 1, 2].map(|row| m[row] * x + m[4 + row] * y + m[8 + row] * z + m[12 + row])
+}
+
+fn determinant(m: &[f32; 16]) -> f32 {
+    m[0] * (m[5] * m[10] - m[9] * m[6]) - m[4] * (m[1] * m[10] - m[9] * m[2]) + m[8] * (m[1] * m[6] - m[5] * m[2])
+}
+
+//the cofactors of the transform's upper 3x3, which turn normals as the inverse transpose does up
+//to a scale, and flip them back for mirroring transforms.
+fn normal_matrix(m: &[f32; 16]) -> [[f32; 3]; 3] {
+    let column = |c: usize| [m[c * 4], m[c * 4 + 1], m[c * 4 + 2]];
+    let cross = |a: [f32; 3], b: [f32; 3]| [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]];
+    let sign = determinant(m).signum();
+    [cross(column(1), column(2)), cross(column(2), column(0)), cross(column(0), column(1))].map(|c| c.map(|v| v * sign))
+}
+
+fn transform_normal(m: &[[f32; 3]; 3], [x, y, z]: [f32; 3]) -> [f32; 3] {
+    let n = [0, 1, 2].map(|row| m[0][row] * x + m[1][row] * y + m[2][row] * z);
+    let length = n.iter().map(|c| c * c).sum::<f32>().sqrt();
+    if length == 0.0 {
+        n
+    } else {
+        n.map(|c| c / length)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::gltf::{decode_base64, load};
+    use crate::mesh::Primitive;
+
+    //a triangle of three floats per corner, and its indices as unsigned shorts.
+    fn buffer() -> Vec<u8> {
+        let mut bytes: Vec<u8> = [0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0]
+            .iter()
+            .flat_map(|f| f.to_le_bytes())
+            .collect();
+        bytes.extend([0u16, 1, 2, 0].iter().flat_map(|i| i.to_le_bytes()));
+        bytes
+    }
+
+    fn document(buffer: &str) -> String {
+        format!(
+            r#"{{
+                "scene": 0,
+                "scenes": [{{"nodes": [0]}}],
+                "nodes": [
+                    {{"name": "parent", "translation": [10, 0, 0], "children": [1]}},
+                    {{"mesh": 0, "scale": [2, 2, 2], "rotation": [0, 0, 0.7071068, 0.7071068]}}
+                ],
+                "meshes": [{{"name": "tri", "primitives": [{{"attributes": {{"POSITION": 0}}, "indices": 1, "material": 0}}]}}],
+                "materials": [{{"pbrMetallicRoughness": {{"baseColorFactor": [1, 0, 0, 1]}}}}],
+                "accessors": [
+                    {{"bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3"}},
+                    {{"bufferView": 1, "componentType": 5123, "count": 3, "type": "SCALAR"}}
+                ],
+                "bufferViews": [
+                    {{"buffer": 0, "byteLength": 36}},
+                    {{"buffer": 0, "byteOffset": 36, "byteLength": 6}}
+                ],
+                "buffers": [{buffer}]
+            }}"#
+        )
+    }
+
+    //a GLB of the document and buffer().
+    fn glb(json: String) -> Vec<u8> {
+        let mut json = json.into_bytes();
+        json.resize(json.len().next_multiple_of(4), b' ');
+        let mut binary = buffer();
+        binary.resize(binary.len().next_multiple_of(4), 0);
+        let mut glb = b"glTF".to_vec();
+        glb.extend(2u32.to_le_bytes());
+        glb.extend((12 + 8 + json.len() as u32 + 8 + binary.len() as u32).to_le_bytes());
+        glb.extend((json.len() as u32).to_le_bytes());
+        glb.extend(b"JSON");
+        glb.extend(json);
+        glb.extend((binary.len() as u32).to_le_bytes());
+        glb.extend(b"BIN\0");
+        glb.extend(binary);
+        glb
+    }
+
+    #[test]
+    fn base64() {
+        assert_eq!(decode_base64("TWFu").unwrap(), b"Man");
+        assert_eq!(decode_base64("TWE=").unwrap(), b"Ma");
+        assert_eq!(decode_base64("TQ==").unwrap(), b"M");
+        assert!(decode_base64("T*==").is_none());
+    }
+
+    #[test]
+    fn gltf_with_data_uri_places_nodes() {
+        let encoded: String = {
+            //encodes with the standard alphabet, three bytes at a time.
+            const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+            buffer()
+                .chunks(3)
+                .flat_map(|chunk| {
+                    let bits = chunk.iter().enumerate().fold(0u32, |bits, (i, &b)| bits | u32::from(b) << (16 - 8 * i));
+                    (0..4).map(move |i| match i <= chunk.len() {
+                        true => ALPHABET[(bits >> (18 - 6 * i) & 63) as usize] as char,
+                        false => '=',
+                    })
+                })
+                .collect()
+        };
+        let json = document(&format!(r#"{{"byteLength": 44, "uri": "data:application/octet-stream;base64,{encoded}"}}"#));
+        let (models, materials) = load(json.as_bytes(), None, None).unwrap();
+        assert_eq!(models.len(), 1);
+        assert_eq!(models[0].name, "tri");
+        assert_eq!(models[0].mesh.indices, [0, 1, 2]);
+        assert_eq!(models[0].mesh.material_id, Some(0));
+        assert_eq!(materials[0].color, [1.0, 0.0, 0.0, 1.0]);
+        //scaled by 2 and turned a quarter about z, then moved along x.
+        let corner = |i: usize| models[0].mesh.positions[i * 3..i * 3 + 3].iter().map(|p| p.round()).collect::<Vec<_>>();
+        assert_eq!(corner(0), [10.0, 0.0, 0.0]);
+        assert_eq!(corner(1), [10.0, 2.0, 0.0]);
+        assert_eq!(corner(2), [8.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn glb_with_binary_chunk() {
+        let (models, _) = load(&glb(document(r#"{"byteLength": 44}"#)), None, None).unwrap();
+        assert_eq!(models[0].mesh.positions.len(), 9);
+
+        //the same corners as a line loop.
+        let json = document(r#"{"byteLength": 44}"#).replace(r#""material": 0}"#, r#""material": 0, "mode": 2}"#);
+        let (models, _) = load(&glb(json), None, None).unwrap();
+        assert_eq!(Primitive::of(&models[0].mesh), Primitive::Lines);
+        assert_eq!(models[0].mesh.indices, [0, 1, 1, 2, 2, 0]);
+
+        let json = document(r#"{"byteLength": 44, "uri": "triangle.bin"}"#);
+        let error = load(json.as_bytes(), None, None).unwrap_err().to_string();
+        assert_eq!(error, "triangle.bin cannot be found without the document's directory");
+    }
+
+    #[test]
+    fn crafted_sizes_are_errors() {
+        let document = document(r#"{"byteLength": 44}"#);
+        let crafted = [
+            (r#""count": 3, "type": "VEC3""#, r#""count": 9223372036854775807, "type": "VEC3""#, "accessor 0: has too many elements"),
+            (r#""count": 3, "type": "VEC3""#, r#""count": 4, "type": "VEC3""#, "accessor 0: is out of its buffer view"),
+            (r#"{"buffer": 0, "byteLength": 36}"#, r#"{"buffer": 0, "byteOffset": 18446744073709551615, "byteLength": 36}"#, "accessor 0: its buffer view is out of its buffer"),
+        ];
+        for (from, to, expected) in crafted {
+            let json = document.replace(from, to);
+            assert_eq!(load(&glb(json), None, None).unwrap_err().to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn attributes_match_positions() {
+        //a NORMAL of a single element, read from the start of the positions.
+        let json = document(r#"{"byteLength": 44}"#)
+            .replace(r#""POSITION": 0}"#, r#""POSITION": 0, "NORMAL": 2}"#)
+            .replace(r#""type": "SCALAR"}"#, r#""type": "SCALAR"}, {"bufferView": 0, "componentType": 5126, "count": 1, "type": "VEC3"}"#);
+        assert_eq!(load(&glb(json), None, None).unwrap_err().to_string(), "NORMAL needs an element for each of the 3 positions");
+    }
+}
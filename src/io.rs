@@ -1,4 +1,20 @@
-use std::num::NonZeroU64;
+use crate::animation::AnimationFormat;
+use crate::background::Background;
+use crate::batch::IndexFormat;
+use crate::camera::{Camera, Pose, Projection, Quat};
+use crate::contact;
+use crate::depthmap::DepthFormat;
+use crate::format::ImageFormat;
+use crate::gbuffer::Layer;
+use crate::point::Stroke;
+use crate::preview::Preview;
+use crate::space::Rect;
+use crate::splat::{SplatColor, SplatShape, Splats, MAX_RADIUS};
+use crate::validate::ReportFormat;
+use crate::turntable::Axis;
+use std::collections::HashMap;
+use std::fmt;
+use std::num::{NonZeroU64, NonZeroUsize};
 
 #[derive(Debug, PartialEq)]
 pub(crate) struct Args {
@@ -7,71 +23,592 @@ pub(crate) struct Args {
     pub(crate) image_width: NonZeroU64,
     pub(crate) image_height: NonZeroU64,
     pub(crate) mode: Mode,
+    pub(crate) lit: bool,
+    pub(crate) stroke: Stroke,
+    pub(crate) splats: Splats,
+    pub(crate) camera: Camera,
+    pub(crate) viewport: Option<Rect>,
+    pub(crate) scissor: Option<Rect>,
+    pub(crate) pick: Option<(u64, u64)>,
+    pub(crate) gbuffer: Vec<(Layer, String)>,
+    pub(crate) gbuffer_bits: u8,
+    pub(crate) depth_background: f32,
+    pub(crate) depth_format: DepthFormat,
+    pub(crate) depth_range: Option<(f32, f32)>,
+    pub(crate) depth_invert: bool,
+    pub(crate) background: Background,
+    pub(crate) format: Option<ImageFormat>,
+    pub(crate) preview: Option<Preview>,
+    pub(crate) turntable: Option<usize>,
+    pub(crate) turntable_axis: Axis,
+    pub(crate) frame_delay: u16,
+    pub(crate) animation_format: Option<AnimationFormat>,
+    pub(crate) camera_path: Option<String>,
+    pub(crate) batch: bool,
+    pub(crate) contact_sheet: bool,
+    pub(crate) jobs: NonZeroUsize,
+    pub(crate) index: IndexFormat,
+    pub(crate) band_rows: Option<NonZeroU64>,
+    pub(crate) validate: bool,
+    pub(crate) report: ReportFormat,
 }
 
-impl Args {
-    pub(crate) fn help<'a>() -> &'a str {
-        "Usage: rusterizer Meshfile Imagefile image_width image_height [-w | --wireframe]"
+//one command line option, which both parsing and the help text are generated from.
+struct OptionSpec {
+    long: &'static str,
+    short: Option<&'static str>,
+    //what the value looks like in the help text, or None for switches that take no value.
+    value: Option<&'static str>,
+    //parsed like a given value when the option is left out.
+    default: Option<&'static str>,
+    //whether the option may be given more than once, each time adding a value.
+    repeat: bool,
+    help: &'static str,
+}
+
+impl OptionSpec {
+    const fn switch(long: &'static str, help: &'static str) -> OptionSpec {
+        OptionSpec {
+            long,
+            short: None,
+            value: None,
+            default: None,
+            repeat: false,
+            help,
+        }
+    }
+
+    const fn value(long: &'static str, value: &'static str, help: &'static str) -> OptionSpec {
+        OptionSpec {
+            value: Some(value),
+            ..OptionSpec::switch(long, help)
+        }
+    }
+
+    const fn short(self, short: &'static str) -> OptionSpec {
+        OptionSpec {
+            short: Some(short),
+            ..self
+        }
+    }
+
+    const fn default(self, default: &'static str) -> OptionSpec {
+        OptionSpec {
+            default: Some(default),
+            ..self
+        }
+    }
+
+    const fn repeat(self) -> OptionSpec {
+        OptionSpec { repeat: true, ..self }
     }
 }
 
+//the options that positional arguments stand in for, in order.
+const POSITIONAL: [&str; 4] = ["--mesh", "--output", "--width", "--height"];
+
+const OPTIONS: &[OptionSpec] = &[
+    OptionSpec::value("--mesh", "file", "the OBJ, STL, PLY, OFF, glTF, GLB or XYZ file to render, or - to read standard input"),
+    OptionSpec::value("--output", "file", "the image to write, or - for standard output")
+        .short("-o")
+        .default("out.png"),
+    OptionSpec::value("--width", "pixels", "the width of the image").default("640"),
+    OptionSpec::value("--height", "pixels", "the height of the image").default("480"),
+    OptionSpec::value(
+        "--mode",
+        "depth|wireframe|color|splats",
+        "fill triangles, only draw their edges, fill them in their vertex colors, or draw every vertex as a splat",
+    )
+    .default("depth"),
+    OptionSpec::switch("--wireframe", "the same as --mode wireframe").short("-w"),
+    OptionSpec::switch("--lit", "light --mode color by a light at the camera"),
+    OptionSpec::value("--point-size", "pixels", "the width of the squares that points are drawn as").default("1"),
+    OptionSpec::value("--line-width", "pixels", "the width of lines").default("1"),
+    OptionSpec::value("--splat", "square|circle|gaussian", "the shape of --mode splats").default("circle"),
+    OptionSpec::value("--splat-radius", "radius", "the radius of splats, in pixels").default("2"),
+    OptionSpec::switch("--splat-world", "measure --splat-radius in the units of the fitted mesh, not pixels"),
+    OptionSpec::value("--splat-color", "vertex|height|depth", "what splats are colored by").default("vertex"),
+    OptionSpec::value("--camera", "x,y,z", "view from this position, looking at the origin").default("0,0,2"),
+    OptionSpec::value("--fov", "degrees", "view in perspective, instead of orthographically"),
+    OptionSpec::value("--viewport", "x,y,width,height", "draw into this rectangle of the image, not all of it"),
+    OptionSpec::value("--scissor", "x,y,width,height", "only write the pixels within this rectangle"),
+    OptionSpec::value("--pick", "x,y", "report the triangle under this pixel"),
+    OptionSpec::value("--gbuffer", "layer=file", "also write a view-space normal, window-space position, world-normal, world-position, uv or depth layer").repeat(),
+    OptionSpec::value("--gbuffer-bits", "8|16", "bits per channel of PNG g-buffer layers").default("8"),
+    OptionSpec::value("--depth-background", "value", "the depth of uncovered pixels").default("inf"),
+    OptionSpec::value(
+        "--depth-format",
+        "rgba|gray16|viridis|turbo|magma",
+        "how the image shows depth",
+    )
+    .default("rgba"),
+    OptionSpec::value("--depth-range", "near,far", "normalize depth between these, not the covered extremes"),
+    OptionSpec::switch("--depth-invert", "make near pixels bright"),
+    OptionSpec::value(
+        "--background",
+        "spec",
+        "transparent, #rrggbb[aa], gradient:top:bottom or image:file.png",
+    )
+    .default("#000000"),
+    OptionSpec::value("--format", "png|ppm|pam|tga|bmp|qoi", "the image format, if not the file's extension"),
+    OptionSpec::value("--preview", "truecolor|ascii|sixel", "also draw the image in the terminal"),
+    OptionSpec::value("--turntable", "frames", "animate one full turn of the model"),
+    OptionSpec::value("--turntable-axis", "x|y|z", "the axis the turntable spins around").default("y"),
+    OptionSpec::value("--frame-delay", "ms", "how long each animation frame is shown").default("40"),
+    OptionSpec::value("--animation-format", "apng|gif|y4m", "the animation format, if not the file's extension"),
+    OptionSpec::value("--camera-path", "file", "animate the camera and model along keyframes"),
+    OptionSpec::switch("--batch", "render a thumbnail of every mesh in the --mesh directory or pattern"),
+    OptionSpec::switch("--contact-sheet", "render front, back, left, right, top and isometric views in a grid"),
+    OptionSpec::value("--jobs", "threads", "how many batch meshes to render at once").default("1"),
+    OptionSpec::value("--index", "html|json", "the listing written with batch thumbnails").default("html"),
+    OptionSpec::value("--band-rows", "rows", "render and write a PNG this many rows at a time, in bounded memory"),
+    OptionSpec::switch("--validate", "check the mesh for broken geometry, instead of rendering it"),
+    OptionSpec::value("--report", "text|json", "how --validate prints what it found").default("text"),
+    OptionSpec::value("--scene", "file", "render a TOML or JSON scene, instead of any other options"),
+    OptionSpec::switch("--help", "print this help").short("-h"),
+];
+
 impl Args {
-    pub(crate) fn new(args: std::env::Args) -> Result<Args, ArgsError> {
+    pub(crate) fn help() -> String {
+        let mut help = String::from(
+            "Usage: rusterizer [options] [mesh [output [width [height]]]]\n       rusterizer --scene scene.toml|scene.json\n\nOptions:\n",
+        );
+        let usages: Vec<String> = OPTIONS
+            .iter()
+            .map(|option| {
+                let short = option.short.map_or(String::from("    "), |short| format!("{short}, "));
+                let value = option.value.map_or(String::new(), |value| format!(" <{value}>"));
+                format!("{short}{}{value}", option.long)
+            })
+            .collect();
+        let column = usages.iter().map(String::len).max().unwrap_or_default() + 2;
+        for (option, usage) in OPTIONS.iter().zip(&usages) {
+            help += &format!("  {usage:column$}{}", option.help);
+            if let Some(default) = option.default {
+                help += &format!(" [default: {default}]");
+            }
+            if option.repeat {
+                help += " (repeatable)";
+            }
+            help.push('\n');
+        }
+        help
+    }
+}
+
+//what the command line asks for: a render described by its arguments, or by a scene file.
+#[derive(Debug, PartialEq)]
+pub(crate) enum Command {
+    Render(Box<Args>),
+    Scene(String),
+    Help,
+}
+
+impl Command {
+    pub(crate) fn new(args: std::env::Args) -> Result<Command, ArgsError> {
         let unstructured_args: Vec<String> = args.collect();
-        Self::structure_args(&unstructured_args)
+        Self::structure(&unstructured_args)
+    }
+
+    //"--scene file" stands in for every other argument, and "--help" for any at all, even ones
+    //that are wrong, but not when it is the value of another option.
+    fn structure<T: AsRef<str>>(args: &[T]) -> Result<Command, ArgsError> {
+        let args = args.get(1..).unwrap_or_default();
+        let mut tokens = args.iter().map(AsRef::as_ref);
+        while let Some(token) = tokens.next() {
+            match OPTIONS.iter().find(|option| option.long == token || option.short == Some(token)) {
+                Some(option) if option.long == "--help" => return Ok(Command::Help),
+                Some(option) if option.value.is_some() => {
+                    tokens.next();
+                }
+                _ => {}
+            }
+        }
+        let options = Options::parse(args)?;
+        match options.value("--scene") {
+            Some(file) => match (options.positional.first(), options.named.keys().find(|&&name| name != "--scene")) {
+                (Some(arg), _) => Err(ArgsError::UnexpectedArgument(String::from(*arg))),
+                (None, Some(name)) => Err(ArgsError::Conflict("--scene", name)),
+                (None, None) => Ok(Command::Scene(String::from(file))),
+            },
+            None => Args::structure_args(&options).map(|args| Command::Render(Box::new(args))),
+        }
+    }
+}
+
+//the command line split into the values of named options and positional arguments, as given.
+struct Options<'a> {
+    named: HashMap<&'static str, Vec<&'a str>>,
+    positional: Vec<&'a str>,
+}
+
+impl<'a> Options<'a> {
+    //accepts "--name value", "--name=value" and "-n value" for options that take values.
+    fn parse<T: AsRef<str>>(args: &'a [T]) -> Result<Options<'a>, ArgsError> {
+        let mut options = Options {
+            named: HashMap::new(),
+            positional: vec![],
+        };
+        let mut tokens = args.iter().map(AsRef::as_ref);
+        while let Some(token) = tokens.next() {
+            //"-" is standard input or output, and "-1" a number, not an option.
+            let number = token
+                .strip_prefix('-')
+                .is_some_and(|rest| rest.starts_with(|c: char| c.is_ascii_digit() || c == '.'));
+            if !token.starts_with('-') || token == "-" || number {
+                options.positional.push(token);
+                continue;
+            }
+            let (name, inline) = match token.split_once('=') {
+                Some((name, value)) if name.starts_with("--") => (name, Some(value)),
+                _ => (token, None),
+            };
+            let option = OPTIONS
+                .iter()
+                .find(|option| option.long == name || option.short == Some(name))
+                .ok_or_else(|| ArgsError::UnknownOption(String::from(token)))?;
+            let value = match (option.value, inline) {
+                (None, None) => "",
+                (None, Some(_)) => return Err(ArgsError::UnexpectedValue(option.long)),
+                (Some(_), Some(value)) => value,
+                (Some(_), None) => tokens.next().ok_or(ArgsError::MissingValue(option.long))?,
+            };
+            let values = options.named.entry(option.long).or_default();
+            if !values.is_empty() && option.value.is_some() && !option.repeat {
+                return Err(ArgsError::Repeated(option.long));
+            }
+            values.push(value);
+        }
+        //positional arguments fill in the options they stand for, but cannot also be named.
+        for (&arg, &name) in options.positional.iter().zip(POSITIONAL.iter()) {
+            if options.named.insert(name, vec![arg]).is_some() {
+                return Err(ArgsError::Repeated(name));
+            }
+        }
+        if let Some(arg) = options.positional.get(POSITIONAL.len()) {
+            return Err(ArgsError::UnexpectedArgument(String::from(*arg)));
+        }
+        Ok(options)
     }
 
-    fn structure_args<T: AsRef<str>>(args: &[T]) -> Result<Args, ArgsError> {
-        if !(args.len() == 5 || args.len() == 6) {
-            //neither nor
-            return Err(ArgsError::BadLength);
+    //the value given for an option, if any.
+    fn value(&self, name: &str) -> Option<&'a str> {
+        self.named.get(name).and_then(|values| values.last().copied())
+    }
+
+    fn is_set(&self, name: &str) -> bool {
+        self.named.contains_key(name)
+    }
+
+    //the value given for an option, or else its default, parsed.
+    fn parse_value<T>(&self, name: &'static str, parse: impl Fn(&str) -> Option<T>) -> Result<Option<T>, ArgsError> {
+        let default = OPTIONS.iter().find(|option| option.long == name).and_then(|option| option.default);
+        match self.value(name).or(default) {
+            Some(value) => parse(value).map(Some).ok_or(ArgsError::InvalidValue(name)),
+            None => Ok(None),
         }
+    }
+
+    //like parse_value, for options that have a default or must be given.
+    fn required<T>(&self, name: &'static str, parse: impl Fn(&str) -> Option<T>) -> Result<T, ArgsError> {
+        self.parse_value(name, parse)?.ok_or(ArgsError::MissingArgument(name))
+    }
+}
 
-        let input_mesh = args[1].as_ref();
-        let input_image = args[2].as_ref();
-        let width = args[3]
-            .as_ref()
-            .parse::<NonZeroU64>()
+impl Args {
+    fn structure_args(options: &Options) -> Result<Args, ArgsError> {
+        let image_width = options
+            .required("--width", |value| value.parse::<NonZeroU64>().ok())
             .map_err(|_| ArgsError::ImageDimensions("width invalid"))?;
-        let height = args[4]
-            .as_ref()
-            .parse::<NonZeroU64>()
+        let image_height = options
+            .required("--height", |value| value.parse::<NonZeroU64>().ok())
             .map_err(|_| ArgsError::ImageDimensions("height invalid"))?;
-        let mode = if args.len() == 6 {
-            match args[5].as_ref() {
-                "--wireframe" | "-w" => Ok(Mode::Wireframe),
-                _ => Err(ArgsError::BadMode), //something was there, but not a valid argument.
-            }?
-        } else {
-            Mode::Depth
+        let mut mode = options.required("--mode", |value| match value {
+            "depth" => Some(Mode::Depth),
+            "wireframe" => Some(Mode::Wireframe),
+            "color" => Some(Mode::Color),
+            "splats" => Some(Mode::Splats),
+            _ => None,
+        })?;
+        if options.is_set("--wireframe") {
+            if options.is_set("--mode") {
+                return Err(ArgsError::Conflict("--wireframe", "--mode"));
+            }
+            mode = Mode::Wireframe;
+        }
+        let position = options.required("--camera", |value| {
+            parse_floats::<3>(value).filter(|&position| position != [0.0; 3])
+        })?;
+        let projection = match options.parse_value("--fov", |value| {
+            value.parse::<f32>().ok().filter(|fov| *fov > 0.0 && *fov < 180.0)
+        })? {
+            Some(fov) => Projection::Perspective(fov.to_radians()),
+            None => Projection::Orthographic(1.0),
         };
-
-        Ok(Args {
-            mesh_file: String::from(input_mesh),
-            image_file: String::from(input_image),
-            image_width: width,
-            image_height: height,
+        let gbuffer = match options.named.get("--gbuffer") {
+            Some(values) => values
+                .iter()
+                .map(|value| parse_layer(value).ok_or(ArgsError::InvalidValue("--gbuffer")))
+                .collect::<Result<Vec<_>, _>>()?,
+            None => vec![],
+        };
+        let args = Args {
+            mesh_file: options.required("--mesh", |value| Some(String::from(value)))?,
+            //a batch writes into a directory.
+            image_file: match options.value("--output") {
+                None if options.is_set("--batch") => String::from("thumbnails"),
+                _ => options.required("--output", |value| Some(String::from(value)))?,
+            },
+            image_width,
+            image_height,
             mode,
-        })
+            lit: options.is_set("--lit"),
+            stroke: Stroke {
+                point_size: options.required("--point-size", parse_width)?,
+                line_width: options.required("--line-width", parse_width)?,
+            },
+            splats: Splats {
+                shape: options.required("--splat", SplatShape::from_name)?,
+                radius: options.required("--splat-radius", |value| {
+                    value.parse::<f32>().ok().filter(|radius| (0.0..=MAX_RADIUS).contains(radius))
+                })?,
+                world: options.is_set("--splat-world"),
+                color: options.required("--splat-color", SplatColor::from_name)?,
+            },
+            //the default position looks at the origin exactly as Camera::default() does.
+            camera: Camera {
+                pose: Pose {
+                    position,
+                    rotation: Quat::look_at(position, [0.0; 3]),
+                    scale: 1.0,
+                },
+                projection,
+            },
+            viewport: options.parse_value("--viewport", parse_rect)?,
+            scissor: options.parse_value("--scissor", parse_rect)?,
+            pick: options.parse_value("--pick", parse_pixel)?,
+            gbuffer,
+            gbuffer_bits: options.required("--gbuffer-bits", |value| match value {
+                "8" => Some(8),
+                "16" => Some(16),
+                _ => None,
+            })?,
+            //accepts "inf" and "NaN" as well as plain numbers.
+            depth_background: options.required("--depth-background", |value| value.parse().ok())?,
+            depth_format: options.required("--depth-format", DepthFormat::from_name)?,
+            depth_range: options.parse_value("--depth-range", parse_range)?,
+            depth_invert: options.is_set("--depth-invert"),
+            background: options.required("--background", Background::from_spec)?,
+            format: options.parse_value("--format", ImageFormat::from_name)?,
+            preview: options.parse_value("--preview", Preview::from_name)?,
+            turntable: options.parse_value("--turntable", |value| {
+                value.parse::<usize>().ok().filter(|&frames| frames > 0)
+            })?,
+            turntable_axis: options.required("--turntable-axis", Axis::from_name)?,
+            frame_delay: options.required("--frame-delay", |value| value.parse().ok())?,
+            animation_format: options.parse_value("--animation-format", AnimationFormat::from_name)?,
+            camera_path: options.value("--camera-path").map(String::from),
+            batch: options.is_set("--batch"),
+            contact_sheet: options.is_set("--contact-sheet"),
+            jobs: options.required("--jobs", |value| value.parse().ok())?,
+            index: options.required("--index", IndexFormat::from_name)?,
+            band_rows: options.parse_value("--band-rows", |value| value.parse().ok())?,
+            validate: options.is_set("--validate"),
+            report: options.required("--report", ReportFormat::from_name)?,
+        };
+
+        //only vertex colors are lit.
+        match args.mode {
+            Mode::Depth if args.lit => return Err(ArgsError::Conflict("--lit", "--mode depth")),
+            Mode::Wireframe if args.lit => return Err(ArgsError::Conflict("--lit", "--mode wireframe")),
+            Mode::Splats if args.lit => return Err(ArgsError::Conflict("--lit", "--mode splats")),
+            _ => {}
+        }
+        //animations only shade frames, so there are no depth images or g-buffers to go with them.
+        let stills = [
+            ("--depth-format", args.depth_format != DepthFormat::Rgba),
+            ("--gbuffer", !args.gbuffer.is_empty()),
+        ];
+        let animations = [
+            ("--turntable", args.turntable.is_some()),
+            ("--camera-path", args.camera_path.is_some()),
+        ];
+        if let Some((still, _)) = stills.iter().find(|(_, set)| *set) {
+            if let Some((animation, _)) = animations.iter().find(|(_, set)| *set) {
+                return Err(ArgsError::Conflict(animation, still));
+            }
+        }
+        if animations.iter().all(|(_, set)| *set) {
+            return Err(ArgsError::Conflict("--turntable", "--camera-path"));
+        }
+        //a batch only writes shaded thumbnails, to and from files.
+        if args.batch {
+            let singles = ["--pick", "--gbuffer", "--preview", "--turntable", "--camera-path"];
+            if let Some(single) = singles.into_iter().find(|&name| options.is_set(name)) {
+                return Err(ArgsError::Conflict("--batch", single));
+            }
+            if args.depth_format != DepthFormat::Rgba {
+                return Err(ArgsError::Conflict("--batch", "--depth-format"));
+            }
+            if args.mesh_file == "-" {
+                return Err(ArgsError::InvalidValue("--mesh"));
+            }
+            if args.image_file == "-" {
+                return Err(ArgsError::InvalidValue("--output"));
+            }
+        }
+        //a contact sheet brings its own cameras, one per cell.
+        if args.contact_sheet {
+            let cameras = ["--camera", "--fov", "--viewport", "--pick", "--turntable", "--camera-path", "--batch"];
+            if let Some(camera) = cameras.into_iter().find(|&name| options.is_set(name)) {
+                return Err(ArgsError::Conflict("--contact-sheet", camera));
+            }
+            if args.image_width.get() < contact::COLUMNS || args.image_height.get() < contact::ROWS {
+                return Err(ArgsError::ImageDimensions("too small for a contact sheet"));
+            }
+        }
+        //bands stream a shaded PNG, so nothing else may need the whole image at once.
+        if args.band_rows.is_some() {
+            let wholes = ["--gbuffer", "--preview", "--turntable", "--camera-path", "--batch", "--contact-sheet"];
+            if let Some(whole) = wholes.into_iter().find(|&name| options.is_set(name)) {
+                return Err(ArgsError::Conflict("--band-rows", whole));
+            }
+            if args.depth_format != DepthFormat::Rgba {
+                return Err(ArgsError::Conflict("--band-rows", "--depth-format"));
+            }
+            if args.format.is_some_and(|format| format != ImageFormat::Png) {
+                return Err(ArgsError::Conflict("--band-rows", "--format"));
+            }
+        }
+        //validation only loads the mesh.
+        if args.validate {
+            let renders = [
+                "--batch",
+                "--turntable",
+                "--camera-path",
+                "--contact-sheet",
+                "--band-rows",
+                "--gbuffer",
+                "--preview",
+                "--pick",
+            ];
+            if let Some(render) = renders.into_iter().find(|&name| options.is_set(name)) {
+                return Err(ArgsError::Conflict("--validate", render));
+            }
+        }
+        //camera paths bring their own cameras.
+        for camera in ["--camera", "--fov"].into_iter().filter(|&name| options.is_set(name)) {
+            if args.camera_path.is_some() {
+                return Err(ArgsError::Conflict("--camera-path", camera));
+            }
+        }
+        Ok(args)
+    }
+}
+
+//parses N comma separated numbers.
+fn parse_floats<const N: usize>(value: &str) -> Option<[f32; N]> {
+    let mut values = [0.0; N];
+    let mut parts = value.split(',');
+    for value in &mut values {
+        *value = parts.next()?.trim().parse().ok()?;
+    }
+    parts.next().is_none().then_some(values)
+}
+
+//parses a pixel coordinate of the form "x,y".
+fn parse_pixel(value: &str) -> Option<(u64, u64)> {
+    let (x, y) = value.split_once(',')?;
+    Some((x.trim().parse().ok()?, y.trim().parse().ok()?))
+}
+
+//parses a rectangle of the form "x,y,width,height", of at least a pixel.
+fn parse_rect(value: &str) -> Option<Rect> {
+    let parts: Vec<&str> = value.split(',').map(str::trim).collect();
+    let [x, y, width, height] = parts[..] else {
+        return None;
+    };
+    let (width, height): (u64, u64) = (width.parse().ok()?, height.parse().ok()?);
+    (width > 0 && height > 0).then_some(Rect::new(x.parse().ok()?, y.parse().ok()?, width, height))
+}
+
+//parses a point size or line width in pixels, which must be positive.
+fn parse_width(value: &str) -> Option<f32> {
+    value.parse::<f32>().ok().filter(|width| width.is_finite() && *width > 0.0)
+}
+
+//parses a depth range of the form "near,far", where near lies in front of far.
+fn parse_range(value: &str) -> Option<(f32, f32)> {
+    let (near, far) = value.split_once(',')?;
+    let (near, far): (f32, f32) = (near.trim().parse().ok()?, far.trim().parse().ok()?);
+    (near < far).then_some((near, far))
+}
+
+//parses a g-buffer output of the form "layer=file".
+fn parse_layer(value: &str) -> Option<(Layer, String)> {
+    let (layer, file) = value.split_once('=')?;
+    if file.is_empty() {
+        return None;
     }
+    Some((Layer::from_name(layer)?, String::from(file)))
 }
 
 #[derive(Debug, PartialEq)]
-pub(crate) enum ArgsError {
-    BadLength,
+pub enum ArgsError {
+    //a required option, given neither by name nor by position.
+    MissingArgument(&'static str),
     ImageDimensions(&'static str),
-    BadMode,
+    UnknownOption(String),
+    //a positional argument beyond the ones that stand in for options.
+    UnexpectedArgument(String),
+    MissingValue(&'static str),
+    InvalidValue(&'static str),
+    //a switch given a value, as in --depth-invert=yes.
+    UnexpectedValue(&'static str),
+    //an option that takes one value, given more than once.
+    Repeated(&'static str),
+    Conflict(&'static str, &'static str),
 }
 
+impl fmt::Display for ArgsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArgsError::MissingArgument(name) => write!(f, "{name} is required"),
+            ArgsError::ImageDimensions(message) => write!(f, "{message}: the image needs a positive size"),
+            ArgsError::UnknownOption(option) => write!(f, "unknown option {option}"),
+            ArgsError::UnexpectedArgument(arg) => write!(f, "unexpected argument \"{arg}\""),
+            ArgsError::MissingValue(name) => write!(f, "{name} needs a value"),
+            ArgsError::InvalidValue(name) => write!(f, "invalid value for {name}"),
+            ArgsError::UnexpectedValue(name) => write!(f, "{name} does not take a value"),
+            ArgsError::Repeated(name) => write!(f, "{name} was given more than once"),
+            ArgsError::Conflict(a, b) => write!(f, "{a} cannot be combined with {b}"),
+        }
+    }
+}
+
+impl std::error::Error for ArgsError {}
+
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
 pub enum Mode {
     Depth,
     Wireframe,
+    //filled like Depth, in the meshes' interpolated vertex colors.
+    Color,
+    //every vertex stamped as a splat, ignoring faces.
+    Splats,
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::depthmap::Colormap;
+
+    fn parse(args: &[&str]) -> Result<Args, ArgsError> {
+        match Command::structure(args)? {
+            Command::Render(args) => Ok(*args),
+            command => panic!("expected a render, not {command:?}"),
+        }
+    }
 
     #[test]
     fn valid_args() {
@@ -81,57 +618,415 @@ mod tests {
             image_width: NonZeroU64::new(1).unwrap(),
             image_height: NonZeroU64::new(1).unwrap(),
             mode: Mode::Wireframe,
+            lit: false,
+            stroke: Stroke::default(),
+            splats: Splats::default(),
+            camera: Camera::default(),
+            viewport: None,
+            scissor: None,
+            pick: None,
+            gbuffer: vec![],
+            gbuffer_bits: 8,
+            depth_background: f32::INFINITY,
+            depth_format: DepthFormat::Rgba,
+            depth_range: None,
+            depth_invert: false,
+            background: Background::default(),
+            format: None,
+            preview: None,
+            turntable: None,
+            turntable_axis: Axis::Y,
+            frame_delay: 40,
+            animation_format: None,
+            camera_path: None,
+            batch: false,
+            contact_sheet: false,
+            jobs: NonZeroUsize::MIN,
+            index: IndexFormat::Html,
+            band_rows: None,
+            validate: false,
+            report: ReportFormat::Text,
         };
     }
 
     #[test]
     fn invalid_height() {
         let raw_args = vec!["name", "a", "b", "1", "-1"];
-        let args = Args::structure_args(&raw_args);
+        let args = parse(&raw_args);
         assert_eq!(args, Err(ArgsError::ImageDimensions("height invalid")));
     }
 
     #[test]
     fn invalid_width() {
         let raw_args = vec!["name", "a", "b", "0", "1"];
-        let args = Args::structure_args(&raw_args);
+        let args = parse(&raw_args);
         assert_eq!(args, Err(ArgsError::ImageDimensions("width invalid")));
     }
 
     #[test]
     fn invalid_mode() {
         let raw_args1 = vec!["name", "a", "b", "1", "1", ""];
-        let args1 = Args::structure_args(&raw_args1);
-        assert_eq!(args1, Err(ArgsError::BadMode));
+        let args1 = parse(&raw_args1);
+        assert_eq!(args1, Err(ArgsError::UnexpectedArgument(String::new())));
 
         let raw_args2 = vec!["name", "a", "b", "1", "1", "garbage"];
-        let args2 = Args::structure_args(&raw_args2);
-        assert_eq!(args2, Err(ArgsError::BadMode));
+        let args2 = parse(&raw_args2);
+        assert_eq!(args2, Err(ArgsError::UnexpectedArgument(String::from("garbage"))));
+
+        let raw_args3 = vec!["name", "a", "b", "1", "1", "--mode", "points"];
+        let args3 = parse(&raw_args3);
+        assert_eq!(args3, Err(ArgsError::InvalidValue("--mode")));
     }
 
     #[test]
     fn wireframe_short() {
         let raw_args = vec!["name", "a", "b", "1", "1", "-w"];
-        let args = Args::structure_args(&raw_args);
+        let args = parse(&raw_args);
         assert_eq!(args.unwrap().mode, Mode::Wireframe);
     }
     #[test]
     fn wireframe_fully_qualified() {
         let raw_args = vec!["name", "a", "b", "1", "1", "--wireframe"];
-        let args = Args::structure_args(&raw_args);
+        let args = parse(&raw_args);
         assert_eq!(args.unwrap().mode, Mode::Wireframe);
     }
 
     #[test]
     fn depth() {
         let raw_args = vec!["name", "a", "b", "1", "1"];
-        let args = Args::structure_args(&raw_args);
+        let args = parse(&raw_args);
         assert_eq!(args.unwrap().mode, Mode::Depth);
     }
 
+    #[test]
+    fn vertex_colors() {
+        let args = parse(&["name", "a", "b", "1", "1", "--mode", "color", "--lit"]).unwrap();
+        assert_eq!(args.mode, Mode::Color);
+        assert!(args.lit);
+        let args = parse(&["name", "a", "b", "1", "1", "--lit"]);
+        assert_eq!(args, Err(ArgsError::Conflict("--lit", "--mode depth")));
+    }
+
+    #[test]
+    fn stroke() {
+        let args = parse(&["name", "a", "b", "1", "1"]).unwrap();
+        assert_eq!(args.stroke, Stroke::default());
+        let args = parse(&["name", "a", "b", "1", "1", "--point-size", "4", "--line-width", "2.5"]).unwrap();
+        assert_eq!(args.stroke.point_size, 4.0);
+        assert_eq!(args.stroke.line_width, 2.5);
+        let args = parse(&["name", "a", "b", "1", "1", "--line-width", "0"]);
+        assert_eq!(args, Err(ArgsError::InvalidValue("--line-width")));
+    }
+
+    #[test]
+    fn splats() {
+        let args = parse(&["name", "a", "b", "1", "1", "--mode", "splats"]).unwrap();
+        assert_eq!(args.mode, Mode::Splats);
+        assert_eq!(args.splats, Splats::default());
+        let raw_args = ["name", "a", "--mode", "splats", "--splat", "gaussian", "--splat-radius", "0.01", "--splat-world", "--splat-color", "height"];
+        let args = parse(&raw_args).unwrap();
+        let expected = Splats {
+            shape: SplatShape::Gaussian,
+            radius: 0.01,
+            world: true,
+            color: SplatColor::Height,
+        };
+        assert_eq!(args.splats, expected);
+        let args = parse(&["name", "a", "--splat", "star"]);
+        assert_eq!(args, Err(ArgsError::InvalidValue("--splat")));
+        for radius in ["1e30", "inf", "NaN", "-1"] {
+            let args = parse(&["name", "a", "--splat-radius", radius]);
+            assert_eq!(args, Err(ArgsError::InvalidValue("--splat-radius")), "{radius}");
+        }
+        let args = parse(&["name", "a", "--mode", "splats", "--lit"]);
+        assert_eq!(args, Err(ArgsError::Conflict("--lit", "--mode splats")));
+    }
+
+    #[test]
+    fn pick() {
+        let raw_args = vec!["name", "a", "b", "1", "1", "--pick", "3,4", "-w"];
+        let args = parse(&raw_args).unwrap();
+        assert_eq!(args.pick, Some((3, 4)));
+        assert_eq!(args.mode, Mode::Wireframe);
+        //and rays are cast through any camera.
+        assert!(parse(&["name", "a", "--pick", "3,4", "--camera", "1,2,3", "--fov", "60"]).is_ok());
+
+        let raw_args = vec!["name", "a", "b", "1", "1", "--pick"];
+        let args = parse(&raw_args);
+        assert_eq!(args, Err(ArgsError::MissingValue("--pick")));
+
+        let raw_args = vec!["name", "a", "b", "1", "1", "--pick", "3"];
+        let args = parse(&raw_args);
+        assert_eq!(args, Err(ArgsError::InvalidValue("--pick")));
+    }
+
+    #[test]
+    fn gbuffer() {
+        let raw_args = vec![
+            "name", "a", "b", "1", "1", "--gbuffer", "normal=n.png", "--gbuffer", "depth=d.pfm",
+            "--gbuffer", "world-position=p.exr", "--gbuffer-bits", "16",
+        ];
+        let args = parse(&raw_args).unwrap();
+        assert_eq!(
+            args.gbuffer,
+            vec![
+                (Layer::Normal, String::from("n.png")),
+                (Layer::Depth, String::from("d.pfm")),
+                (Layer::WorldPosition, String::from("p.exr"))
+            ]
+        );
+        assert_eq!(args.gbuffer_bits, 16);
+
+        let raw_args = vec!["name", "a", "b", "1", "1", "--gbuffer", "albedo=a.png"];
+        let args = parse(&raw_args);
+        assert_eq!(args, Err(ArgsError::InvalidValue("--gbuffer")));
+
+        let raw_args = vec!["name", "a", "b", "1", "1", "--gbuffer-bits", "12"];
+        let args = parse(&raw_args);
+        assert_eq!(args, Err(ArgsError::InvalidValue("--gbuffer-bits")));
+    }
+
+    #[test]
+    fn depth_background() {
+        let raw_args = vec!["name", "a", "b", "1", "1"];
+        let args = parse(&raw_args).unwrap();
+        assert_eq!(args.depth_background, f32::INFINITY);
+
+        let raw_args = vec!["name", "a", "b", "1", "1", "--depth-background", "-1e3"];
+        let args = parse(&raw_args).unwrap();
+        assert_eq!(args.depth_background, -1000.0);
+
+        let raw_args = vec!["name", "a", "b", "1", "1", "--depth-background", "NaN"];
+        let args = parse(&raw_args).unwrap();
+        assert!(args.depth_background.is_nan());
+
+        let raw_args = vec!["name", "a", "b", "1", "1", "--depth-background", "far"];
+        let args = parse(&raw_args);
+        assert_eq!(args, Err(ArgsError::InvalidValue("--depth-background")));
+    }
+
+    #[test]
+    fn depth_format() {
+        let raw_args = vec![
+            "name", "a", "b", "1", "1", "--depth-format", "turbo", "--depth-range", "-0.5,0.5",
+            "--depth-invert",
+        ];
+        let args = parse(&raw_args).unwrap();
+        assert_eq!(args.depth_format, DepthFormat::Colormap(Colormap::Turbo));
+        assert_eq!(args.depth_range, Some((-0.5, 0.5)));
+        assert!(args.depth_invert);
+
+        let raw_args = vec!["name", "a", "b", "1", "1", "--depth-format", "gray8"];
+        let args = parse(&raw_args);
+        assert_eq!(args, Err(ArgsError::InvalidValue("--depth-format")));
+
+        let raw_args = vec!["name", "a", "b", "1", "1", "--depth-range", "1,0"];
+        let args = parse(&raw_args);
+        assert_eq!(args, Err(ArgsError::InvalidValue("--depth-range")));
+    }
+
+    #[test]
+    fn background() {
+        let raw_args = vec!["name", "a", "b", "1", "1", "--background", "transparent"];
+        let args = parse(&raw_args).unwrap();
+        assert_eq!(args.background, Background::Transparent);
+
+        let raw_args = vec!["name", "a", "b", "1", "1", "--background", "mint"];
+        let args = parse(&raw_args);
+        assert_eq!(args, Err(ArgsError::InvalidValue("--background")));
+    }
+
+    #[test]
+    fn format() {
+        let raw_args = vec!["name", "a", "b.img", "1", "1", "--format", "qoi"];
+        let args = parse(&raw_args).unwrap();
+        assert_eq!(args.format, Some(ImageFormat::Qoi));
+
+        let raw_args = vec!["name", "a", "b", "1", "1", "--format", "jpeg"];
+        let args = parse(&raw_args);
+        assert_eq!(args, Err(ArgsError::InvalidValue("--format")));
+    }
+
+    #[test]
+    fn preview() {
+        let raw_args = vec!["name", "a", "b", "1", "1", "--preview", "sixel"];
+        let args = parse(&raw_args).unwrap();
+        assert_eq!(args.preview, Some(Preview::Sixel));
+
+        let raw_args = vec!["name", "a", "b", "1", "1", "--preview", "kitty"];
+        let args = parse(&raw_args);
+        assert_eq!(args, Err(ArgsError::InvalidValue("--preview")));
+    }
+
+    #[test]
+    fn turntable() {
+        let raw_args = vec![
+            "name", "a", "b", "1", "1", "--turntable", "36", "--turntable-axis", "x",
+            "--frame-delay", "100", "--animation-format", "y4m",
+        ];
+        let args = parse(&raw_args).unwrap();
+        assert_eq!(args.animation_format, Some(AnimationFormat::Y4m));
+        assert_eq!(args.turntable, Some(36));
+        assert_eq!(args.turntable_axis, Axis::X);
+        assert_eq!(args.frame_delay, 100);
+
+        let raw_args = vec!["name", "a", "b", "1", "1", "--turntable", "0"];
+        let args = parse(&raw_args);
+        assert_eq!(args, Err(ArgsError::InvalidValue("--turntable")));
+
+        let raw_args = vec![
+            "name", "a", "b", "1", "1", "--turntable", "8", "--depth-format", "gray16",
+        ];
+        let args = parse(&raw_args);
+        assert_eq!(args, Err(ArgsError::Conflict("--turntable", "--depth-format")));
+    }
+
+    #[test]
+    fn camera_path() {
+        let raw_args = vec!["name", "a", "b", "1", "1", "--camera-path", "fly.path"];
+        let args = parse(&raw_args).unwrap();
+        assert_eq!(args.camera_path.as_deref(), Some("fly.path"));
+
+        let raw_args = vec![
+            "name", "a", "b", "1", "1", "--camera-path", "fly.path", "--turntable", "8",
+        ];
+        let args = parse(&raw_args);
+        assert_eq!(args, Err(ArgsError::Conflict("--turntable", "--camera-path")));
+    }
+
+    #[test]
+    fn batch() {
+        let raw_args = vec!["name", "--batch", "assets/*.obj", "--jobs", "4", "--index", "json"];
+        let args = parse(&raw_args).unwrap();
+        assert!(args.batch);
+        assert_eq!(args.mesh_file, "assets/*.obj");
+        assert_eq!(args.image_file, "thumbnails");
+        assert_eq!(args.jobs.get(), 4);
+        assert_eq!(args.index, IndexFormat::Json);
+
+        let raw_args = vec!["name", "--batch", "assets", "--jobs", "0"];
+        assert_eq!(parse(&raw_args), Err(ArgsError::InvalidValue("--jobs")));
+
+        let raw_args = vec!["name", "--batch", "assets", "--turntable", "8"];
+        assert_eq!(parse(&raw_args), Err(ArgsError::Conflict("--batch", "--turntable")));
+    }
+
+    #[test]
+    fn contact_sheet() {
+        let args = parse(&["name", "a", "b", "300", "200", "--contact-sheet"]).unwrap();
+        assert!(args.contact_sheet);
+
+        let raw_args = vec!["name", "a", "b", "2", "2", "--contact-sheet"];
+        assert_eq!(parse(&raw_args), Err(ArgsError::ImageDimensions("too small for a contact sheet")));
+
+        let raw_args = vec!["name", "a", "--contact-sheet", "--camera", "1,1,1"];
+        assert_eq!(parse(&raw_args), Err(ArgsError::Conflict("--contact-sheet", "--camera")));
+    }
+
+    #[test]
+    fn band_rows() {
+        let args = parse(&["name", "a", "--band-rows", "256"]).unwrap();
+        assert_eq!(args.band_rows, NonZeroU64::new(256));
+
+        let raw_args = vec!["name", "a", "--band-rows", "256", "--format", "bmp"];
+        assert_eq!(parse(&raw_args), Err(ArgsError::Conflict("--band-rows", "--format")));
+
+        let raw_args = vec!["name", "a", "--band-rows", "0"];
+        assert_eq!(parse(&raw_args), Err(ArgsError::InvalidValue("--band-rows")));
+    }
+
+    #[test]
+    fn validate() {
+        let args = parse(&["name", "--validate", "a.obj", "--report", "json"]).unwrap();
+        assert!(args.validate);
+        assert_eq!(args.report, ReportFormat::Json);
+
+        let raw_args = vec!["name", "--validate", "a.obj", "--turntable", "8"];
+        assert_eq!(parse(&raw_args), Err(ArgsError::Conflict("--validate", "--turntable")));
+    }
+
+    #[test]
+    fn viewport_and_scissor() {
+        let raw_args = vec!["name", "a", "--viewport", "-10,20,300,200", "--scissor=0,0,64,64"];
+        let args = parse(&raw_args).unwrap();
+        assert_eq!(args.viewport, Some(Rect::new(-10, 20, 300, 200)));
+        assert_eq!(args.scissor, Some(Rect::new(0, 0, 64, 64)));
+
+        let raw_args = vec!["name", "a", "--scissor", "0,0,0,64"];
+        assert_eq!(parse(&raw_args), Err(ArgsError::InvalidValue("--scissor")));
+    }
+
+    #[test]
+    fn scene() {
+        let raw_args = vec!["name", "--scene", "scene.toml"];
+        let command = Command::structure(&raw_args);
+        assert_eq!(command, Ok(Command::Scene(String::from("scene.toml"))));
+
+        let raw_args = vec!["name", "--scene"];
+        let command = Command::structure(&raw_args);
+        assert_eq!(command, Err(ArgsError::MissingValue("--scene")));
+
+        let raw_args = vec!["name", "a", "b", "1", "1"];
+        assert!(matches!(Command::structure(&raw_args), Ok(Command::Render(_))));
+
+        let raw_args = vec!["name", "--scene", "scene.toml", "-w"];
+        let command = Command::structure(&raw_args);
+        assert_eq!(command, Err(ArgsError::Conflict("--scene", "--wireframe")));
+    }
+
+    #[test]
+    fn non_ascii_paths() {
+        let args = parse(&["name", "ö.obj", "ü-1.png", "10", "10"]).unwrap();
+        assert_eq!((args.mesh_file.as_str(), args.image_file.as_str()), ("ö.obj", "ü-1.png"));
+        assert_eq!(parse(&["name", "a", "-ö"]), Err(ArgsError::UnknownOption(String::from("-ö"))));
+    }
+
+    #[test]
+    fn named_options() {
+        let raw_args = vec![
+            "name", "a.obj", "--height=200", "--output", "b.png", "--camera", "0,0,-3", "--fov", "60",
+        ];
+        let args = parse(&raw_args).unwrap();
+        assert_eq!(args.mesh_file, "a.obj");
+        assert_eq!(args.image_file, "b.png");
+        assert_eq!((args.image_width.get(), args.image_height.get()), (640, 200));
+        assert_eq!(args.camera.pose.position, [0.0, 0.0, -3.0]);
+        assert_eq!(args.camera.projection, Projection::Perspective(60f32.to_radians()));
+
+        //the defaults are the legacy behaviour.
+        let args = parse(&["name", "--mesh", "-"]).unwrap();
+        assert_eq!(args.image_file, "out.png");
+        assert_eq!(args.camera, Camera::default());
+        assert_eq!(args.background, Background::default());
+
+        assert_eq!(parse(&["name"]), Err(ArgsError::MissingArgument("--mesh")));
+        assert_eq!(parse(&["name", "a", "--mesh", "b"]), Err(ArgsError::Repeated("--mesh")));
+        assert_eq!(parse(&["name", "a", "--colour", "red"]), Err(ArgsError::UnknownOption(String::from("--colour"))));
+        assert_eq!(parse(&["name", "a", "--depth-invert=yes"]), Err(ArgsError::UnexpectedValue("--depth-invert")));
+        assert_eq!(parse(&["name", "a", "-w", "--mode", "depth"]), Err(ArgsError::Conflict("--wireframe", "--mode")));
+        assert_eq!(parse(&["name", "a", "--camera", "0,0,0"]), Err(ArgsError::InvalidValue("--camera")));
+    }
+
+    #[test]
+    fn help() {
+        assert_eq!(Command::structure(&["name", "a", "--bogus", "-h"]), Ok(Command::Help));
+        //unless it is another option's value.
+        let command = Command::structure(&["name", "a", "--output", "-h", "--width", "1", "--height", "1"]);
+        assert!(matches!(command, Ok(Command::Render(args)) if args.image_file == "-h"));
+        let help = Args::help();
+        //every option is listed on its own line, with its default.
+        let lines: Vec<&str> = help.lines().filter(|line| line.trim_start().starts_with('-')).collect();
+        assert_eq!(lines.len(), OPTIONS.len());
+        for (line, option) in lines.iter().zip(OPTIONS) {
+            assert!(line.contains(option.long), "{line}");
+        }
+        assert!(help.contains("  -o, --output <file>"));
+        assert!(help.contains("[default: 640]"));
+    }
+
     #[test]
     fn no_args() {
-        let args = std::env::args();
-        assert!(Args::new(args).is_err(), "calling with no arguments should never return an Ok status.");
+        let args = ["name"];
+        assert!(Command::structure(&args).is_err(), "calling with no arguments should never return an Ok status.");
     }
 }
@@ -1,149 +1,523 @@
-use crate::io::Args;
-use crate::obj::{get_mesh_data, resize_obj};
-use crate::point::rasterize;
-use crate::space::Fragment;
-use array2d::Array2D;
-use png::Writer;
-
+use crate::animation::{is_sequence, write_sequence, AnimationFormat};
+use crate::camera::{Camera, ModelView, Pose};
+use crate::depthmap::{normalize, write_depth_map, DepthFormat};
+use crate::error::RenderError;
+use crate::format::{write_png_bands, ImageFormat};
+use crate::framebuffer::FrameBuffer;
+use crate::gbuffer::Layer;
+use crate::background::{Backdrop, Background};
+use crate::batch::{find_meshes, render_batch, write_index};
+use crate::io::{Args, Command, Mode};
+use crate::material::Material;
+use crate::obj::{get_fitted_mesh_data, get_lenient_mesh_data, get_mesh_data};
+use crate::path::CameraPath;
+use crate::pick::{pick, Bvh};
+use crate::point::{rasterize, Stroke};
+use crate::poster::render_bands;
+use crate::preview::{terminal_columns, Preview};
+use crate::scene::Scene;
+use crate::shade::{
+    shade_depth, shade_lit, shade_materials, shade_objects, shade_splats, shade_vertex_colors, Light, BASE_COLOR,
+};
+use crate::space::{Fragment, Rect};
+use crate::splat::{height_range, rasterize_splats, SplatColor};
+use crate::turntable::turntable;
+use crate::validate::{Report, ReportFormat};
 use std::fs::File;
-use std::io::BufWriter;
+use std::io::{BufWriter, Write};
 use std::num::NonZeroU64;
-use std::path::Path;
-use std::process;
-
-//use crate::point::rasterize;
+use std::process::ExitCode;
 
+mod animation;
+mod background;
+mod batch;
+mod camera;
+mod contact;
+mod depthmap;
+mod encode;
+mod error;
+mod format;
+mod framebuffer;
+mod gbuffer;
+mod gltf;
 mod io;
+mod material;
+mod mesh;
 mod obj;
+mod off;
+mod path;
+mod pick;
+mod ply;
 mod point;
+mod poster;
+mod preview;
+mod scene;
+mod shade;
 mod space;
+mod splat;
+mod stl;
+mod turntable;
+mod validate;
+mod xyz;
 
-fn parse_cmd() -> Args {
-    match Args::new(std::env::args()) {
-        Ok(args) => args,
-        Err(e) => {
-            eprintln!("An error occurred during command line parsing: {e:#?}");
-            eprintln!("{}", Args::help());
-            process::exit(1);
-        }
+fn create_space_transforms(
+    width: NonZeroU64,
+    height: NonZeroU64,
+    viewport: Option<Rect>,
+    scissor: Option<Rect>,
+) -> Result<space::Space, RenderError> {
+    let space = match viewport {
+        Some(viewport) => space::Space::viewport(viewport),
+        None => space::Space::new(width, height),
+    };
+    match space {
+        Ok(space) => Ok(match scissor {
+            Some(scissor) => space.with_scissor(scissor),
+            None => space,
+        }),
+        Err(space::Error::Init) => Err(RenderError::Geometry("the viewport is empty")),
     }
 }
 
-//the panic indicates a bug in error-handling for parse_cmd, or in Space's constructor.
-fn create_space_transforms(width: NonZeroU64, height: NonZeroU64) -> space::Space {
-    match space::Space::new(width, height) {
-        Ok(space) => space,
-        Err(e) => panic!("An error occurred during view volume creation: {e:#?}"),
+//the models of a mesh file, and the materials they refer to.
+type Meshes<'a> = (&'a [tobj::Model], &'a [Material]);
+
+//reports what stopped the render, and exits with the code of its class.
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{e}");
+            if let RenderError::Arguments(_) = e {
+                eprintln!("{}", Args::help());
+            }
+            ExitCode::from(e.exit_code())
+        }
     }
 }
 
-fn main() {
-    let args = parse_cmd();
-    let space = create_space_transforms(args.image_width, args.image_height);
+fn run() -> Result<(), RenderError> {
+    let args = match Command::new(std::env::args())? {
+        Command::Render(args) => *args,
+        Command::Scene(file) => return render_scene(&file),
+        Command::Help => {
+            print!("{}", Args::help());
+            return Ok(());
+        }
+    };
+    let space = create_space_transforms(args.image_width, args.image_height, args.viewport, args.scissor)?;
+    if args.batch {
+        return write_batch(&args, &space);
+    }
+    if args.validate {
+        return print_report(&args);
+    }
     //store triangle's indices and vertex positions into packed data structures.
-    let models = get_mesh_data(&args.mesh_file);
+    let (models, materials, fits) = get_fitted_mesh_data(&args.mesh_file)?;
     //positions proportionally scaled in the range [-1,1]
-    
+    if let Some((x, y)) = args.pick {
+        print_pick(&args, (&models, &fits), &space, (x, y));
+    }
+    if let Some(frames) = args.turntable {
+        return write_turntable(&args, &space, (&models, &materials), frames);
+    }
+    if let Some(file) = &args.camera_path {
+        return write_camera_path(&args, &space, (&models, &materials), file);
+    }
+    if let Some(rows) = args.band_rows {
+        return write_poster(&args, (&models, &materials), rows);
+    }
 
-    //maintain a z buffer, a 2d structure to store depth information per pixel.
-    let mut fragments = Array2D::filled_with(
-        f32::MAX,
+    //maintain a z buffer, a 2d structure to store depth information per pixel,
+    //and surface attributes when auxiliary outputs were requested or textures are mapped by them.
+    let mut frame = FrameBuffer::new(
         args.image_width.get() as usize,
         args.image_height.get() as usize,
+        args.gbuffer.iter().any(|(layer, _)| *layer != Layer::Depth) || needs_gbuffer(&args, &materials),
     );
     //the actual rasterization operation.
-    for model in &models {
-        rasterize(
-            &mut fragments,
-            &space,
-            &model.mesh.positions,
-            &model.mesh.indices,
-            args.mode,
-        );
-    }
-    for (i, item) in fragments.elements_column_major_iter().enumerate() {
-            if (item - 1.0f32).abs() < f32::EPSILON {
-                print!("{item:^3}");
-            } else {
-                print!("{:^3}",0);
-            }
-        if i % args.image_width.get() as usize == args.image_width.get() as usize - 1 {
-            println!();
-        }
+    if args.contact_sheet {
+        rasterize_contact_sheet(&args, &mut frame, &models)?;
+    } else {
+        let view = ModelView::new(&args.camera, &Pose::IDENTITY);
+        rasterize_models(&mut frame, &space, &models, &args, &view);
+    }
+    write_gbuffer(&args, &frame, &fits)?;
+    if args.depth_format != DepthFormat::Rgba {
+        return write_depth_image(&args, &frame);
+    }
+    let background = create_background(&args.background, args.image_width, args.image_height)?;
+    let mut data = shade_models(&args, &frame, &background, (&models, &materials));
+    if args.contact_sheet {
+        label_contact_sheet(&args, &mut data);
+    }
+    if let Some(preview) = args.preview {
+        print_preview(&args, preview, &data);
     }
-    
-    let mut writer = get_writer(&args);
-
-    let pixels: Vec<f32> = fragments
-        .elements_column_major_iter()
-        //.map(|&f| if f > 1.0 { 1.0 } else { f })
-        .copied().collect();
-    
-
-    let mut data = [165u8, 255u8, 214u8, 255u8]
-        .repeat((args.image_width.get() * args.image_height.get()) as usize); // An array containing a RGBA sequence.
-
-    for i in 0..pixels.len() {
-        //pixels = [-1, 1]
-        // -    -> [1 ,-1]
-        // +1   -> [2 , 0]
-        // /2   -> [2 , 0]
-        // *data-> [255,0]
-
-        let one = ((-pixels[i] + 1.0) / 2.0) * (f32::try_from(data[i * 4]).unwrap());
-        let two = ((-pixels[i] + 1.0) / 2.0) * (f32::try_from(data[i * 4 + 1]).unwrap());
-        let three = ((-pixels[i] + 1.0) / 2.0) * (f32::try_from(data[i * 4 + 2]).unwrap());
-        let four = ((-pixels[i] + 1.0) / 2.0) * (f32::try_from(data[i * 4 + 3]).unwrap());
-
-        let floats = [one, two, three, four].map(|f| if f < 0.0{0.0} else {f});
-        #[allow(clippy::cast_possible_truncation)]
-        #[allow(clippy::cast_sign_loss)]
-        let bytes = floats.map(|x| x as u8);
-
-        for j in 0..3 {
-            data[i * 4 + j] = bytes[j];
+    write_image(&args.image_file, args.format, args.image_width, args.image_height, &data)?; // Save
+    eprintln!("wrote to: {}", args.image_file);
+    Ok(())
+}
+
+//renders each view of a contact sheet into its own cell of the frame.
+fn rasterize_contact_sheet(args: &Args, frame: &mut FrameBuffer, models: &[tobj::Model]) -> Result<(), RenderError> {
+    let cells = contact::cells(args.image_width.get(), args.image_height.get());
+    for ((_, camera), (x, y, width, height)) in contact::views().iter().zip(cells) {
+        //every cell is at least a pixel in each direction, as parse_cmd checked the image size.
+        let cell = Rect::new(x as i64, y as i64, width, height);
+        //the cell is the scissor too, narrowed by any the command line gave.
+        let scissor = args.scissor.map_or(cell, |scissor| scissor.intersect(cell));
+        let space = create_space_transforms(args.image_width, args.image_height, Some(cell), Some(scissor))?;
+        let view = ModelView::new(camera, &Pose::IDENTITY);
+        rasterize_models(frame, &space, models, args, &view);
+    }
+    Ok(())
+}
+
+//rasterizes each model as its own object, so that it is shaded in its own material, or as splats
+//for --mode splats.
+fn rasterize_models(frame: &mut FrameBuffer, space: &space::Space, models: &[tobj::Model], args: &Args, view: &ModelView) {
+    let heights = height_range(models);
+    for (object, model) in models.iter().enumerate() {
+        frame.object = object;
+        match args.mode {
+            Mode::Splats => rasterize_splats(frame, space, &model.mesh, view, &args.splats, heights),
+            _ => rasterize(frame, space, &model.mesh, args.mode, view, args.stroke),
         }
     }
-    println!();
-    for (i,d) in data.iter().step_by(4).enumerate() {
-        print!("{d:^4.2}");
-        if i % args.image_width.get() as usize == args.image_width.get() as usize - 1 {
-            println!();
+}
+
+//shades the models that rasterize_models drew, each in the material it refers to, or in their
+//vertex colors for --mode color and --mode splats.
+fn shade_models(args: &Args, frame: &FrameBuffer, background: &[u8], (models, materials): Meshes) -> Vec<u8> {
+    if args.mode == Mode::Splats {
+        //depth colors span the nearest and farthest splats, or --depth-range.
+        let depths = (args.splats.color == SplatColor::Depth)
+            .then(|| normalize(frame, args.depth_range, args.depth_invert));
+        return shade_splats(frame, background, depths.as_deref());
+    }
+    if materials.is_empty() && args.mode != Mode::Color {
+        return shade_depth(frame, background);
+    }
+    let materials: Vec<Option<&Material>> = models
+        .iter()
+        .map(|model| model.mesh.material_id.and_then(|material| materials.get(material)))
+        .collect();
+    if args.mode == Mode::Color {
+        //meshes without vertex colors show the base color of their material.
+        let colors: Vec<[u8; 4]> = materials
+            .iter()
+            .map(|material| material.map_or(BASE_COLOR, |material| material.color(None)))
+            .collect();
+        //a light at the camera, which looks down -z.
+        let headlight = Light {
+            direction: [0.0, 0.0, -1.0],
+            color: [0.9; 3],
+        };
+        let lights = if args.lit { vec![headlight] } else { vec![] };
+        return shade_vertex_colors(frame, background, &colors, &lights, 0.1);
+    }
+    shade_materials(frame, background, &materials)
+}
+
+//whether frames need a g-buffer to shade the models: for the texture coordinates of textured
+//materials, or the vertex colors and normals of --mode color and the splats of --mode splats.
+fn needs_gbuffer(args: &Args, materials: &[Material]) -> bool {
+    matches!(args.mode, Mode::Color | Mode::Splats) || materials.iter().any(|material| material.texture.is_some())
+}
+
+//names each view in the top left corner of its cell, in letters that grow with the cells.
+fn label_contact_sheet(args: &Args, data: &mut [u8]) {
+    let cells = contact::cells(args.image_width.get(), args.image_height.get());
+    for ((label, _), (x, y, _, height)) in contact::views().iter().zip(cells) {
+        let scale = (height as usize / 100).max(1);
+        let margin = scale * 3;
+        contact::draw_label(data, args.image_width.get() as usize, (x as usize + margin, y as usize + margin), label, scale);
+    }
+}
+
+//renders the image a band of rows at a time, and streams each band into a PNG as it is shaded,
+//so that posters far larger than memory can be rendered.
+fn write_poster(args: &Args, (models, materials): Meshes, rows: NonZeroU64) -> Result<(), RenderError> {
+    let (width, height) = (args.image_width, args.image_height);
+    //reads a background image once, now, rather than for every band or failing partway through
+    //the PNG.
+    let backdrop = decode_background(&args.background)?;
+    let viewport = args.viewport.unwrap_or(Rect::new(0, 0, width.get(), height.get()));
+    let view = ModelView::new(&args.camera, &Pose::IDENTITY);
+    let draw = |frame: &mut FrameBuffer, space: &space::Space| rasterize_models(frame, space, models, args, &view);
+    let shade = |frame: &FrameBuffer, band: std::ops::Range<u64>| {
+        let band = band.start as usize..band.end as usize;
+        let background = backdrop.render_rows(width.get() as usize, height.get() as usize, band);
+        Ok(shade_models(args, frame, &background, (models, materials)))
+    };
+    let scissor = args.scissor.unwrap_or(viewport);
+    let bands = render_bands((width, height), rows, viewport, scissor, needs_gbuffer(args, materials), draw, shade);
+    u32::try_from(width.get())
+        .and_then(|width| Ok((width, u32::try_from(height.get())?)))
+        .map_err(std::io::Error::other)
+        .and_then(|(width, height)| {
+            create_output(&args.image_file).and_then(|w| write_png_bands(w, width, height, bands))
+        })
+        .map_err(RenderError::output(&args.image_file))?;
+    eprintln!("wrote to: {}", args.image_file);
+    Ok(())
+}
+
+//renders every mesh instance of a scene file through its camera, each in its own material, and
+//lit when the scene has lights. Each mesh file is loaded once and shared by its instances.
+//Instances without a scene material show the base colors of the mesh file's materials, as
+//scenes neither sample textures nor interpolate vertex colors.
+fn render_scene(file: &str) -> Result<(), RenderError> {
+    let scene = Scene::from_file(file).map_err(RenderError::input(file))?;
+    let output = &scene.output;
+    let space = create_space_transforms(output.width, output.height, None, None)?;
+    let mut frame = FrameBuffer::new(
+        output.width.get() as usize,
+        output.height.get() as usize,
+        !scene.lights.is_empty(),
+    );
+    let meshes = scene.meshes.iter().map(|file| get_mesh_data(file)).collect::<Result<Vec<_>, _>>()?;
+    //each model of each instance is an object of its own color.
+    let mut colors = vec![];
+    for instance in &scene.instances {
+        let view = ModelView::new(&scene.camera, &instance.pose);
+        let (models, materials) = &meshes[instance.mesh];
+        for model in models {
+            frame.object = colors.len();
+            rasterize(&mut frame, &space, &model.mesh, output.mode, &view, Stroke::default());
+            let material = model.mesh.material_id.and_then(|material| materials.get(material));
+            colors.push(instance.color.unwrap_or_else(|| material.map_or(BASE_COLOR, |material| material.color(None))));
         }
     }
+    let background = create_background(&output.background, output.width, output.height)?;
+    let data = if scene.lights.is_empty() {
+        shade_objects(&frame, &background, &colors)
+    } else {
+        shade_lit(&frame, &background, &colors, &scene.lights, scene.ambient)
+    };
+    write_image(&output.file, output.format, output.width, output.height, &data)?;
+    eprintln!("wrote to: {}", output.file);
+    Ok(())
+}
+
+//renders a thumbnail of every mesh that --mesh finds into the --output directory, with an index
+//of them, and reports the meshes that failed once the rest are done.
+fn write_batch(args: &Args, space: &space::Space) -> Result<(), RenderError> {
+    let meshes = find_meshes(&args.mesh_file).map_err(RenderError::input(&args.mesh_file))?;
+    let output = std::path::Path::new(&args.image_file);
+    std::fs::create_dir_all(output).map_err(RenderError::output(&args.image_file))?;
+    let (Ok(width), Ok(height)) = (u32::try_from(args.image_width.get()), u32::try_from(args.image_height.get())) else {
+        let error = std::io::Error::other("the thumbnails are too large to encode");
+        return Err(RenderError::output(&args.image_file)(error));
+    };
+    let background = create_background(&args.background, args.image_width, args.image_height)?;
+    let view = ModelView::new(&args.camera, &Pose::IDENTITY);
+    let render = |models: &[tobj::Model], materials: &[Material]| {
+        let mut frame = FrameBuffer::new(width as usize, height as usize, needs_gbuffer(args, materials));
+        rasterize_models(&mut frame, space, models, args, &view);
+        shade_models(args, &frame, &background, (models, materials))
+    };
+    let format = args.format.unwrap_or(ImageFormat::Png);
+    let (thumbnails, failures) = render_batch(&meshes, output, format, (width, height), args.jobs, render);
+    let index = write_index(output, args.index, (width, height), &thumbnails, &failures)
+        .map_err(RenderError::output(&args.image_file))?;
+    eprintln!("wrote {} thumbnails to: {}", thumbnails.len(), index.display());
+    for failure in &failures {
+        eprintln!("could not render {}: {}", failure.mesh, failure.error);
+    }
+    if !failures.is_empty() {
+        return Err(RenderError::Batch {
+            failed: failures.len(),
+            total: meshes.len(),
+        });
+    }
+    Ok(())
+}
+
+//renders the background that uncovered pixels show.
+fn create_background(background: &Background, width: NonZeroU64, height: NonZeroU64) -> Result<Vec<u8>, RenderError> {
+    background
+        .render(width.get() as usize, height.get() as usize)
+        .map_err(RenderError::input(background_file(background)))
+}
+
+//reads any background image, for rendering the background a band at a time.
+fn decode_background(background: &Background) -> Result<Backdrop<'_>, RenderError> {
+    background.decode().map_err(RenderError::input(background_file(background)))
+}
+
+//what reading the background fails on: only image backgrounds read anything.
+fn background_file(background: &Background) -> &str {
+    match background {
+        Background::Image(file) => file,
+        _ => "the background",
+    }
+}
+
+//renders one shaded frame per step of a full turn, and writes them as an animation.
+fn write_turntable(args: &Args, space: &space::Space, meshes: Meshes, frames: usize) -> Result<(), RenderError> {
+    let poses = turntable(args.turntable_axis, frames).map(|pose| (args.camera, pose));
+    write_animation(args, space, meshes, poses)?;
+    eprintln!("wrote {frames} frames to: {}", args.image_file);
+    Ok(())
+}
+
+//renders the camera path every --frame-delay milliseconds, and writes them as an animation.
+fn write_camera_path(args: &Args, space: &space::Space, meshes: Meshes, file: &str) -> Result<(), RenderError> {
+    let path = CameraPath::from_file(file).map_err(RenderError::input(file))?;
+    let frames = path.frames(args.frame_delay).map_err(RenderError::input(file))?;
+    let count = frames.len();
+    write_animation(args, space, meshes, frames)?;
+    eprintln!("wrote {count} frames to: {}", args.image_file);
+    Ok(())
+}
 
-    println!("wrote to: {}", args.image_file);
-    
-    writer.write_image_data(&data).unwrap(); // Save
-}
-
-fn get_writer(args: &Args) -> Writer<BufWriter<File>> {
-    let path = Path::new(&args.image_file);
-    let create = File::create(path);
-    if create.is_err() {
-        eprintln!(
-            "an error happened when attempting to create {}: {}",
-            args.image_file,
-            create.unwrap_err()
-        );
-        process::exit(1);
-    }
-    let w = BufWriter::new(create.unwrap());
-    let mut encoder = png::Encoder::new(w, args.image_width.get().try_into().unwrap(), args.image_height.get().try_into().unwrap());
-    encoder.set_color(png::ColorType::Rgba);
-    encoder.set_depth(png::BitDepth::Eight);
-    encoder.set_source_gamma(png::ScaledFloat::from_scaled(45455)); // 1.0 / 2.2, scaled by 100000
-    encoder.set_source_gamma(png::ScaledFloat::new(1.0 / 2.2)); // 1.0 / 2.2, unscaled, but rounded
-    let source_chromaticities = png::SourceChromaticities::new(
-        // Using unscaled instantiation here
-        (0.31270, 0.32900),
-        (0.64000, 0.33000),
-        (0.30000, 0.60000),
-        (0.15000, 0.06000),
+//shades each frame's models, placed and viewed by its camera, into a single frame buffer that is
+//cleared between frames, and writes every frame as soon as it is shaded: to numbered image
+//files for patterns like "out_%04d.png", otherwise to one animation in the
+//--animation-format, or the one matching the file's extension, or an animated PNG.
+fn write_animation(
+    args: &Args,
+    space: &space::Space,
+    (models, materials): Meshes,
+    frames: impl ExactSizeIterator<Item = (Camera, Pose)>,
+) -> Result<(), RenderError> {
+    let background = create_background(&args.background, args.image_width, args.image_height)?;
+    let mut frame = FrameBuffer::new(
+        args.image_width.get() as usize,
+        args.image_height.get() as usize,
+        needs_gbuffer(args, materials),
     );
-    encoder.set_source_chromaticities(source_chromaticities);
+    let images = frames.map(|(camera, pose)| {
+        frame.clear();
+        let view = ModelView::new(&camera, &pose);
+        rasterize_models(&mut frame, space, models, args, &view);
+        shade_models(args, &frame, &background, (models, materials))
+    });
+    u32::try_from(args.image_width.get())
+        .and_then(|width| Ok((width, u32::try_from(args.image_height.get())?)))
+        .map_err(std::io::Error::other)
+        .and_then(|(width, height)| {
+            if is_sequence(&args.image_file) {
+                let format = args
+                    .format
+                    .or_else(|| ImageFormat::from_path(&args.image_file))
+                    .unwrap_or(ImageFormat::Png);
+                write_sequence(&args.image_file, format, width, height, images)
+            } else {
+                let format = args
+                    .animation_format
+                    .or_else(|| AnimationFormat::from_path(&args.image_file))
+                    .unwrap_or(AnimationFormat::Apng);
+                create_output(&args.image_file)
+                    .and_then(|w| format.write(w, width, height, images, args.frame_delay))
+            }
+        })
+        .map_err(RenderError::output(&args.image_file))
+}
+
+//writes each requested auxiliary layer to its own file. The `fits` take each model back to where
+//the mesh file put it, for the world layers.
+fn write_gbuffer(args: &Args, frame: &FrameBuffer, fits: &[Pose]) -> Result<(), RenderError> {
+    for (layer, file) in &args.gbuffer {
+        frame
+            .export(*layer, file, args.gbuffer_bits, args.depth_background, fits)
+            .map_err(RenderError::output(file))?;
+        eprintln!("wrote {layer:?} to: {file}");
+    }
+    Ok(())
+}
+
+//writes the z-buffer as a normalized grayscale or colormapped depth image.
+fn write_depth_image(args: &Args, frame: &FrameBuffer) -> Result<(), RenderError> {
+    create_output(&args.image_file)
+        .and_then(|w| write_depth_map(w, args.depth_format, frame, args.depth_range, args.depth_invert))
+        .map_err(RenderError::output(&args.image_file))?;
+    eprintln!("wrote to: {}", args.image_file);
+    Ok(())
+}
+
+//prints what --validate found in the mesh, as the file has it, to standard output, and fails if it
+//found problems.
+fn print_report(args: &Args) -> Result<(), RenderError> {
+    let (models, out_of_range) = get_lenient_mesh_data(&args.mesh_file)?;
+    let report = Report::new(&args.mesh_file, &models, &out_of_range);
+    let mut out = std::io::stdout().lock();
+    match args.report {
+        ReportFormat::Text => write!(out, "{report}"),
+        ReportFormat::Json => serde_json::to_writer_pretty(&mut out, &report)
+            .map_err(std::io::Error::from)
+            .and_then(|()| writeln!(out)),
+    }
+    .map_err(RenderError::output("standard output"))?;
+    match report.problems() {
+        0 => Ok(()),
+        problems => Err(RenderError::Invalid {
+            file: args.mesh_file.clone(),
+            problems,
+        }),
+    }
+}
+
+//draws the image in the terminal, on standard error when standard output carries the image itself.
+fn print_preview(args: &Args, preview: Preview, data: &[u8]) {
+    let width = args.image_width.get() as usize;
+    let height = args.image_height.get() as usize;
+    let columns = terminal_columns();
+    let result = if args.image_file == "-" {
+        preview.write(std::io::stderr().lock(), width, height, data, columns)
+    } else {
+        preview.write(std::io::stdout().lock(), width, height, data, columns)
+    };
+    if let Err(e) = result {
+        eprintln!("an error happened when attempting to draw the preview: {e}");
+    }
+}
+
+//reports the model and triangle visible at pixel (x, y).
+fn print_pick(args: &Args, (models, fits): (&[tobj::Model], &[Pose]), space: &space::Space, (x, y): (u64, u64)) {
+    let bvh = Bvh::new(models, fits, &ModelView::new(&args.camera, &Pose::IDENTITY));
+    match pick(&bvh, space, x, y) {
+        Some(hit) => {
+            let [alpha, beta, gamma] = hit.barycentric;
+            let p = hit.position;
+            eprintln!(
+                "pick ({x}, {y}): model {} \"{}\", triangle {}, barycentric ({alpha}, {beta}, {gamma}), position ({}, {}, {})",
+                hit.model, models[hit.model].name, hit.triangle, p.x, p.y, p.z
+            );
+        }
+        None => eprintln!("pick ({x}, {y}): no hit"),
+    }
+}
+
+//"-" writes to standard output, which then carries nothing but the image.
+fn create_output(path: &str) -> std::io::Result<Box<dyn Write>> {
+    if path == "-" {
+        Ok(Box::new(BufWriter::new(std::io::stdout().lock())))
+    } else {
+        Ok(Box::new(BufWriter::new(File::create(path)?)))
+    }
+}
 
-    encoder.write_header().unwrap()
+//encodes the RGBA image in the requested format, or the one matching the file's extension.
+fn write_image(
+    file: &str,
+    format: Option<ImageFormat>,
+    width: NonZeroU64,
+    height: NonZeroU64,
+    data: &[u8],
+) -> Result<(), RenderError> {
+    let format = format
+        .or_else(|| ImageFormat::from_path(file))
+        .unwrap_or(ImageFormat::Png);
+    create_output(file)
+        .and_then(|w| {
+            let width = u32::try_from(width.get()).map_err(std::io::Error::other)?;
+            let height = u32::try_from(height.get()).map_err(std::io::Error::other)?;
+            format.write_rgba(w, width, height, data)
+        })
+        .map_err(RenderError::output(file))
 }
 //Points(x,y,z)
 //for each triangle(p1, p2, p3):
@@ -0,0 +1,104 @@
+use crate::background::decode_png_rgba;
+use std::io;
+
+//how a model's surface is colored: a base color, multiplied by a texture where it has one.
+//Models refer to their material by their mesh's material_id.
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) struct Material {
+    //linear RGBA.
+    pub(crate) color: [f32; 4],
+    pub(crate) texture: Option<Texture>,
+}
+
+impl Material {
+    //the sRGB color of the surface at the texture coordinates `uv`, or of the base color alone
+    //without a texture or coordinates.
+    pub(crate) fn color(&self, uv: Option<[f32; 2]>) -> [u8; 4] {
+        let texel = match (&self.texture, uv) {
+            (Some(texture), Some(uv)) => texture.sample(uv),
+            _ => [1.0; 4],
+        };
+        let linear = [0, 1, 2, 3].map(|c| self.color[c] * texel[c]);
+        #[allow(clippy::cast_possible_truncation)]
+        #[allow(clippy::cast_sign_loss)]
+        let alpha = (linear[3].clamp(0.0, 1.0) * 255.0).round() as u8;
+        [to_srgb(linear[0]), to_srgb(linear[1]), to_srgb(linear[2]), alpha]
+    }
+}
+
+//an sRGB image that surfaces are colored by, with (0, 0) at the top left of the first row.
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) struct Texture {
+    width: usize,
+    height: usize,
+    //row-major RGBA.
+    pixels: Vec<u8>,
+}
+
+impl Texture {
+    //decodes a PNG texture, the only format that is read.
+    pub(crate) fn decode(bytes: &[u8]) -> io::Result<Texture> {
+        if !bytes.starts_with(b"\x89PNG") {
+            return Err(io::Error::other("only PNG textures can be read"));
+        }
+        let (width, height, pixels) = decode_png_rgba(bytes)?;
+        Ok(Texture { width, height, pixels })
+    }
+
+    //the linear RGBA of the texel nearest to `uv`, which repeats outside [0, 1].
+    fn sample(&self, [u, v]: [f32; 2]) -> [f32; 4] {
+        #[allow(clippy::cast_possible_truncation)]
+        #[allow(clippy::cast_sign_loss)]
+        let texel = |t: f32, size: usize| ((t.rem_euclid(1.0) * size as f32) as usize).min(size - 1);
+        let i = (texel(v, self.height) * self.width + texel(u, self.width)) * 4;
+        let channel = |c: usize| f32::from(self.pixels[i + c]) / 255.0;
+        [from_srgb(channel(0)), from_srgb(channel(1)), from_srgb(channel(2)), channel(3)]
+    }
+}
+
+fn from_srgb(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+#[allow(clippy::cast_possible_truncation)]
+#[allow(clippy::cast_sign_loss)]
+fn to_srgb(c: f32) -> u8 {
+    (encode_srgb(c) * 255.0).round() as u8
+}
+
+//a linear channel in [0, 1], encoded as sRGB.
+pub(crate) fn encode_srgb(c: f32) -> f32 {
+    let c = c.clamp(0.0, 1.0);
+    if c <= 0.003_130_8 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::material::{Material, Texture};
+
+    #[test]
+    fn base_color_times_texture() {
+        let texture = Texture {
+            width: 2,
+            height: 1,
+            pixels: vec![255, 255, 255, 255, 255, 0, 0, 128],
+        };
+        let material = Material {
+            color: [1.0, 0.5, 1.0, 1.0],
+            texture: Some(texture),
+        };
+        assert_eq!(material.color(None), [255, 188, 255, 255]);
+        assert_eq!(material.color(Some([0.25, 0.5])), [255, 188, 255, 255]);
+        assert_eq!(material.color(Some([0.75, 0.5])), [255, 0, 0, 128]);
+        //coordinates repeat.
+        assert_eq!(material.color(Some([-0.25, 3.0])), [255, 0, 0, 128]);
+    }
+}
@@ -0,0 +1,367 @@
+use crate::material::Material;
+use crate::{gltf, off, ply, stl, xyz};
+use serde::Serialize;
+use std::borrow::Cow;
+use std::fmt::Write;
+use std::io;
+use std::path::Path;
+use tobj::{Mesh, Model};
+
+//the mesh file formats that can be rendered. OBJ is read by tobj, and the others natively, into
+//the same triangulated models. glTF is a .gltf document or a binary .glb, and XYZ a point cloud.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum MeshFormat {
+    Obj,
+    Stl,
+    Ply,
+    Off,
+    Gltf,
+    Xyz,
+}
+
+impl MeshFormat {
+    pub(crate) fn from_name(name: &str) -> Option<MeshFormat> {
+        match name.to_ascii_lowercase().as_str() {
+            "obj" => Some(MeshFormat::Obj),
+            "stl" => Some(MeshFormat::Stl),
+            "ply" => Some(MeshFormat::Ply),
+            "off" => Some(MeshFormat::Off),
+            "gltf" | "glb" => Some(MeshFormat::Gltf),
+            "xyz" => Some(MeshFormat::Xyz),
+            _ => None,
+        }
+    }
+
+    //picks the format from a file's extension.
+    pub(crate) fn from_path(path: &str) -> Option<MeshFormat> {
+        Path::new(path)
+            .extension()
+            .and_then(|e| e.to_str())
+            .and_then(MeshFormat::from_name)
+    }
+
+    //recognizes a format by its first bytes, for standard input and files without a known
+    //extension. OBJ has no signature, so it is whatever the others are not, and neither has XYZ,
+    //which is only recognized by its extension.
+    pub(crate) fn from_contents(bytes: &[u8]) -> MeshFormat {
+        if bytes.starts_with(b"ply\n") || bytes.starts_with(b"ply\r\n") {
+            MeshFormat::Ply
+        } else if stl::is_binary(bytes) || bytes.starts_with(b"solid") {
+            MeshFormat::Stl
+        } else if off::is_off(bytes) {
+            MeshFormat::Off
+        } else if gltf::is_glb(bytes) || bytes.trim_ascii_start().starts_with(b"{") {
+            MeshFormat::Gltf
+        } else {
+            MeshFormat::Obj
+        }
+    }
+
+    //parses the contents of a mesh file into its models and the materials they refer to by
+    //material_id, which only glTF files have. OBJ material libraries and glTF buffers and
+    //images are looked for in `directory`, or not at all without one.
+    pub(crate) fn load(self, bytes: &[u8], directory: Option<&Path>) -> io::Result<(Vec<Model>, Vec<Material>)> {
+        self.load_dropping(bytes, directory, None)
+    }
+
+    //like load, but leaves out the faces that refer to vertices the file does not have, rather
+    //than failing on them, and returns them too, for --validate to report.
+    pub(crate) fn load_lenient(
+        self,
+        bytes: &[u8],
+        directory: Option<&Path>,
+    ) -> io::Result<(Vec<Model>, Vec<Material>, Vec<OutOfRange>)> {
+        let mut dropped = vec![];
+        let (models, materials) = self.load_dropping(bytes, directory, Some(&mut dropped))?;
+        Ok((models, materials, dropped))
+    }
+
+    //loads strictly without `dropped`, and leniently into it with.
+    fn load_dropping(
+        self,
+        bytes: &[u8],
+        directory: Option<&Path>,
+        dropped: Option<&mut Vec<OutOfRange>>,
+    ) -> io::Result<(Vec<Model>, Vec<Material>)> {
+        match self {
+            MeshFormat::Obj => Ok((load_obj(bytes, directory, dropped)?, vec![])),
+            MeshFormat::Stl => Ok((stl::load(bytes)?, vec![])),
+            MeshFormat::Ply => Ok((ply::load(bytes, dropped)?, vec![])),
+            MeshFormat::Off => Ok((off::load(bytes, dropped)?, vec![])),
+            MeshFormat::Gltf => gltf::load(bytes, directory, dropped),
+            MeshFormat::Xyz => Ok((xyz::load(bytes)?, vec![])),
+        }
+    }
+}
+
+//a face that refers to a vertex the file does not have: its number among the faces of the file,
+//in the order the file gives them, and the first such index, counted from 0. OBJ files number
+//their polylines and points along with their faces.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Serialize)]
+pub(crate) struct OutOfRange {
+    pub(crate) face: usize,
+    pub(crate) index: i64,
+}
+
+//what the indices of a model draw. Loaders give triangles, and OBJ and glTF files may also give
+//segments and points, in models of their own whose face_arities are all 2 or all 1.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub(crate) enum Primitive {
+    Triangles,
+    Lines,
+    Points,
+}
+
+impl Primitive {
+    pub(crate) fn of(mesh: &Mesh) -> Primitive {
+        match mesh.face_arities.first() {
+            Some(1) => Primitive::Points,
+            Some(2) => Primitive::Lines,
+            _ => Primitive::Triangles,
+        }
+    }
+}
+
+//faces are triangulated, and normals and texture coordinates share the position indices. Each
+//object's lines and points become models of their own.
+fn load_obj(bytes: &[u8], directory: Option<&Path>, dropped: Option<&mut Vec<OutOfRange>>) -> io::Result<Vec<Model>> {
+    let options = tobj::LoadOptions {
+        single_index: true,
+        ..tobj::LoadOptions::default()
+    };
+    let bytes = match dropped {
+        Some(dropped) => drop_out_of_range(bytes, dropped),
+        None => Cow::Borrowed(bytes),
+    };
+    let bytes = split_lines_and_points(&bytes);
+    let (models, _mats_result) = tobj::load_obj_buf(&mut bytes.as_ref(), &options, |file| match directory {
+        Some(directory) => tobj::load_mtl(directory.join(file)),
+        None => Err(tobj::LoadError::OpenFileFailed),
+    })
+    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    Ok(models.into_iter().flat_map(split_primitives).collect())
+}
+
+//blanks out the faces, polylines and points that refer to positions the file does not have,
+//which tobj fails on without saying which, and adds them to `dropped`.
+fn drop_out_of_range<'a>(bytes: &'a [u8], dropped: &mut Vec<OutOfRange>) -> Cow<'a, [u8]> {
+    let text = String::from_utf8_lossy(bytes);
+    let statement = |line: &str| line.split_whitespace().next().map(String::from);
+    let positions = text.lines().filter(|line| statement(line).as_deref() == Some("v")).count() as i64;
+    let (mut seen, mut face) = (0, 0);
+    let mut kept = String::with_capacity(text.len());
+    let mut changed = false;
+    for line in text.lines() {
+        match statement(line).as_deref() {
+            Some("v") => seen += 1,
+            Some("f" | "l" | "p") => {
+                //positions count from 1, or back from the latest one when negative.
+                let out_of_range = line
+                    .split_whitespace()
+                    .skip(1)
+                    .filter_map(|corner| corner.split('/').next()?.parse::<i64>().ok())
+                    .map(|index| if index < 0 { seen + index } else { index - 1 })
+                    .find(|index| !(0..positions).contains(index));
+                face += 1;
+                if let Some(index) = out_of_range {
+                    dropped.push(OutOfRange { face: face - 1, index });
+                    changed = true;
+                    kept.push('\n');
+                    continue;
+                }
+            }
+            _ => {}
+        }
+        kept.push_str(line);
+        kept.push('\n');
+    }
+    if changed {
+        Cow::Owned(kept.into_bytes())
+    } else {
+        Cow::Borrowed(bytes)
+    }
+}
+
+//tobj reads "l" statements of two vertices as segments, but longer polylines as polygons, and
+//skips "p" statements. Polylines are rewritten as their segments, and points as segments that
+//end where they start.
+fn split_lines_and_points(bytes: &[u8]) -> Cow<'_, [u8]> {
+    let text = String::from_utf8_lossy(bytes);
+    fn statement(line: &str) -> Option<&str> {
+        line.split_whitespace().next().filter(|&word| word == "l" || word == "p")
+    }
+    if !text.lines().any(|line| statement(line).is_some()) {
+        return Cow::Borrowed(bytes);
+    }
+    let mut split = String::with_capacity(text.len());
+    for line in text.lines() {
+        let corners: Vec<&str> = line.split_whitespace().skip(1).collect();
+        //writing to a String cannot fail.
+        let _ = match statement(line) {
+            Some("l") => corners.windows(2).try_for_each(|pair| writeln!(split, "l {} {}", pair[0], pair[1])),
+            Some(_) => corners.iter().try_for_each(|corner| writeln!(split, "l {corner} {corner}")),
+            None => writeln!(split, "{line}"),
+        };
+    }
+    Cow::Owned(split.into_bytes())
+}
+
+//splits a model that tobj did not triangulate into a model of its polygons, fanned out into
+//triangles as tobj would, and models of its segments and its points, leaving out any without
+//indices. All of them share the model's vertices.
+fn split_primitives(model: Model) -> Vec<Model> {
+    let mesh = &model.mesh;
+    //tobj leaves out the arities of meshes of triangles alone.
+    if mesh.face_arities.is_empty() {
+        return vec![model];
+    }
+    let (mut triangles, mut lines, mut points) = (vec![], vec![], vec![]);
+    let mut start = 0;
+    for &arity in &mesh.face_arities {
+        let face = &mesh.indices[start..start + arity as usize];
+        start += arity as usize;
+        match face {
+            [a] => points.push(*a),
+            [a, b] if a == b => points.push(*a),
+            [a, b] => lines.extend_from_slice(&[*a, *b]),
+            _ => {
+                for pair in face[1..].windows(2) {
+                    triangles.extend_from_slice(&[face[0], pair[0], pair[1]]);
+                }
+            }
+        }
+    }
+    [(triangles, 3), (lines, 2), (points, 1)]
+        .into_iter()
+        .filter(|(indices, _)| !indices.is_empty())
+        .map(|(indices, arity)| {
+            let face_arities = if arity == 3 { vec![] } else { vec![arity; indices.len() / arity as usize] };
+            let mesh = Mesh {
+                indices,
+                face_arities,
+                ..model.mesh.clone()
+            };
+            Model::new(mesh, model.name.clone())
+        })
+        .collect()
+}
+
+//what the native loaders report for files they cannot make sense of.
+pub(crate) fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+//a model of the loaders' positions and triangles, and RGB colors in [0, 1] for every vertex or
+//none. Models of files without names are named as tobj names them.
+pub(crate) fn model(positions: Vec<f32>, indices: Vec<u32>, vertex_color: Vec<f32>) -> Model {
+    let mesh = Mesh {
+        positions,
+        indices,
+        vertex_color,
+        ..Mesh::default()
+    };
+    Model::new(mesh, String::from("unnamed_object"))
+}
+
+//parses the "r g b" of a vertex in a text file, as the file gives them.
+pub(crate) fn parse_color(values: &[&str]) -> Option<[f32; 3]> {
+    let [r, g, b] = values else {
+        return None;
+    };
+    let color = [r, g, b].map(|value| value.parse::<f32>().ok());
+    Some([color[0]?, color[1]?, color[2]?])
+}
+
+//brings the colors of a text file's vertices into [0, 1]. A file gives them all in [0, 255] when
+//any is above 1, and all in [0, 1] otherwise, however each one is written. Anything outside
+//becomes the nearest end, and NaN becomes 0.
+pub(crate) fn scale_colors(colors: &mut [f32]) {
+    let scale = if colors.iter().any(|&c| c > 1.0) { 255.0 } else { 1.0 };
+    for c in colors {
+        *c = if c.is_nan() { 0.0 } else { (*c / scale).clamp(0.0, 1.0) };
+    }
+}
+
+//a model of points at every vertex, for files of vertices without faces.
+pub(crate) fn point_cloud(positions: Vec<f32>, vertex_color: Vec<f32>) -> io::Result<Model> {
+    let vertices = u32::try_from(positions.len() / 3).map_err(|_| invalid(String::from("too many vertices")))?;
+    let mut model = model(positions, (0..vertices).collect(), vertex_color);
+    model.mesh.face_arities = vec![1; vertices as usize];
+    Ok(model)
+}
+
+//adds the triangles of the polygon numbered `face`, fanning out from its first corner, after
+//checking that its corners are among the `vertices`. Polygons that are not are errors, or left
+//out and added to `dropped` with it.
+pub(crate) fn push_polygon(
+    indices: &mut Vec<u32>,
+    (face, polygon): (usize, &[u32]),
+    vertices: usize,
+    dropped: Option<&mut Vec<OutOfRange>>,
+) -> Result<(), String> {
+    if polygon.len() < 3 {
+        return Err(format!("a face needs at least 3 corners, not {}", polygon.len()));
+    }
+    if let Some(&corner) = polygon.iter().find(|&&corner| corner as usize >= vertices) {
+        let Some(dropped) = dropped else {
+            return Err(format!("vertex {corner} is out of range, with {vertices} vertices"));
+        };
+        dropped.push(OutOfRange {
+            face,
+            index: i64::from(corner),
+        });
+        return Ok(());
+    }
+    for pair in polygon[1..].windows(2) {
+        indices.extend_from_slice(&[polygon[0], pair[0], pair[1]]);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mesh::{push_polygon, MeshFormat, OutOfRange, Primitive};
+
+    #[test]
+    fn formats_from_names_and_contents() {
+        assert_eq!(MeshFormat::from_path("scan.PLY"), Some(MeshFormat::Ply));
+        assert_eq!(MeshFormat::from_path("scan"), None);
+        assert_eq!(MeshFormat::from_contents(b"ply\nformat ascii 1.0\n"), MeshFormat::Ply);
+        assert_eq!(MeshFormat::from_contents(b"solid part\n"), MeshFormat::Stl);
+        assert_eq!(MeshFormat::from_contents(b"# a comment\nOFF\n"), MeshFormat::Off);
+        assert_eq!(MeshFormat::from_contents(b"COFF 3 1 0\n"), MeshFormat::Off);
+        assert_eq!(MeshFormat::from_contents(b"v 0 0 0\n"), MeshFormat::Obj);
+        assert_eq!(MeshFormat::from_path("scene.glb"), Some(MeshFormat::Gltf));
+        assert_eq!(MeshFormat::from_contents(b"  {\"asset\": {}}"), MeshFormat::Gltf);
+        //a binary STL, whose header need not say so.
+        let mut binary = vec![b' '; 80];
+        binary.extend_from_slice(&1u32.to_le_bytes());
+        binary.extend_from_slice(&[0; 50]);
+        assert_eq!(MeshFormat::from_contents(&binary), MeshFormat::Stl);
+    }
+
+    #[test]
+    fn polygons_fan_into_triangles() {
+        let mut indices = vec![];
+        push_polygon(&mut indices, (0, &[0, 1, 2, 3]), 4, None).unwrap();
+        assert_eq!(indices, [0, 1, 2, 0, 2, 3]);
+        assert!(push_polygon(&mut indices, (1, &[0, 1, 4]), 4, None).is_err());
+        assert!(push_polygon(&mut indices, (1, &[0, 1]), 4, None).is_err());
+        let mut dropped = vec![];
+        push_polygon(&mut indices, (1, &[0, 1, 4]), 4, Some(&mut dropped)).unwrap();
+        assert_eq!(indices.len(), 6);
+        assert_eq!(dropped, [OutOfRange { face: 1, index: 4 }]);
+    }
+
+    #[test]
+    fn obj_lines_and_points() {
+        let text = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nf 1 2 3 4\nl 1 2 3\np 4 1\n";
+        let models = MeshFormat::Obj.load(text.as_bytes(), None).unwrap().0;
+        let primitives: Vec<_> = models.iter().map(|model| Primitive::of(&model.mesh)).collect();
+        assert_eq!(primitives, [Primitive::Triangles, Primitive::Lines, Primitive::Points]);
+        assert_eq!(models[0].mesh.indices, [0, 1, 2, 0, 2, 3]);
+        assert_eq!(models[1].mesh.indices, [0, 1, 1, 2]);
+        assert_eq!(models[2].mesh.indices, [3, 0]);
+        assert_eq!(models[2].mesh.positions, models[0].mesh.positions);
+    }
+}
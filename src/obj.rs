@@ -1,15 +1,76 @@
+use crate::camera::Pose;
+use crate::error::RenderError;
+use crate::material::Material;
+use crate::mesh::{MeshFormat, OutOfRange};
 use crate::space;
 use space::Transform;
+use std::io::{self, Read};
 use std::iter::{Skip, StepBy};
+use std::path::Path;
 use std::slice::{Iter, IterMut};
-use tobj::load_obj;
 use tobj::Model;
 
-pub(crate) fn get_mesh_data(handle: &str) -> Vec<Model> {
-    let (mut models, _mats_result) =
-        load_obj(handle, &tobj::LoadOptions::default()).expect("obj load error");
-    resize_obj(&mut models);
-    models
+//loads a mesh in the format of its extension, or else the one its contents look like, with the
+//materials its models refer to. A handle of "-" reads the mesh from standard input, without any
+//material libraries or external buffers.
+pub(crate) fn get_mesh_data(handle: &str) -> Result<(Vec<Model>, Vec<Material>), RenderError> {
+    load_mesh_data(handle).map_err(|error| RenderError::Mesh {
+        file: String::from(handle),
+        error,
+    })
+}
+
+//models and their materials, with the pose that takes each fitted model back to where the file
+//put it.
+type FittedMesh = (Vec<Model>, Vec<Material>, Vec<Pose>);
+
+//like get_mesh_data, with the fits of the models.
+pub(crate) fn get_fitted_mesh_data(handle: &str) -> Result<FittedMesh, RenderError> {
+    load_fitted_mesh_data(handle).map_err(|error| RenderError::Mesh {
+        file: String::from(handle),
+        error,
+    })
+}
+
+//like get_mesh_data, for callers that report the bare reason, as batches do.
+pub(crate) fn load_mesh_data(handle: &str) -> io::Result<(Vec<Model>, Vec<Material>)> {
+    load_fitted_mesh_data(handle).map(|(models, materials, _)| (models, materials))
+}
+
+//like get_mesh_data, without materials and leaving out the faces that refer to vertices the file
+//does not have, which come back too, for --validate to check the models as the file has them.
+pub(crate) fn get_lenient_mesh_data(handle: &str) -> Result<(Vec<Model>, Vec<OutOfRange>), RenderError> {
+    read_mesh(handle)
+        .and_then(|(bytes, directory, format)| format.load_lenient(&bytes, directory))
+        .map(|(models, _, out_of_range)| (models, out_of_range))
+        .map_err(|error| RenderError::Mesh {
+            file: String::from(handle),
+            error,
+        })
+}
+
+fn load_fitted_mesh_data(handle: &str) -> io::Result<FittedMesh> {
+    let (bytes, directory, format) = read_mesh(handle)?;
+    let (mut models, materials) = format.load(&bytes, directory)?;
+    //a glTF's nodes place its models relative to each other.
+    let fits = match format {
+        MeshFormat::Gltf => vec![resize_together(&mut models); models.len()],
+        _ => resize_obj(&mut models),
+    };
+    Ok((models, materials, fits))
+}
+
+//the bytes of a mesh, the directory its files are relative to, and its format.
+fn read_mesh(handle: &str) -> io::Result<(Vec<u8>, Option<&Path>, MeshFormat)> {
+    let (bytes, directory) = if handle == "-" {
+        let mut bytes = vec![];
+        std::io::stdin().lock().read_to_end(&mut bytes)?;
+        (bytes, None)
+    } else {
+        (std::fs::read(handle)?, Path::new(handle).parent())
+    };
+    let format = MeshFormat::from_path(handle).unwrap_or_else(|| MeshFormat::from_contents(&bytes));
+    Ok((bytes, directory, format))
 }
 
 fn get_min_max(model: &Model, offset: usize) -> (f32, f32) {
@@ -19,32 +80,47 @@ fn get_min_max(model: &Model, offset: usize) -> (f32, f32) {
 }
 
 //returns an iterator over a given dimension offset (0,1,2) referring to (x,y,z)
-fn get_vertices_of_dim(model: &Model, offset: usize) -> StepBy<Skip<Iter<f32>>> {
+fn get_vertices_of_dim(model: &Model, offset: usize) -> StepBy<Skip<Iter<'_, f32>>> {
     model.mesh.positions.iter().skip(offset).step_by(3)
 }
 
-fn get_mut_vertices_of_dim(model: &mut Model, offset: usize) -> StepBy<Skip<IterMut<f32>>> {
+fn get_mut_vertices_of_dim(model: &mut Model, offset: usize) -> StepBy<Skip<IterMut<'_, f32>>> {
     model.mesh.positions.iter_mut().skip(offset).step_by(3)
 }
 
-//modifies mesh positions in-place to be in the range [-1,1].
-pub(crate) fn resize_obj(obj: &mut [Model]) {
-    for model in obj.iter_mut() {
-        //find min and max of each dimension x,y,z
-        let x = get_min_max(model, 0);
-        let y = get_min_max(model, 1);
-        let z = get_min_max(model, 2);
-        //from these, compute necessary shift and scale for each dimension
-        let max_extent = get_max_extent(x, y, z);
-        let x_transform = Transform::from_extent(x.0, max_extent);
-        let y_transform = Transform::from_extent(y.0, max_extent);
-        let z_transform = Transform::from_extent(z.0, max_extent);
-        //shift and scale all vertices.
+//modifies mesh positions in-place to be in the range [-1,1], returning the pose that undoes it
+//for each model.
+pub(crate) fn resize_obj(obj: &mut [Model]) -> Vec<Pose> {
+    obj.iter_mut().map(|model| resize_together(std::slice::from_mut(model))).collect()
+}
 
+//like resize_obj, shifting and scaling every model alike, so that they keep their places.
+pub(crate) fn resize_together(models: &mut [Model]) -> Pose {
+    //find min and max of each dimension x,y,z
+    let bounds = |offset: usize| {
+        models
+            .iter()
+            .map(|model| get_min_max(model, offset))
+            .fold((f32::MAX, -f32::MAX), |acc, (min, max)| (acc.0.min(min), acc.1.max(max)))
+    };
+    let (x, y, z) = (bounds(0), bounds(1), bounds(2));
+    //from these, compute necessary shift and scale for each dimension
+    let max_extent = get_max_extent(x, y, z);
+    let x_transform = Transform::from_extent(x, max_extent);
+    let y_transform = Transform::from_extent(y, max_extent);
+    let z_transform = Transform::from_extent(z, max_extent);
+    //shift and scale all vertices.
+    for model in models.iter_mut() {
         get_mut_vertices_of_dim(model, 0).for_each(|f| *f = x_transform.apply(*f));
         get_mut_vertices_of_dim(model, 1).for_each(|f| *f = y_transform.apply(*f));
         get_mut_vertices_of_dim(model, 2).for_each(|f| *f = z_transform.apply(*f));
     }
+    //every axis shares the scale of the widest.
+    Pose {
+        position: [x_transform.shift, y_transform.shift, z_transform.shift],
+        scale: 1.0 / x_transform.scale,
+        ..Pose::IDENTITY
+    }
 }
 //return the widest difference in minimum and maximum's across all 3 dimensions.
 fn get_max_extent(x: (f32, f32), y: (f32, f32), z: (f32, f32)) -> f32 {
@@ -53,11 +129,14 @@ fn get_max_extent(x: (f32, f32), y: (f32, f32), z: (f32, f32)) -> f32 {
 //return shift and scale factors
 
 impl Transform {
-    fn from_extent(min: f32, extent: f32) -> Transform {
+    //axes without any width, like every axis of a single point, are centered rather than divided
+    //by their extent.
+    fn from_extent((min, max): (f32, f32), extent: f32) -> Transform {
         Transform {
             extent: extent as u64,
-            scale: 2.0 / extent,
-            shift: min + (extent / 2.0),
+            scale: if extent > 0.0 { 2.0 / extent } else { 1.0 },
+            shift: if max > min { min + (extent / 2.0) } else { min },
+            offset: 0,
         }
     }
     fn apply(&self, dimension: f32) -> f32 {
@@ -68,9 +147,9 @@ impl Transform {
 #[cfg(test)]
 mod tests {
 
-    use crate::obj::get_min_max;
-    use crate::resize_obj;
-    use tobj::{load_obj, LoadOptions};
+    use crate::mesh::MeshFormat;
+    use crate::obj::{get_min_max, resize_obj, resize_together};
+    use tobj::{load_obj, LoadOptions, Mesh, Model};
 
     #[test]
     fn chunked_access_eq_to_skipping_access() {
@@ -100,4 +179,31 @@ mod tests {
             assert!(a.mesh.positions.iter().all(|&f| (-1.0..=1.0).contains(&f)));
         }
     }
+
+    #[test]
+    fn resize_together_keeps_places() {
+        let model = |x: f32| {
+            let mesh = Mesh {
+                positions: vec![x, 0.0, 0.0, x + 1.0, 1.0, 1.0],
+                ..Mesh::default()
+            };
+            Model::new(mesh, String::new())
+        };
+        let mut models = vec![model(0.0), model(3.0)];
+        let fit = resize_together(&mut models);
+        assert_eq!(models[0].mesh.positions[..3], [-1.0, -1.0, -1.0]);
+        assert_eq!(models[1].mesh.positions[3..], [1.0, -0.5, -0.5]);
+        //and the fit puts them back.
+        assert_eq!(fit.apply([1.0, -0.5, -0.5]), [4.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn flat_clouds_are_centered() {
+        let (mut models, _) = MeshFormat::Xyz.load(b"5 6 7\n", None).unwrap();
+        resize_obj(&mut models);
+        assert_eq!(models[0].mesh.positions, [0.0, 0.0, 0.0]);
+        let (mut models, _) = MeshFormat::Xyz.load(b"0 0 0\n1 0 0\n", None).unwrap();
+        resize_obj(&mut models);
+        assert_eq!(models[0].mesh.positions, [-1.0, 0.0, 0.0, 1.0, 0.0, 0.0]);
+    }
 }
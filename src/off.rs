@@ -0,0 +1,125 @@
+use crate::mesh::{invalid, model, parse_color, push_polygon, scale_colors, OutOfRange};
+use std::io;
+use tobj::Model;
+
+//whether the file starts with an OFF header, after any comments.
+pub(crate) fn is_off(bytes: &[u8]) -> bool {
+    let start = &bytes[..bytes.len().min(1024)];
+    let text = String::from_utf8_lossy(start);
+    let mut lines = text.lines().map(|line| line.split('#').next().unwrap_or_default().trim());
+    lines
+        .find(|line| !line.is_empty())
+        .and_then(|line| line.split_whitespace().next())
+        .is_some_and(|word| word == "OFF" || word == "COFF")
+}
+
+//an "OFF" header, or "COFF" when vertices have colors, then the vertex, face and edge counts,
+//each vertex as "x y z" (and "r g b a" for COFF, in [0, 255] or [0, 1]), and each face as its number of corners and the
+//corners. Anything after a '#' is a comment, and colors of faces are ignored. Faces with corners
+//past the vertices are errors, or `dropped` with it.
+pub(crate) fn load(bytes: &[u8], mut dropped: Option<&mut Vec<OutOfRange>>) -> io::Result<Vec<Model>> {
+    let text = std::str::from_utf8(bytes).map_err(|_| invalid(String::from("an OFF file must be text")))?;
+    let mut lines = text
+        .lines()
+        .enumerate()
+        .map(|(number, line)| (number + 1, line.split('#').next().unwrap_or_default()))
+        .filter(|(_, line)| !line.trim().is_empty());
+    let ends_early = || invalid(String::from("the file ends early"));
+    let (number, header) = lines.next().ok_or_else(ends_early)?;
+    let mut words = header.split_whitespace();
+    let colored = match words.next() {
+        Some("OFF") => false,
+        Some("COFF") => true,
+        _ => return Err(invalid(format!("line {number}: expected OFF or COFF"))),
+    };
+    //the counts may share the header's line.
+    let (number, counts) = match words.next() {
+        Some(_) => (number, header.split_whitespace().skip(1).collect::<Vec<_>>()),
+        None => {
+            let (number, line) = lines.next().ok_or_else(ends_early)?;
+            (number, line.split_whitespace().collect())
+        }
+    };
+    let error = |number: usize, message: &str| invalid(format!("line {number}: {message}"));
+    let (vertices, faces) = match counts[..] {
+        [vertices, faces, ..] => match (vertices.parse::<usize>(), faces.parse::<usize>()) {
+            (Ok(vertices), Ok(faces)) => (vertices, faces),
+            _ => return Err(error(number, "expected the vertex, face and edge counts")),
+        },
+        _ => return Err(error(number, "expected the vertex, face and edge counts")),
+    };
+    //the counts are only trusted as far as the file could hold them, at a byte or more per line.
+    let reserve = |count: usize| count.min(bytes.len()) * 3;
+    let mut positions = Vec::with_capacity(reserve(vertices));
+    let mut colors = vec![];
+    for _ in 0..vertices {
+        let (number, line) = lines.next().ok_or_else(ends_early)?;
+        let values: Vec<&str> = line.split_whitespace().collect();
+        let parse = |values: &[&str]| values.iter().map(|v| v.parse::<f32>()).collect::<Result<Vec<_>, _>>();
+        match (values.get(..3).map(parse), colored) {
+            (Some(Ok(position)), _) => positions.extend(position),
+            _ => return Err(error(number, "expected x y z")),
+        }
+        if colored {
+            let Some(color) = values.get(3..6).and_then(parse_color) else {
+                return Err(error(number, "expected x y z r g b a"));
+            };
+            colors.extend(color);
+        }
+    }
+    let mut indices = Vec::with_capacity(reserve(faces));
+    for face in 0..faces {
+        let (number, line) = lines.next().ok_or_else(ends_early)?;
+        let mut values = line.split_whitespace().map(|v| v.parse::<u32>());
+        let Some(Ok(corners)) = values.next() else {
+            return Err(error(number, "expected the number of corners"));
+        };
+        let polygon = values
+            .take(corners as usize)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| error(number, "expected the corners"))?;
+        if polygon.len() != corners as usize {
+            return Err(error(number, &format!("expected {corners} corners")));
+        }
+        push_polygon(&mut indices, (face, &polygon), vertices, dropped.as_deref_mut()).map_err(|e| error(number, &e))?;
+    }
+    scale_colors(&mut colors);
+    Ok(vec![model(positions, indices, colors)])
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::off::load;
+
+    #[test]
+    fn polygons_and_colors() {
+        let text = "# a square\nOFF\n4 1 0\n0 0 0\n1 0 0\n1 1 0\n0 1 0\n4 0 1 2 3 # a face\n";
+        let mesh = &load(text.as_bytes(), None).unwrap()[0].mesh;
+        assert_eq!(mesh.indices, [0, 1, 2, 0, 2, 3]);
+        assert!(mesh.vertex_color.is_empty());
+
+        let text = "COFF 3 1 0\n0 0 0 255 0 0 255\n1 0 0 0 51 0 255\n0 1 0 0 0 255 255\n3 0 1 2\n";
+        let mesh = &load(text.as_bytes(), None).unwrap()[0].mesh;
+        assert_eq!(mesh.vertex_color[..6], [1.0, 0.0, 0.0, 0.0, 0.2, 0.0]);
+
+        //the file decides the scale, not how each vertex writes its numbers.
+        let text = "COFF 3 1 0\n0 0 0 1 1 1 1\n1 0 0 0.5 0.5 0.5 1\n0 1 0 1 0 0 1\n3 0 1 2\n";
+        let mesh = &load(text.as_bytes(), None).unwrap()[0].mesh;
+        assert_eq!(mesh.vertex_color, [1.0, 1.0, 1.0, 0.5, 0.5, 0.5, 1.0, 0.0, 0.0]);
+        let text = "COFF 2 0 0\n0 0 0 255 0 0 255\n1 0 0 -3 51.0 300 255\n";
+        let mesh = &load(text.as_bytes(), None).unwrap()[0].mesh;
+        assert_eq!(mesh.vertex_color, [1.0, 0.0, 0.0, 0.0, 0.2, 1.0]);
+    }
+
+    #[test]
+    fn reports_lines() {
+        let text = "OFF\n3 1 0\n0 0 0\n1 0 0\n0 1 0\n3 0 1 3\n";
+        assert_eq!(load(text.as_bytes(), None).unwrap_err().to_string(), "line 6: vertex 3 is out of range, with 3 vertices");
+        let text = "OFF\n3 1 0\n0 0 0\n1 0\n";
+        assert_eq!(load(text.as_bytes(), None).unwrap_err().to_string(), "line 4: expected x y z");
+        for counts in ["9999999999999999999 0 0", "3000000000000000000 0 0", "0 3000000000000000000 0"] {
+            let text = format!("OFF\n{counts}\n");
+            assert_eq!(load(text.as_bytes(), None).unwrap_err().to_string(), "the file ends early", "{counts}");
+        }
+    }
+}
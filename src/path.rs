@@ -0,0 +1,298 @@
+use crate::camera::{lerp, Camera, Pose, Projection, Quat, Vec3};
+use std::io;
+
+//how poses between keyframes are found.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Interpolation {
+    //straight lines between positions, and normalized linear blends of rotations.
+    Linear,
+    //straight lines between positions, and rotations at constant angular speed.
+    Slerp,
+    //Catmull-Rom splines through positions, which pass through every key smoothly, and slerp.
+    CatmullRom,
+}
+
+impl Interpolation {
+    fn from_name(name: &str) -> Option<Interpolation> {
+        match name {
+            "linear" => Some(Interpolation::Linear),
+            "slerp" => Some(Interpolation::Slerp),
+            "catmull-rom" => Some(Interpolation::CatmullRom),
+            _ => None,
+        }
+    }
+}
+
+//the most frames a path is sampled into, which paths of days of keys would otherwise exceed.
+const MAX_FRAMES: f64 = 100_000.0;
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+struct Keyframe {
+    //seconds.
+    time: f32,
+    pose: Pose,
+}
+
+//camera and model poses over time, read from a keyframe file of one statement per line:
+//
+//  interpolation linear|slerp|catmull-rom
+//  projection orthographic <half height> | perspective <vertical fov in degrees>
+//  camera <time> position <x y z> (target <x y z> | rotation <w x y z>)
+//  model <time> [position <x y z>] [rotation <w x y z>] [scale <s>]
+//
+//times are in seconds, and keys of each track are sorted by them. Blank lines and anything
+//after a '#' are ignored.
+#[derive(Debug, PartialEq)]
+pub(crate) struct CameraPath {
+    interpolation: Interpolation,
+    projection: Projection,
+    camera: Vec<Keyframe>,
+    model: Vec<Keyframe>,
+}
+
+impl CameraPath {
+    pub(crate) fn from_file(file: &str) -> io::Result<CameraPath> {
+        let text = std::fs::read_to_string(file)?;
+        CameraPath::parse(&text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    pub(crate) fn parse(text: &str) -> Result<CameraPath, String> {
+        let mut path = CameraPath {
+            interpolation: Interpolation::Linear,
+            projection: Camera::default().projection,
+            camera: vec![],
+            model: vec![],
+        };
+        for (number, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default();
+            let mut words = line.split_whitespace();
+            let Some(statement) = words.next() else {
+                continue;
+            };
+            let error = |message: &str| format!("line {}: {message}", number + 1);
+            match statement {
+                "interpolation" => {
+                    path.interpolation = words
+                        .next()
+                        .and_then(Interpolation::from_name)
+                        .ok_or_else(|| error("expected linear, slerp or catmull-rom"))?;
+                }
+                "projection" => {
+                    path.projection = match (words.next(), numbers::<1>(&mut words)) {
+                        (Some("orthographic"), Some([half_height])) if half_height > 0.0 => {
+                            Projection::Orthographic(half_height)
+                        }
+                        (Some("perspective"), Some([fov])) if fov > 0.0 && fov < 180.0 => {
+                            Projection::Perspective(fov.to_radians())
+                        }
+                        _ => return Err(error("expected orthographic <half height> or perspective <fov>")),
+                    };
+                }
+                "camera" | "model" => {
+                    let time = numbers::<1>(&mut words).ok_or_else(|| error("expected a time"))?[0];
+                    let mut pose = if statement == "camera" {
+                        Camera::default().pose
+                    } else {
+                        Pose::IDENTITY
+                    };
+                    let mut target = None;
+                    while let Some(key) = words.next() {
+                        match key {
+                            "position" => pose.position = numbers(&mut words).ok_or_else(|| error("expected x y z"))?,
+                            "target" => target = Some(numbers::<3>(&mut words).ok_or_else(|| error("expected x y z"))?),
+                            "rotation" => {
+                                let [w, x, y, z] = numbers(&mut words).ok_or_else(|| error("expected w x y z"))?;
+                                pose.rotation = Quat::new(w, x, y, z);
+                            }
+                            "scale" if statement == "model" => {
+                                pose.scale = numbers::<1>(&mut words).ok_or_else(|| error("expected a scale"))?[0];
+                            }
+                            _ => return Err(error(&format!("unexpected {key}"))),
+                        }
+                    }
+                    if let Some(target) = target {
+                        if target == pose.position {
+                            return Err(error("the camera cannot look at its own position"));
+                        }
+                        pose.rotation = Quat::look_at(pose.position, target);
+                    }
+                    let track = if statement == "camera" {
+                        &mut path.camera
+                    } else {
+                        &mut path.model
+                    };
+                    track.push(Keyframe { time, pose });
+                }
+                _ => return Err(error(&format!("unknown statement {statement}"))),
+            }
+        }
+        if path.camera.is_empty() && path.model.is_empty() {
+            return Err(String::from("no camera or model keyframes"));
+        }
+        path.camera.sort_by(|a, b| a.time.total_cmp(&b.time));
+        path.model.sort_by(|a, b| a.time.total_cmp(&b.time));
+        Ok(path)
+    }
+
+    //the first and last keyframe times of either track.
+    fn span(&self) -> (f32, f32) {
+        self.camera
+            .iter()
+            .chain(&self.model)
+            .fold((f32::MAX, -f32::MAX), |(start, end), key| {
+                (start.min(key.time), end.max(key.time))
+            })
+    }
+
+    //the camera, and the pose of the models, at `time` seconds.
+    pub(crate) fn sample(&self, time: f32) -> (Camera, Pose) {
+        let camera = Camera {
+            pose: sample(&self.camera, time, self.interpolation).unwrap_or(Camera::default().pose),
+            projection: self.projection,
+        };
+        let model = sample(&self.model, time, self.interpolation).unwrap_or(Pose::IDENTITY);
+        (camera, model)
+    }
+
+    //samples the path every `delay` milliseconds, from its first key up to its last, unless that
+    //takes more than MAX_FRAMES frames.
+    pub(crate) fn frames(&self, delay: u16) -> io::Result<impl ExactSizeIterator<Item = (Camera, Pose)> + '_> {
+        let (start, end) = self.span();
+        let step = f32::from(delay.max(1)) / 1000.0;
+        let frames = ((f64::from(end) - f64::from(start)) / f64::from(step) + 1e-3).floor() + 1.0;
+        if frames > MAX_FRAMES {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("the path takes more than the {MAX_FRAMES} frames allowed"),
+            ));
+        }
+        #[allow(clippy::cast_possible_truncation)]
+        #[allow(clippy::cast_sign_loss)]
+        let count = frames as usize;
+        Ok((0..count).map(move |frame| self.sample(start + frame as f32 * step)))
+    }
+}
+
+//the next `N` words as finite numbers.
+fn numbers<'a, const N: usize>(words: &mut impl Iterator<Item = &'a str>) -> Option<[f32; N]> {
+    let mut values = [0.0; N];
+    for value in &mut values {
+        *value = words.next()?.parse().ok().filter(|value: &f32| value.is_finite())?;
+    }
+    Some(values)
+}
+
+//the pose along a track, held at its ends, or None for an empty track.
+fn sample(track: &[Keyframe], time: f32, interpolation: Interpolation) -> Option<Pose> {
+    let first = track.first()?;
+    let last = track.last()?;
+    if time <= first.time {
+        return Some(first.pose);
+    }
+    if time >= last.time {
+        return Some(last.pose);
+    }
+    //the segment from key i to key i + 1 contains time.
+    let i = track.partition_point(|key| key.time <= time) - 1;
+    let (from, to) = (&track[i], &track[i + 1]);
+    let t = (time - from.time) / (to.time - from.time);
+    let position = match interpolation {
+        Interpolation::Linear | Interpolation::Slerp => lerp(from.pose.position, to.pose.position, t),
+        Interpolation::CatmullRom => {
+            //the keys either side of the segment, repeating the ends.
+            let before = track[i.saturating_sub(1)].pose.position;
+            let after = track[(i + 2).min(track.len() - 1)].pose.position;
+            catmull_rom(before, from.pose.position, to.pose.position, after, t)
+        }
+    };
+    let rotation = match interpolation {
+        Interpolation::Linear => from.pose.rotation.nlerp(to.pose.rotation, t),
+        Interpolation::Slerp | Interpolation::CatmullRom => from.pose.rotation.slerp(to.pose.rotation, t),
+    };
+    Some(Pose {
+        position,
+        rotation,
+        scale: from.pose.scale + (to.pose.scale - from.pose.scale) * t,
+    })
+}
+
+//the uniform Catmull-Rom spline from p1 to p2.
+fn catmull_rom(p0: Vec3, p1: Vec3, p2: Vec3, p3: Vec3, t: f32) -> Vec3 {
+    let (t2, t3) = (t * t, t * t * t);
+    [0, 1, 2].map(|i| {
+        0.5 * (2.0 * p1[i]
+            + (p2[i] - p0[i]) * t
+            + (2.0 * p0[i] - 5.0 * p1[i] + 4.0 * p2[i] - p3[i]) * t2
+            + (3.0 * p1[i] - p0[i] - 3.0 * p2[i] + p3[i]) * t3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::camera::{Camera, Projection};
+    use crate::path::{catmull_rom, CameraPath};
+
+    const PATH: &str = "
+        # orbit a quarter turn, then hold.
+        interpolation slerp
+        projection perspective 60
+        camera 0 position 0 0 2 target 0 0 0
+        camera 1 position 2 0 0 target 0 0 0
+        model 0.5 scale 2 # a single key holds for the whole path
+    ";
+
+    fn close(l: &[f32], r: &[f32]) -> bool {
+        l.iter().zip(r).all(|(l, r)| (l - r).abs() < 1e-5)
+    }
+
+    #[test]
+    fn parses_keyframes() {
+        let path = CameraPath::parse(PATH).unwrap();
+        assert_eq!(path.projection, Projection::Perspective(60f32.to_radians()));
+        let (camera, model) = path.sample(0.5);
+        assert_eq!(model.scale, 2.0);
+        //halfway round, the camera still faces the origin.
+        let forward = camera.pose.rotation.rotate([0.0, 0.0, -1.0]);
+        assert!(close(&forward, &[-0.5f32.sqrt(), 0.0, -0.5f32.sqrt()]));
+        assert!(close(&camera.pose.position, &[1.0, 0.0, 1.0]));
+    }
+
+    #[test]
+    fn frame_count_covers_span() {
+        let path = CameraPath::parse(PATH).unwrap();
+        assert_eq!(path.frames(250).unwrap().len(), 5);
+        let (last, _) = path.frames(250).unwrap().last().unwrap();
+        assert!(close(&last.pose.position, &[2.0, 0.0, 0.0]));
+        let (first, _) = CameraPath::parse("model 0").unwrap().sample(0.0);
+        assert_eq!(first, Camera::default());
+        let path = CameraPath::parse("model 0\nmodel 3e38\nmodel -3e38").unwrap();
+        assert!(path.frames(1).is_err());
+    }
+
+    #[test]
+    fn reports_bad_lines() {
+        assert_eq!(
+            CameraPath::parse("interpolation cubic"),
+            Err(String::from("line 1: expected linear, slerp or catmull-rom"))
+        );
+        assert_eq!(
+            CameraPath::parse("camera 0\ncamera 1 position 0 0"),
+            Err(String::from("line 2: expected x y z"))
+        );
+        assert!(CameraPath::parse("# nothing").is_err());
+        assert_eq!(
+            CameraPath::parse("camera inf position 0 0 3 target 0 0 0"),
+            Err(String::from("line 1: expected a time"))
+        );
+        assert_eq!(CameraPath::parse("model 0 scale NaN"), Err(String::from("line 1: expected a scale")));
+    }
+
+    #[test]
+    fn catmull_rom_passes_through_keys() {
+        let points = [[0.0, 0.0, 0.0], [1.0, 1.0, 0.0], [2.0, 0.0, 0.0], [3.0, 1.0, 0.0]];
+        let [p0, p1, p2, p3] = points;
+        assert!(close(&catmull_rom(p0, p1, p2, p3, 0.0), &p1));
+        assert!(close(&catmull_rom(p0, p1, p2, p3, 1.0), &p2));
+        assert!(close(&catmull_rom(p0, p1, p2, p3, 0.5), &[1.5, 0.5, 0.0]));
+    }
+}
@@ -0,0 +1,316 @@
+use crate::camera::{ModelView, Pose, Vec3};
+use crate::mesh::Primitive;
+use crate::point::Point;
+use crate::space::Space;
+use tobj::Model;
+
+//triangles per leaf before a node stops splitting.
+const LEAF_SIZE: usize = 4;
+
+//the closest triangle under a pixel, with the barycentric weights of the hit point on it, and
+//that point in world space, where the mesh file put it before it was fitted to the view.
+#[derive(Debug)]
+pub(crate) struct Hit {
+    pub(crate) model: usize,
+    pub(crate) triangle: usize,
+    pub(crate) barycentric: [f32; 3],
+    pub(crate) position: Point,
+    //the window z of the hit, which the rasterizer keeps the largest of.
+    depth: f32,
+}
+
+#[derive(Debug)]
+struct PickTriangle {
+    model: usize,
+    index: usize,
+    //in window coordinates, as rasterize() draws them.
+    vertices: [Point; 3],
+    //in world space.
+    world: [Vec3; 3],
+    //what perspective divided each vertex by.
+    distances: [f32; 3],
+}
+
+impl PickTriangle {
+    fn centroid(&self, axis: usize) -> f32 {
+        self.vertices.iter().map(|p| p.axis(axis)).sum::<f32>() / 3.0
+    }
+    //the view ray is parallel to z, so the intersection is a 2D point-in-triangle test in x and y,
+    //followed by interpolating z with the resulting barycentric weights.
+    fn intersect(&self, x: f32, y: f32) -> Option<([f32; 3], f32)> {
+        let [a, b, c] = self.vertices;
+        let area = (b.x - a.x) * (c.y - a.y) - (c.x - a.x) * (b.y - a.y);
+        if area == 0.0 {
+            return None; //degenerate, or seen edge-on.
+        }
+        let beta = ((x - a.x) * (c.y - a.y) - (c.x - a.x) * (y - a.y)) / area;
+        let gamma = ((b.x - a.x) * (y - a.y) - (x - a.x) * (b.y - a.y)) / area;
+        let alpha = 1.0 - beta - gamma;
+        let range = 0f32..=1f32;
+        if range.contains(&alpha) && range.contains(&beta) && range.contains(&gamma) {
+            Some(([alpha, beta, gamma], alpha * a.z + beta * b.z + gamma * c.z))
+        } else {
+            None
+        }
+    }
+    //the hit at the given weights within the window, which perspective skews from the weights on
+    //the triangle itself.
+    fn hit(&self, window: [f32; 3], depth: f32) -> Hit {
+        let weights: [f32; 3] = std::array::from_fn(|i| window[i] / self.distances[i]);
+        let total: f32 = weights.iter().sum();
+        let barycentric = weights.map(|weight| weight / total);
+        let axis = |axis: usize| (0..3).map(|i| barycentric[i] * self.world[i][axis]).sum();
+        Hit {
+            model: self.model,
+            triangle: self.index,
+            barycentric,
+            position: Point {
+                x: axis(0),
+                y: axis(1),
+                z: axis(2),
+            },
+            depth,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Aabb {
+    min: [f32; 3],
+    max: [f32; 3],
+}
+
+impl Aabb {
+    fn empty() -> Self {
+        Aabb {
+            min: [f32::MAX; 3],
+            max: [-f32::MAX; 3],
+        }
+    }
+    fn grow(&mut self, p: Point) {
+        for axis in 0..3 {
+            self.min[axis] = self.min[axis].min(p.axis(axis));
+            self.max[axis] = self.max[axis].max(p.axis(axis));
+        }
+    }
+    fn contains_xy(&self, x: f32, y: f32) -> bool {
+        (self.min[0]..=self.max[0]).contains(&x) && (self.min[1]..=self.max[1]).contains(&y)
+    }
+    fn widest_axis(&self) -> usize {
+        let extent = |axis: usize| self.max[axis] - self.min[axis];
+        (0..3)
+            .max_by(|&l, &r| extent(l).total_cmp(&extent(r)))
+            .unwrap_or(0)
+    }
+}
+
+#[derive(Debug)]
+enum Node {
+    Leaf { bounds: Aabb, start: usize, end: usize },
+    Inner { bounds: Aabb, left: usize, right: usize },
+}
+
+impl Node {
+    fn bounds(&self) -> &Aabb {
+        match self {
+            Node::Leaf { bounds, .. } | Node::Inner { bounds, .. } => bounds,
+        }
+    }
+}
+
+//bounding volume hierarchy over every triangle of every model, in the same window coordinates
+//that rasterize() draws them at through `view`, so that rays cast straight down z see what the
+//camera does. `fits` take each fitted model back into world space.
+#[derive(Debug)]
+pub(crate) struct Bvh {
+    nodes: Vec<Node>,
+    triangles: Vec<PickTriangle>,
+}
+
+impl Bvh {
+    pub(crate) fn new(models: &[Model], fits: &[Pose], view: &ModelView) -> Self {
+        let mut triangles = vec![];
+        for (model_idx, (model, fit)) in models.iter().zip(fits).enumerate() {
+            //lines and points have no area to pick.
+            if Primitive::of(&model.mesh) != Primitive::Triangles {
+                continue;
+            }
+            let vertices = &model.mesh.positions;
+            let vertex = |i: u32| {
+                let i = i as usize * 3;
+                [vertices[i], vertices[i + 1], vertices[i + 2]]
+            };
+            for (index, tri) in model.mesh.indices.chunks_exact(3).enumerate() {
+                let mesh = [vertex(tri[0]), vertex(tri[1]), vertex(tri[2])];
+                //the rasterizer drops triangles too close to a perspective camera, too.
+                let [Some(a), Some(b), Some(c)] = mesh.map(|v| view.position(v)) else {
+                    continue;
+                };
+                triangles.push(PickTriangle {
+                    model: model_idx,
+                    index,
+                    vertices: [a, b, c].map(|[x, y, z]| Point { x, y, z }),
+                    world: mesh.map(|v| fit.apply(v)),
+                    distances: mesh.map(|v| view.distance(v)),
+                });
+            }
+        }
+        let mut bvh = Bvh {
+            nodes: vec![],
+            triangles,
+        };
+        if !bvh.triangles.is_empty() {
+            bvh.build(0, bvh.triangles.len());
+        }
+        bvh
+    }
+
+    //recursively splits triangles[start..end] at the median centroid of the widest axis,
+    //returning the index of the node that covers them.
+    fn build(&mut self, start: usize, end: usize) -> usize {
+        let mut bounds = Aabb::empty();
+        for tri in &self.triangles[start..end] {
+            tri.vertices.iter().for_each(|&p| bounds.grow(p));
+        }
+        let node_idx = self.nodes.len();
+        if end - start <= LEAF_SIZE {
+            self.nodes.push(Node::Leaf { bounds, start, end });
+            return node_idx;
+        }
+        let axis = bounds.widest_axis();
+        let mid = start + (end - start) / 2;
+        self.triangles[start..end].select_nth_unstable_by(mid - start, |l, r| {
+            l.centroid(axis).total_cmp(&r.centroid(axis))
+        });
+        //reserve this node's slot so that children are appended after it.
+        self.nodes.push(Node::Leaf { bounds, start, end });
+        let left = self.build(start, mid);
+        let right = self.build(mid, end);
+        self.nodes[node_idx] = Node::Inner {
+            bounds,
+            left,
+            right,
+        };
+        node_idx
+    }
+
+    //casts a ray through the given window coordinates, returning the hit closest to the viewer.
+    fn cast(&self, x: f32, y: f32) -> Option<Hit> {
+        let mut best: Option<Hit> = None;
+        let mut stack = if self.nodes.is_empty() { vec![] } else { vec![0] };
+        while let Some(node_idx) = stack.pop() {
+            let node = &self.nodes[node_idx];
+            //skip nodes the ray misses, or whose nearest point is behind the current best hit.
+            if !node.bounds().contains_xy(x, y)
+                || best
+                    .as_ref()
+                    .is_some_and(|hit| node.bounds().max[2] < hit.depth)
+            {
+                continue;
+            }
+            match *node {
+                Node::Inner { left, right, .. } => {
+                    stack.push(left);
+                    stack.push(right);
+                }
+                Node::Leaf { start, end, .. } => {
+                    for tri in &self.triangles[start..end] {
+                        if let Some((barycentric, z)) = tri.intersect(x, y) {
+                            //the rasterizer keeps the smallest depth, which is the largest z.
+                            if best.as_ref().is_none_or(|hit| z > hit.depth) {
+                                best = Some(tri.hit(barycentric, z));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        best
+    }
+}
+
+//returns the triangle visible at pixel (x, y) of the image described by space.
+pub(crate) fn pick(bvh: &Bvh, space: &Space, x: u64, y: u64) -> Option<Hit> {
+    let (window_x, window_y) = space.pixel_to_window(x, y);
+    bvh.cast(window_x, window_y)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::camera::{Camera, ModelView, Pose, Projection};
+    use crate::obj::get_fitted_mesh_data;
+    use crate::pick::{pick, Bvh};
+    use crate::space::Space;
+    use std::f32::consts::FRAC_PI_2;
+    use std::num::NonZeroU64;
+
+    fn space(extent: u64) -> Space {
+        Space::new(
+            NonZeroU64::new(extent).unwrap(),
+            NonZeroU64::new(extent).unwrap(),
+        )
+        .unwrap()
+    }
+
+    fn bvh(file: &str, camera: &Camera) -> Bvh {
+        let (models, _, fits) = get_fitted_mesh_data(file).unwrap();
+        Bvh::new(&models, &fits, &ModelView::new(camera, &Pose::IDENTITY))
+    }
+
+    fn close(l: &[f32], r: &[f32]) -> bool {
+        l.iter().zip(r).all(|(l, r)| (l - r).abs() < 1e-4)
+    }
+
+    #[test]
+    fn hit_center_of_cube() {
+        let bvh = bvh("./tests/resources/cube.obj", &Camera::default());
+        let hit = pick(&bvh, &space(100), 50, 50).expect("the cube covers the image center");
+        assert!((hit.position.z - 1.0).abs() < 1e-4, "the front face is at z = 1");
+        assert!((hit.barycentric.iter().sum::<f32>() - 1.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn miss_outside_mesh() {
+        let bvh = bvh("./tests/resources/tri.obj", &Camera::default());
+        assert!(pick(&bvh, &space(100), 99, 99).is_none());
+    }
+
+    #[test]
+    fn positions_are_in_world_space() {
+        //the triangle from (0, 0, 0) over (5, 5, 5) to (10, 0, 0) is fitted to the view from
+        //(-1, -1, -1) over the origin to (1, -1, -1).
+        let hit = bvh("./tests/resources/tri.obj", &Camera::default()).cast(0.0, -0.5).unwrap();
+        let p = hit.position;
+        assert!(close(&[p.x, p.y, p.z], &[5.0, 2.5, 2.5]));
+        assert!(close(&hit.barycentric, &[0.25, 0.5, 0.25]));
+
+        //in perspective, the far corners shrink towards the middle, and the same point on the
+        //triangle is seen further down than it would be without.
+        let camera = Camera {
+            projection: Projection::Perspective(FRAC_PI_2),
+            ..Camera::default()
+        };
+        let hit = bvh("./tests/resources/tri.obj", &camera).cast(0.0, -0.2).unwrap();
+        let p = hit.position;
+        assert!(close(&[p.x, p.y, p.z], &[5.0, 2.5, 2.5]));
+        assert!(close(&hit.barycentric, &[0.25, 0.5, 0.25]));
+    }
+
+    #[test]
+    fn bvh_matches_brute_force() {
+        let bvh = bvh("./tests/resources/bunny.obj", &Camera::default());
+        let space = space(40);
+        for y in 0..40 {
+            for x in 0..40 {
+                let (wx, wy) = space.pixel_to_window(x, y);
+                let brute = bvh
+                    .triangles
+                    .iter()
+                    .filter_map(|tri| tri.intersect(wx, wy))
+                    .map(|(_, z)| z)
+                    .fold(None, |best: Option<f32>, z| Some(best.map_or(z, |b| b.max(z))));
+                assert_eq!(pick(&bvh, &space, x, y).map(|hit| hit.depth), brute);
+            }
+        }
+    }
+}
@@ -0,0 +1,298 @@
+use crate::mesh::{invalid, model, point_cloud, push_polygon, OutOfRange};
+use std::io;
+use std::str::SplitAsciiWhitespace;
+use tobj::Model;
+
+//the numeric types of PLY properties.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+enum Scalar {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl Scalar {
+    fn from_name(name: &str) -> Option<Scalar> {
+        match name {
+            "char" | "int8" => Some(Scalar::I8),
+            "uchar" | "uint8" => Some(Scalar::U8),
+            "short" | "int16" => Some(Scalar::I16),
+            "ushort" | "uint16" => Some(Scalar::U16),
+            "int" | "int32" => Some(Scalar::I32),
+            "uint" | "uint32" => Some(Scalar::U32),
+            "float" | "float32" => Some(Scalar::F32),
+            "double" | "float64" => Some(Scalar::F64),
+            _ => None,
+        }
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Scalar::I8 | Scalar::U8 => 1,
+            Scalar::I16 | Scalar::U16 => 2,
+            Scalar::I32 | Scalar::U32 | Scalar::F32 => 4,
+            Scalar::F64 => 8,
+        }
+    }
+
+    //what a color channel of this type is divided by to bring it into [0, 1].
+    fn full_scale(self) -> f64 {
+        match self {
+            Scalar::U8 => 255.0,
+            Scalar::U16 => 65535.0,
+            _ => 1.0,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Property {
+    Scalar(Scalar),
+    //a count of this type, then that many items.
+    List(Scalar, Scalar),
+}
+
+#[derive(Debug, PartialEq)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<(String, Property)>,
+}
+
+//where property values are read from, after the header.
+enum Body<'a> {
+    Ascii(SplitAsciiWhitespace<'a>),
+    Binary { bytes: &'a [u8], big_endian: bool },
+}
+
+impl Body<'_> {
+    fn read(&mut self, scalar: Scalar) -> Result<f64, String> {
+        match self {
+            Body::Ascii(words) => {
+                let word = words.next().ok_or("the file ends early")?;
+                word.parse().map_err(|_| format!("{word} is not a number"))
+            }
+            Body::Binary { bytes, big_endian } => {
+                if bytes.len() < scalar.size() {
+                    return Err(String::from("the file ends early"));
+                }
+                let (value, rest) = bytes.split_at(scalar.size());
+                *bytes = rest;
+                Ok(decode(scalar, value, *big_endian))
+            }
+        }
+    }
+}
+
+fn decode(scalar: Scalar, value: &[u8], big_endian: bool) -> f64 {
+    macro_rules! number {
+        ($t:ty) => {{
+            let bytes = value.try_into().unwrap_or_default();
+            f64::from(if big_endian { <$t>::from_be_bytes(bytes) } else { <$t>::from_le_bytes(bytes) })
+        }};
+    }
+    match scalar {
+        Scalar::I8 => number!(i8),
+        Scalar::U8 => number!(u8),
+        Scalar::I16 => number!(i16),
+        Scalar::U16 => number!(u16),
+        Scalar::I32 => number!(i32),
+        Scalar::U32 => number!(u32),
+        Scalar::F32 => number!(f32),
+        Scalar::F64 => number!(f64),
+    }
+}
+
+//an ASCII header of "ply", the format, and the elements with their properties, up to
+//"end_header"; then every element's values in the order the header declared them, as text or in
+//binary of either byte order. Vertices need x, y and z, and may have red, green and blue. Faces
+//are polygons, whose "vertex_indices" are triangulated, and files without any are point clouds.
+//Anything else is skipped. Faces with corners past the vertices are errors, or `dropped` with it.
+pub(crate) fn load(bytes: &[u8], mut dropped: Option<&mut Vec<OutOfRange>>) -> io::Result<Vec<Model>> {
+    let (elements, mut body) = header(bytes)?;
+    let mut positions = vec![];
+    let mut colors = vec![];
+    let mut polygons = vec![];
+    for element in &elements {
+        let names: Vec<&str> = element.properties.iter().map(|(name, _)| name.as_str()).collect();
+        let find = |wanted: &[&str]| wanted.iter().map(|w| names.iter().position(|name| name == w)).collect::<Option<Vec<_>>>();
+        let position = find(&["x", "y", "z"]);
+        let color = find(&["red", "green", "blue"]);
+        let corners = find(&["vertex_indices"]).or_else(|| find(&["vertex_index"])).map(|found| found[0]);
+        if element.name == "vertex" && position.is_none() {
+            return Err(invalid(String::from("vertices need x, y and z")));
+        }
+        let mut values = Vec::with_capacity(element.properties.len());
+        let mut items = vec![];
+        for item in 0..element.count {
+            let error = |message: String| invalid(format!("{} {item}: {message}", element.name));
+            values.clear();
+            for (property, (_, kind)) in element.properties.iter().enumerate() {
+                match kind {
+                    Property::Scalar(scalar) => values.push(body.read(*scalar).map_err(error)?),
+                    Property::List(count, scalar) => {
+                        let count = body.read(*count).map_err(error)?;
+                        items.clear();
+                        for _ in 0..count as usize {
+                            items.push(body.read(*scalar).map_err(error)?);
+                        }
+                        if Some(property) == corners {
+                            //negative indices are out of range, rather than 0.
+                            let corners = items.iter().map(|&i| if i < 0.0 { u32::MAX } else { i as u32 });
+                            polygons.push(corners.collect::<Vec<_>>());
+                        }
+                        values.push(0.0);
+                    }
+                }
+            }
+            if element.name != "vertex" {
+                continue;
+            }
+            if let Some(position) = &position {
+                positions.extend(position.iter().map(|&p| values[p] as f32));
+            }
+            if let Some(color) = &color {
+                colors.extend(color.iter().map(|&c| {
+                    let Property::Scalar(scalar) = element.properties[c].1 else {
+                        return 0.0;
+                    };
+                    (values[c] / scalar.full_scale()) as f32
+                }));
+            }
+        }
+    }
+    if polygons.is_empty() {
+        return Ok(vec![point_cloud(positions, colors)?]);
+    }
+    let vertices = positions.len() / 3;
+    let mut indices = vec![];
+    for (face, polygon) in polygons.iter().enumerate() {
+        push_polygon(&mut indices, (face, polygon), vertices, dropped.as_deref_mut())
+            .map_err(|e| invalid(format!("face {face}: {e}")))?;
+    }
+    Ok(vec![model(positions, indices, colors)])
+}
+
+fn header(bytes: &[u8]) -> io::Result<(Vec<Element>, Body<'_>)> {
+    let mut elements: Vec<Element> = vec![];
+    let mut format = None;
+    let mut rest = bytes;
+    for number in 1.. {
+        let Some(end) = rest.iter().position(|&b| b == b'\n') else {
+            return Err(invalid(String::from("the header has no end_header")));
+        };
+        let line = std::str::from_utf8(&rest[..end]).map_err(|_| invalid(format!("line {number}: not text")))?;
+        rest = &rest[end + 1..];
+        let error = |message: &str| invalid(format!("line {number}: {message}"));
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words[..] {
+            ["ply"] if number == 1 => {}
+            _ if number == 1 => return Err(error("not a PLY file")),
+            ["format", encoding, "1.0"] => format = Some(encoding),
+            ["comment", ..] | ["obj_info", ..] | [] => {}
+            ["element", name, count] => elements.push(Element {
+                name: String::from(name),
+                count: count.parse().map_err(|_| error("expected an element count"))?,
+                properties: vec![],
+            }),
+            ["property", "list", count, item, name] => {
+                let (Some(count), Some(item)) = (Scalar::from_name(count), Scalar::from_name(item)) else {
+                    return Err(error("unknown property type"));
+                };
+                let element = elements.last_mut().ok_or_else(|| error("a property before any element"))?;
+                element.properties.push((String::from(name), Property::List(count, item)));
+            }
+            ["property", scalar, name] => {
+                let scalar = Scalar::from_name(scalar).ok_or_else(|| error("unknown property type"))?;
+                let element = elements.last_mut().ok_or_else(|| error("a property before any element"))?;
+                element.properties.push((String::from(name), Property::Scalar(scalar)));
+            }
+            ["end_header"] => break,
+            _ => return Err(error(&format!("unexpected {line}"))),
+        }
+    }
+    let body = match format {
+        Some("ascii") => {
+            let text = std::str::from_utf8(rest).map_err(|_| invalid(String::from("the ASCII body is not text")))?;
+            Body::Ascii(text.split_ascii_whitespace())
+        }
+        Some("binary_little_endian") => Body::Binary {
+            bytes: rest,
+            big_endian: false,
+        },
+        Some("binary_big_endian") => Body::Binary {
+            bytes: rest,
+            big_endian: true,
+        },
+        Some(format) => return Err(invalid(format!("unknown format {format}"))),
+        None => return Err(invalid(String::from("the header has no format"))),
+    };
+    Ok((elements, body))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mesh::Primitive;
+    use crate::ply::load;
+
+    const HEADER: &str = "element vertex 4\n\
+        property float x\nproperty float y\nproperty float z\n\
+        property uchar red\nproperty uchar green\nproperty uchar blue\n\
+        element face 1\nproperty list uchar int vertex_indices\nend_header\n";
+
+    #[test]
+    fn ascii_with_colors() {
+        let text = format!(
+            "ply\nformat ascii 1.0\ncomment a quad\n{HEADER}0 0 0 255 0 0\n1 0 0 0 255 0\n1 1 0 0 0 255\n0 1 0 0 0 0\n4 0 1 2 3\n"
+        );
+        let models = load(text.as_bytes(), None).unwrap();
+        let mesh = &models[0].mesh;
+        assert_eq!(mesh.positions.len(), 12);
+        assert_eq!(mesh.indices, [0, 1, 2, 0, 2, 3]);
+        assert_eq!(mesh.vertex_color[..6], [1.0, 0.0, 0.0, 0.0, 1.0, 0.0]);
+    }
+
+    #[test]
+    fn vertices_alone_are_points() {
+        let text = "ply\nformat ascii 1.0\nelement vertex 2\nproperty float x\nproperty float y\nproperty float z\nend_header\n0 0 0\n1 1 1\n";
+        let mesh = &load(text.as_bytes(), None).unwrap()[0].mesh;
+        assert_eq!(mesh.indices, [0, 1]);
+        assert_eq!(Primitive::of(mesh), Primitive::Points);
+    }
+
+    #[test]
+    fn binary_of_either_byte_order() {
+        for (format, big_endian) in [("binary_little_endian", false), ("binary_big_endian", true)] {
+            let mut bytes = format!("ply\nformat {format} 1.0\n{HEADER}").into_bytes();
+            for corner in [[0.0f32, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 1.0, 0.0], [0.0, 1.0, 0.5]] {
+                for value in corner {
+                    bytes.extend(if big_endian { value.to_be_bytes() } else { value.to_le_bytes() });
+                }
+                bytes.extend([10, 20, 30]);
+            }
+            bytes.push(3);
+            for index in [3i32, 1, 2] {
+                bytes.extend(if big_endian { index.to_be_bytes() } else { index.to_le_bytes() });
+            }
+            let mesh = &load(&bytes, None).unwrap()[0].mesh;
+            assert_eq!(mesh.positions[9..], [0.0, 1.0, 0.5], "{format}");
+            assert_eq!(mesh.indices, [3, 1, 2], "{format}");
+            assert_eq!(mesh.vertex_color[2], 30.0 / 255.0);
+        }
+    }
+
+    #[test]
+    fn reports_where_it_went_wrong() {
+        let text = format!("ply\nformat ascii 1.0\n{HEADER}0 0 0 0 0 0\n");
+        assert_eq!(load(text.as_bytes(), None).unwrap_err().to_string(), "vertex 1: the file ends early");
+        let text = format!("ply\nformat ascii 1.0\n{HEADER}{}3 0 1 7\n", "0 0 0 0 0 0\n".repeat(4));
+        assert_eq!(load(text.as_bytes(), None).unwrap_err().to_string(), "face 0: vertex 7 is out of range, with 4 vertices");
+        let text = "ply\nformat ascii 1.0\nproperty float x\nend_header\n";
+        assert_eq!(load(text.as_bytes(), None).unwrap_err().to_string(), "line 3: a property before any element");
+    }
+}
@@ -1,10 +1,14 @@
 use std::ops::RangeInclusive;
 
+use crate::camera::ModelView;
+use crate::framebuffer::FrameBuffer;
+use crate::gbuffer::Surface;
 use crate::io::Mode;
+use crate::mesh::Primitive;
 use crate::{space, Fragment};
-use array2d::Array2D;
 use barycentric::BaryCentricConstants;
-use space::Space;
+use space::{Rect, Space};
+use tobj::Mesh;
 
 mod barycentric {
     use crate::point::{Fragment, Triangle};
@@ -20,14 +24,17 @@ mod barycentric {
         pub(crate) p_dot_a_dot_b: Fragment,
         pub(crate) p_dot_a_dot_c: Fragment,
         pub(crate) pabac: i64,
-        pub(crate) total_area: i64,
+        //products of squared lengths overflow i64 for triangles spanning more than a few
+        //thousand pixels.
+        pub(crate) total_area: i128,
     }
     impl From<&Triangle> for BaryCentricConstants {
         fn from(tri: &Triangle) -> Self {
             let p_dot_a_dot_b = tri.b - tri.a;
             let p_dot_a_dot_c = tri.c - tri.a;
             let pabac = p_dot_a_dot_b.dot(p_dot_a_dot_c);
-            let total_area = (p_dot_a_dot_b.dot_self()) * (p_dot_a_dot_c.dot_self()) - pabac * pabac;
+            let total_area = i128::from(p_dot_a_dot_b.dot_self()) * i128::from(p_dot_a_dot_c.dot_self())
+                - i128::from(pabac) * i128::from(pabac);
             BaryCentricConstants {
                 p_dot_a_dot_b,
                 p_dot_a_dot_c,
@@ -44,6 +51,17 @@ pub struct Point {
     pub(crate) z: f32,
 }
 
+impl Point {
+    //component by dimension offset (0,1,2) referring to (x,y,z)
+    pub(crate) fn axis(&self, offset: usize) -> f32 {
+        match offset {
+            0 => self.x,
+            1 => self.y,
+            _ => self.z,
+        }
+    }
+}
+
 #[derive(Debug)]
 struct Triangle {
     a: Fragment,
@@ -81,7 +99,8 @@ impl Triangle {
                 let p_dot_a_dot_c = c - a;
                 let pabac = p_dot_a_dot_b.dot(p_dot_a_dot_c);
 
-                let total_area = (p_dot_a_dot_b.dot_self()) * (p_dot_a_dot_c.dot_self()) - (pabac * pabac);
+                let total_area = i128::from(p_dot_a_dot_b.dot_self()) * i128::from(p_dot_a_dot_c.dot_self())
+                    - i128::from(pabac) * i128::from(pabac);
                 BaryCentricConstants {
                     p_dot_a_dot_b,
                     p_dot_a_dot_c,
@@ -93,17 +112,17 @@ impl Triangle {
     }
     pub(crate) fn barycentric_coordinates(&self, v: &Fragment) -> BarycentricResult {
         let pav = *v - self.a; //vector from triangle's "a" to a given fragment v
-        let pavab: i64 = pav.dot(self.barycentric_constants.p_dot_a_dot_b);
+        let pavab = i128::from(pav.dot(self.barycentric_constants.p_dot_a_dot_b));
+        let pavac = i128::from(pav.dot(self.barycentric_constants.p_dot_a_dot_c));
+        let pabac = i128::from(self.barycentric_constants.pabac);
 
         //(ac * ac) * (av * ab) - (ab * ac) * (av * ac), with floating-point divide
-        let beta = (self.barycentric_constants.p_dot_a_dot_c.dot_self() * pavab
-            - (self.barycentric_constants.pabac * pav.dot(self.barycentric_constants.p_dot_a_dot_c)))
+        let beta = (i128::from(self.barycentric_constants.p_dot_a_dot_c.dot_self()) * pavab - pabac * pavac)
             as f32
             / self.barycentric_constants.total_area as f32;
         //(ab * ab) * (av * ac) - (ab * ac) * (av * ab), with floating-point divide
-        let gamma = (self.barycentric_constants.p_dot_a_dot_b.dot_self()
-            * pav.dot(self.barycentric_constants.p_dot_a_dot_c)
-            - self.barycentric_constants.pabac * pavab) as f32
+        let gamma = (i128::from(self.barycentric_constants.p_dot_a_dot_b.dot_self()) * pavac - pabac * pavab)
+            as f32
             / self.barycentric_constants.total_area as f32;
         //from a + b + c = 1
         let alpha = 1.0 - beta - gamma;
@@ -137,83 +156,224 @@ impl BoundingBox {
     pub(crate) fn column_range(&self) -> RangeInclusive<i64> {
         self.x_min..=self.x_max
     }
+    //shrinks the box to the pixels it shares with the rectangle, leaving the ranges empty if none.
+    fn clip(&mut self, rect: Rect) {
+        self.x_min = self.x_min.max(rect.x);
+        self.x_max = self.x_max.min(rect.x + rect.width as i64 - 1);
+        self.y_min = self.y_min.max(rect.y);
+        self.y_max = self.y_max.min(rect.y + rect.height as i64 - 1);
+    }
 }
 
-pub fn rasterize(
-    pixels: &mut Array2D<f32>,
-    space: &Space,
-    vertices: &[f32],
-    indices: &[u32],
-    mode: Mode,
-) {
+//the widths in pixels of the squares that points, and every step along a line, cover.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub(crate) struct Stroke {
+    pub(crate) point_size: f32,
+    pub(crate) line_width: f32,
+}
+
+impl Default for Stroke {
+    fn default() -> Self {
+        Stroke {
+            point_size: 1.0,
+            line_width: 1.0,
+        }
+    }
+}
+
+//rasterizes every triangle, line or point of the mesh, placed by `view`. The mesh itself is only
+//read, so any number of instances can share it.
+pub fn rasterize(frame: &mut FrameBuffer, space: &Space, mesh: &Mesh, mode: Mode, view: &ModelView, stroke: Stroke) {
+    match Primitive::of(mesh) {
+        Primitive::Triangles => rasterize_triangles(frame, space, mesh, mode, view),
+        Primitive::Lines => rasterize_strokes(frame, space, mesh, view, 2, stroke.line_width),
+        Primitive::Points => rasterize_strokes(frame, space, mesh, view, 1, stroke.point_size),
+    }
+}
+
+fn frame_rect(frame: &FrameBuffer) -> Rect {
+    Rect::new(0, 0, frame.depth.num_rows() as u64, frame.depth.num_columns() as u64)
+}
+
+//the vertex stage: construct a Point for an index, 0,1,2 indexed from beginning of slice 0,3,6...
+fn vertex(mesh: &Mesh, view: &ModelView, i: u32) -> Option<Point> {
+    let vertices = &mesh.positions;
+    let i = i as usize * 3;
+    let [x, y, z] = view.position([vertices[i], vertices[i + 1], vertices[i + 2]])?;
+    Some(Point { x, y, z })
+}
+
+fn rasterize_triangles(frame: &mut FrameBuffer, space: &Space, mesh: &Mesh, mode: Mode, view: &ModelView) {
+    let indices = &mesh.indices;
     assert_eq!(indices.len() % 3, 0);
+    let frame_rect = frame_rect(frame);
+    let vertex = |i: u32| vertex(mesh, view, i);
     //for every triangle with coords x,y,z
+    for index in indices.chunks_exact(3) {
+        //collect 3 indices, skipping triangles the view culls.
+        let (Some(a), Some(b), Some(c)) = (vertex(index[0]), vertex(index[1]), vertex(index[2])) else {
+            continue;
+        };
+        //also computes bounding box and constant factors of barycentric coordinate evaluation
+        let mut triangle = Triangle::new(space, a, b, c);
+        //only pixels within both the scissor rectangle and the frame are visited.
+        triangle.bounding_box.clip(space.scissor());
+        triangle.bounding_box.clip(frame_rect);
+        //vertex attributes are only gathered when there is a g-buffer to write them to.
+        let surface = frame.gbuffer.is_some().then(|| {
+            Surface::new(mesh, index).place([a, b, c].map(|p| [p.x, p.y, p.z]), |n| view.normal(n))
+        });
+        //iterate over every pixel in the bounding box
+        write_triangle(frame, mode, &triangle, surface.as_ref());
+    }
+}
 
-    for triangle in indices
-        .chunks_exact(3) //collect 3 indices.
-        .map(|index| {
-            let a = Point {
-                //construct a Point for each index from slice. 0,1,2 indexed from beginning of slice 0,3,6...
-                x: vertices[(index[0] * 3) as usize],
-                y: vertices[(index[0] * 3 + 1) as usize],
-                z: vertices[(index[0] * 3 + 2) as usize],
-            };
-            let b = Point {
-                x: vertices[(index[1] * 3) as usize],
-                y: vertices[(index[1] * 3 + 1) as usize],
-                z: vertices[(index[1] * 3 + 2) as usize],
-            };
-            let c = Point {
-                x: vertices[(index[2] * 3) as usize],
-                y: vertices[(index[2] * 3 + 1) as usize],
-                z: vertices[(index[2] * 3 + 2) as usize],
+//rasterizes segments (of two indices) or points (of one) by stamping a square `width` pixels wide
+//at every pixel step from one end to the other, depth tested all the same. Lines and points have
+//no mode of their own.
+fn rasterize_strokes(frame: &mut FrameBuffer, space: &Space, mesh: &Mesh, view: &ModelView, corners: usize, width: f32) {
+    #[allow(clippy::cast_possible_truncation)]
+    let width = (width.round() as i64).max(1);
+    //the stamp's pixels, relative to the pixel it is centered on.
+    let (low, high) = (-(width - 1) / 2, width / 2);
+    let mut clip = BoundingBox {
+        x_min: i64::MIN,
+        x_max: i64::MAX,
+        y_min: i64::MIN,
+        y_max: i64::MAX,
+    };
+    clip.clip(space.scissor());
+    clip.clip(frame_rect(frame));
+    //steps whose stamps may reach the clipped pixels.
+    let reach = BoundingBox {
+        x_min: clip.x_min - high,
+        x_max: clip.x_max - low,
+        y_min: clip.y_min - high,
+        y_max: clip.y_max - low,
+    };
+    for index in mesh.indices.chunks_exact(corners) {
+        let (first, last) = (index[0], index[corners - 1]);
+        let (Some(a), Some(b)) = (vertex(mesh, view, first), vertex(mesh, view, last)) else {
+            continue;
+        };
+        let (from, to) = (space.window_to_pixel(a), space.window_to_pixel(b));
+        let Some((start, end)) = clip_segment(from, to, &reach) else {
+            continue;
+        };
+        let surface = frame.gbuffer.is_some().then(|| {
+            Surface::new(mesh, &[first, last, last]).place([a, b, b].map(|p| [p.x, p.y, p.z]), |n| view.normal(n))
+        });
+        let steps = (to.x - from.x).abs().max((to.y - from.y).abs());
+        #[allow(clippy::cast_possible_truncation)]
+        let (start, end) = ((start * steps as f64).ceil() as i64, (end * steps as f64).floor() as i64);
+        for step in start..=end {
+            let t = if steps == 0 { 0.0 } else { step as f64 / steps as f64 };
+            #[allow(clippy::cast_possible_truncation)]
+            let along = |from: i64, to: i64| from + ((to - from) as f64 * t).round() as i64;
+            let (x, y) = (along(from.x, to.x), along(from.y, to.y));
+            #[allow(clippy::cast_possible_truncation)]
+            let z = from.z + (to.z - from.z) * t as f32;
+            let mut stamp = BoundingBox {
+                x_min: x + low,
+                x_max: x + high,
+                y_min: y + low,
+                y_max: y + high,
             };
-            //also computes bounding box and constant factors of barycentric coordinate evaluation
-            Triangle::new(space, a, b, c)
-        })
-    {
-        //iterate over every pixel in the bounding box
-        write_triangle(pixels, mode, &triangle);
+            stamp.x_min = stamp.x_min.max(clip.x_min);
+            stamp.x_max = stamp.x_max.min(clip.x_max);
+            stamp.y_min = stamp.y_min.max(clip.y_min);
+            stamp.y_max = stamp.y_max.min(clip.y_max);
+            for y in stamp.row_range() {
+                for x in stamp.column_range() {
+                    let visible = color_depth(frame, Fragment { x, y, z });
+                    if let (true, Some(gbuffer), Some(surface)) = (visible, frame.gbuffer.as_mut(), &surface) {
+                        #[allow(clippy::cast_possible_truncation)]
+                        let t = t as f32;
+                        gbuffer.write(x as usize, y as usize, surface, [1.0 - t, t, 0.0]);
+                    }
+                }
+            }
+        }
+    }
+}
+
+//the part of the segment within the box, as the fractions of the way from `from` to `to` that it
+//starts and ends at, or None if it misses the box.
+fn clip_segment(from: Fragment, to: Fragment, bounds: &BoundingBox) -> Option<(f64, f64)> {
+    let (mut start, mut end) = (0.0f64, 1.0f64);
+    let (dx, dy) = ((to.x - from.x) as f64, (to.y - from.y) as f64);
+    let (x, y) = (from.x as f64, from.y as f64);
+    //each edge, as how fast the segment leaves through it and how far inside it starts.
+    let edges = [
+        (-dx, x - bounds.x_min as f64),
+        (dx, bounds.x_max as f64 - x),
+        (-dy, y - bounds.y_min as f64),
+        (dy, bounds.y_max as f64 - y),
+    ];
+    for (leaving, inside) in edges {
+        if leaving == 0.0 {
+            if inside < 0.0 {
+                return None;
+            }
+        } else if leaving < 0.0 {
+            start = start.max(inside / leaving);
+        } else {
+            end = end.min(inside / leaving);
+        }
     }
+    (start <= end).then_some((start, end))
 }
 
-fn write_triangle(pixels: &mut Array2D<f32>, mode: Mode, triangle: &Triangle) {
+fn write_triangle(
+    frame: &mut FrameBuffer,
+    mode: Mode,
+    triangle: &Triangle,
+    surface: Option<&Surface>,
+) {
     for row_idx in triangle.bounding_box.row_range() {
         for column_idx in triangle.bounding_box.column_range() {
             //compute barycentric coordinates, returning an alpha, beta, and gamma value.
-            write_pixel(pixels, mode, triangle, row_idx, column_idx);
+            write_pixel(frame, mode, triangle, surface, row_idx, column_idx);
         }
     }
 }
 
 fn write_pixel(
-    pixels: &mut Array2D<f32>,
+    frame: &mut FrameBuffer,
     mode: Mode,
     triangle: &Triangle,
+    surface: Option<&Surface>,
     row_idx: i64,
     column_idx: i64,
 ) {
     let mut frag = Fragment {
-        x: row_idx,
-        y: column_idx,
+        x: column_idx,
+        y: row_idx,
         z: 0.0,
     };
     let bary = triangle.barycentric_coordinates(&frag);
     frag.z = bary.z; //explicit about where assignment is happening
                      //if Point is inside triangle,
     if inside_triangle(bary.alpha, bary.beta, bary.gamma) {
-        //perform wireframe or depth coloring
-        match mode {
-            Mode::Depth => color_depth(pixels, frag),
+        //perform wireframe or depth coloring. Vertex colors fill triangles as depth does, and are
+        //shaded from the g-buffer. Splats are drawn by rasterize_splats instead.
+        let visible = match mode {
+            Mode::Depth | Mode::Color | Mode::Splats => color_depth(frame, frag),
             Mode::Wireframe => {
                 const EPSILON: f32 = 0.3;
-                if [bary.alpha, bary.beta, bary.gamma]
+                [bary.alpha, bary.beta, bary.gamma]
                     .iter()
                     .all(|&b| f32::abs(b) < EPSILON)
-                {
-                    color_depth(pixels, frag);
-                }
+                    && color_depth(frame, frag)
             }
+        };
+        if let (true, Some(gbuffer), Some(surface)) = (visible, frame.gbuffer.as_mut(), surface) {
+            gbuffer.write(
+                frag.x as usize,
+                frag.y as usize,
+                surface,
+                [bary.alpha, bary.beta, bary.gamma],
+            );
         }
     }
 }
@@ -225,9 +385,17 @@ fn write_pixel(
 //      /|
 //     / |
 //    /  |
-fn color_depth(pixels: &mut Array2D<f32>, frag: Fragment) {
-    if let Some(pixel) = pixels.get_mut(frag.x as usize, frag.y as usize) {
-        *pixel = pixel.min(frag.z);
+//returns whether the fragment passed the depth test.
+pub(crate) fn color_depth(frame: &mut FrameBuffer, frag: Fragment) -> bool {
+    let (x, y) = (frag.x as usize, frag.y as usize);
+    match frame.depth.get_mut(x, y) {
+        Some(pixel) if frag.z < *pixel => {
+            *pixel = frag.z;
+            frame.coverage[(x, y)] = true;
+            frame.objects[(x, y)] = frame.object;
+            true
+        }
+        _ => false,
     }
 }
 
@@ -238,10 +406,94 @@ fn inside_triangle(alpha: f32, beta: f32, gamma: f32) -> bool {
 
 #[cfg(test)]
 mod tests {
-    use crate::point::{inside_triangle, Point, Triangle};
-    use crate::space::Space;
+    use crate::camera::{Camera, ModelView, Pose};
+    use crate::framebuffer::FrameBuffer;
+    use crate::io::Mode;
+    use crate::point::{inside_triangle, rasterize, Point, Stroke, Triangle};
+    use crate::space::{Rect, Space};
     use crate::Fragment;
     use std::num::NonZeroU64;
+    use tobj::Mesh;
+
+    //two triangles covering the whole view volume.
+    fn square() -> Mesh {
+        Mesh {
+            positions: vec![-1.0, -1.0, 0.0, 1.0, -1.0, 0.0, 1.0, 1.0, 0.0, -1.0, 1.0, 0.0],
+            indices: vec![0, 1, 2, 0, 2, 3],
+            ..Mesh::default()
+        }
+    }
+
+    fn covered(frame: &FrameBuffer) -> Vec<(usize, usize)> {
+        let (width, height) = (frame.depth.num_rows(), frame.depth.num_columns());
+        (0..width)
+            .flat_map(|x| (0..height).map(move |y| (x, y)))
+            .filter(|&pixel| frame.coverage[pixel])
+            .collect()
+    }
+
+    #[test]
+    fn scissors_limit_coverage() {
+        let view = ModelView::new(&Camera::default(), &Pose::IDENTITY);
+        let mut frame = FrameBuffer::new(10, 10, false);
+        let space = Space::viewport(Rect::new(0, 0, 10, 10)).unwrap().with_scissor(Rect::new(2, 3, 4, 5));
+        rasterize(&mut frame, &space, &square(), Mode::Depth, &view, Stroke::default());
+        let pixels = covered(&frame);
+        assert_eq!(pixels.len(), 20);
+        assert!(pixels.iter().all(|&(x, y)| (2..6).contains(&x) && (3..8).contains(&y)));
+    }
+
+    #[test]
+    fn viewports_may_exceed_the_frame() {
+        let view = ModelView::new(&Camera::default(), &Pose::IDENTITY);
+        let mut frame = FrameBuffer::new(10, 10, false);
+        //the frame sees the middle of a viewport twenty times its size.
+        let space = Space::viewport(Rect::new(-95, -95, 200, 200)).unwrap();
+        rasterize(&mut frame, &space, &square(), Mode::Depth, &view, Stroke::default());
+        assert_eq!(covered(&frame).len(), 100);
+        //and nothing of one beside it.
+        let mut frame = FrameBuffer::new(10, 10, false);
+        let space = Space::viewport(Rect::new(10, 0, 10, 10)).unwrap();
+        rasterize(&mut frame, &space, &square(), Mode::Depth, &view, Stroke::default());
+        assert!(covered(&frame).is_empty());
+    }
+
+    #[test]
+    fn lines_and_points() {
+        let view = ModelView::new(&Camera::default(), &Pose::IDENTITY);
+        let space = Space::viewport(Rect::new(0, 0, 10, 10)).unwrap();
+        let line = Mesh {
+            positions: vec![-0.9, -0.9, 0.0, 0.9, -0.9, 0.0],
+            indices: vec![0, 1],
+            face_arities: vec![2],
+            ..Mesh::default()
+        };
+        let mut frame = FrameBuffer::new(10, 10, false);
+        rasterize(&mut frame, &space, &line, Mode::Depth, &view, Stroke::default());
+        assert_eq!(covered(&frame), (0..10).map(|x| (x, 0)).collect::<Vec<_>>());
+        //wide lines are clipped to the frame like everything else.
+        let mut frame = FrameBuffer::new(10, 10, false);
+        let stroke = Stroke {
+            line_width: 3.0,
+            ..Stroke::default()
+        };
+        rasterize(&mut frame, &space, &line, Mode::Depth, &view, stroke);
+        assert_eq!(covered(&frame).len(), 20);
+
+        let points = Mesh {
+            positions: vec![0.0; 3],
+            indices: vec![0],
+            face_arities: vec![1],
+            ..Mesh::default()
+        };
+        let stroke = Stroke {
+            point_size: 2.0,
+            ..Stroke::default()
+        };
+        let mut frame = FrameBuffer::new(10, 10, false);
+        rasterize(&mut frame, &space, &points, Mode::Depth, &view, stroke);
+        assert_eq!(covered(&frame), [(5, 5), (5, 6), (6, 5), (6, 6)]);
+    }
 
     #[test]
     fn triangle_creation() {
@@ -280,4 +532,22 @@ mod tests {
         }
         assert_eq!(results, vec![true, false, false, true, true]);
     }
+
+    #[test]
+    fn pixels_are_not_transposed() {
+        let space = Space::new(NonZeroU64::new(20).unwrap(), NonZeroU64::new(10).unwrap()).unwrap();
+        let mut frame = FrameBuffer::new(20, 10, false);
+        //a long, flat triangle along the bottom left of a wide image, from pixel (0, 0) to (10, 0)
+        //and (0, 2).
+        let mesh = Mesh {
+            positions: vec![-2.0, -1.0, 0.0, 0.0, -1.0, 0.0, -2.0, -0.6, 0.0],
+            indices: vec![0, 1, 2],
+            ..Mesh::default()
+        };
+        let view = ModelView::new(&Camera::default(), &Pose::IDENTITY);
+        rasterize(&mut frame, &space, &mesh, Mode::Depth, &view, Stroke::default());
+        assert!(frame.depth[(8, 0)] < f32::MAX);
+        //its mirror image across the diagonal is not drawn instead.
+        assert_eq!(frame.depth[(0, 8)], f32::MAX);
+    }
 }
@@ -0,0 +1,90 @@
+use crate::framebuffer::FrameBuffer;
+use crate::space::{Error, Rect, Space};
+use std::io;
+use std::num::NonZeroU64;
+use std::ops::Range;
+
+//the rows of an image of `height` that each band covers, top first, all `rows` tall but the last.
+pub(crate) fn bands(height: u64, rows: NonZeroU64) -> impl Iterator<Item = Range<u64>> {
+    (0..height)
+        .step_by(rows.get() as usize)
+        .map(move |top| top..(top + rows.get()).min(height))
+}
+
+//renders a width * height image a band of rows at a time, returning each band as it is shaded.
+//Only one band's frame buffer exists at once, however large the image is. `draw` rasterizes
+//everything into a band's frame through a space that maps the image's `viewport` and `scissor`
+//onto the band, and `shade` turns the frame into the band's row-major RGBA rows. Band frames
+//have a g-buffer `with_gbuffer`.
+pub(crate) fn render_bands<'a>(
+    (width, height): (NonZeroU64, NonZeroU64),
+    rows: NonZeroU64,
+    viewport: Rect,
+    scissor: Rect,
+    with_gbuffer: bool,
+    mut draw: impl FnMut(&mut FrameBuffer, &Space) + 'a,
+    mut shade: impl FnMut(&FrameBuffer, Range<u64>) -> io::Result<Vec<u8>> + 'a,
+) -> impl Iterator<Item = io::Result<Vec<u8>>> + 'a {
+    bands(height.get(), rows).map(move |band| {
+        //moves everything up by the band's top row, so that it lands on the frame's first row.
+        let shift = |rect: Rect| Rect::new(rect.x, rect.y - band.start as i64, rect.width, rect.height);
+        let space = Space::viewport(shift(viewport))
+            .map_err(|Error::Init| io::Error::other("the viewport is empty"))?
+            .with_scissor(shift(scissor));
+        let mut frame = FrameBuffer::new(width.get() as usize, (band.end - band.start) as usize, with_gbuffer);
+        draw(&mut frame, &space);
+        shade(&frame, band)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::camera::{Camera, ModelView, Pose};
+    use crate::framebuffer::FrameBuffer;
+    use crate::io::Mode;
+    use crate::point::{rasterize, Stroke};
+    use crate::poster::{bands, render_bands};
+    use crate::shade::shade_depth;
+    use crate::space::{Rect, Space};
+    use std::num::NonZeroU64;
+    use tobj::Mesh;
+
+    #[test]
+    fn bands_cover_every_row() {
+        let rows = NonZeroU64::new(4).unwrap();
+        assert_eq!(bands(10, rows).collect::<Vec<_>>(), [0..4, 4..8, 8..10]);
+        assert_eq!(bands(3, rows).next(), Some(0..3));
+    }
+
+    #[test]
+    fn bands_match_a_whole_frame() {
+        let mesh = Mesh {
+            positions: vec![-0.9, -0.8, 0.5, 0.7, -0.3, -0.2, -0.1, 0.9, 0.0],
+            indices: vec![0, 1, 2],
+            ..Mesh::default()
+        };
+        let view = ModelView::new(&Camera::default(), &Pose::IDENTITY);
+        let (width, height) = (NonZeroU64::new(9).unwrap(), NonZeroU64::new(11).unwrap());
+        let viewport = Rect::new(-2, 1, 12, 9);
+        let scissor = Rect::new(1, 0, 7, 10);
+        let background = vec![0; 9 * 11 * 4];
+
+        let mut frame = FrameBuffer::new(9, 11, false);
+        let space = Space::viewport(viewport).unwrap().with_scissor(scissor);
+        rasterize(&mut frame, &space, &mesh, Mode::Depth, &view, Stroke::default());
+        let whole = shade_depth(&frame, &background);
+
+        let rows = NonZeroU64::new(4).unwrap();
+        let draw = |frame: &mut FrameBuffer, space: &Space| rasterize(frame, space, &mesh, Mode::Depth, &view, Stroke::default());
+        let shade = |frame: &FrameBuffer, band: std::ops::Range<u64>| {
+            let rows = (band.start * 9 * 4) as usize..(band.end * 9 * 4) as usize;
+            Ok(shade_depth(frame, &background[rows]))
+        };
+        let banded: Vec<u8> = render_bands((width, height), rows, viewport, scissor, false, draw, shade)
+            .map(Result::unwrap)
+            .collect::<Vec<_>>()
+            .concat();
+        assert_eq!(banded, whole);
+        assert!(whole.iter().any(|&c| c != 0));
+    }
+}
@@ -0,0 +1,228 @@
+use crate::background::scale;
+use std::io::{self, Write};
+
+//dark to bright.
+const RAMP: &[u8] = b" .:-=+*#%@";
+//terminal cells are assumed to be about this many pixels wide when sizing sixel output.
+const CELL_WIDTH: usize = 8;
+
+//how the finished image is drawn in the terminal.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Preview {
+    //24-bit ANSI colors on Unicode upper half blocks, two pixels per character.
+    Truecolor,
+    //a luminance ramp of plain ASCII characters, for terminals without color.
+    Ascii,
+    //DEC sixel graphics, with a 6x6x6 color cube palette.
+    Sixel,
+}
+
+impl Preview {
+    pub(crate) fn from_name(name: &str) -> Option<Preview> {
+        match name {
+            "truecolor" => Some(Preview::Truecolor),
+            "ascii" => Some(Preview::Ascii),
+            "sixel" => Some(Preview::Sixel),
+            _ => None,
+        }
+    }
+
+    //draws row-major RGBA data, shrunk to fit `columns` characters, composited over black.
+    pub(crate) fn write<W: Write>(
+        self,
+        mut w: W,
+        width: usize,
+        height: usize,
+        data: &[u8],
+        columns: usize,
+    ) -> io::Result<()> {
+        let max_width = match self {
+            Preview::Sixel => columns * CELL_WIDTH,
+            Preview::Truecolor | Preview::Ascii => columns,
+        };
+        let to_width = width.min(max_width).max(1);
+        //half blocks draw square pixels, but characters are about twice as tall as they are wide.
+        let aspect = match self {
+            Preview::Ascii => 0.5,
+            Preview::Truecolor | Preview::Sixel => 1.0,
+        };
+        #[allow(clippy::cast_possible_truncation)]
+        #[allow(clippy::cast_sign_loss)]
+        let to_height = ((height * to_width) as f32 / width as f32 * aspect).round().max(1.0) as usize;
+        let pixels: Vec<[u8; 3]> = scale(data, width, height, to_width, to_height)
+            .chunks_exact(4)
+            .map(|p| [0, 1, 2].map(|i| (u16::from(p[i]) * u16::from(p[3]) / 255) as u8))
+            .collect();
+        match self {
+            Preview::Truecolor => write_half_blocks(&mut w, to_width, &pixels)?,
+            Preview::Ascii => write_ascii(&mut w, to_width, &pixels)?,
+            Preview::Sixel => write_sixel(&mut w, to_width, to_height, &pixels)?,
+        }
+        w.flush()
+    }
+}
+
+//the terminal's width in characters: of the terminal on standard output or standard error, which
+//previews are drawn on, or else as exported by the shell, or else 80.
+pub(crate) fn terminal_columns() -> usize {
+    tty_columns()
+        .or_else(|| std::env::var("COLUMNS").ok().and_then(|c| c.parse().ok()))
+        .filter(|&c| c > 0)
+        .unwrap_or(80)
+}
+
+#[cfg(unix)]
+fn tty_columns() -> Option<usize> {
+    [libc::STDOUT_FILENO, libc::STDERR_FILENO].into_iter().find_map(|fd| {
+        let mut size = libc::winsize {
+            ws_row: 0,
+            ws_col: 0,
+            ws_xpixel: 0,
+            ws_ypixel: 0,
+        };
+        //SAFETY: TIOCGWINSZ only writes a winsize through the pointer, which is valid for the call,
+        //and fails without writing anything on descriptors that are not terminals.
+        let result = unsafe { libc::ioctl(fd, libc::TIOCGWINSZ, &mut size) };
+        (result == 0 && size.ws_col > 0).then_some(usize::from(size.ws_col))
+    })
+}
+
+//other platforms only have COLUMNS.
+#[cfg(not(unix))]
+fn tty_columns() -> Option<usize> {
+    None
+}
+
+fn write_half_blocks<W: Write>(w: &mut W, width: usize, pixels: &[[u8; 3]]) -> io::Result<()> {
+    let rows: Vec<&[[u8; 3]]> = pixels.chunks_exact(width).collect();
+    for pair in rows.chunks(2) {
+        for x in 0..width {
+            let [r, g, b] = pair[0][x];
+            //an odd final row has nothing below it.
+            let [br, bg, bb] = pair.get(1).map_or([0; 3], |row| row[x]);
+            write!(w, "\x1b[38;2;{r};{g};{b}m\x1b[48;2;{br};{bg};{bb}m\u{2580}")?;
+        }
+        writeln!(w, "\x1b[0m")?;
+    }
+    Ok(())
+}
+
+fn write_ascii<W: Write>(w: &mut W, width: usize, pixels: &[[u8; 3]]) -> io::Result<()> {
+    for row in pixels.chunks_exact(width) {
+        let line: Vec<u8> = row
+            .iter()
+            .map(|&[r, g, b]| {
+                //Rec. 709 luma.
+                let luma = 0.2126 * f32::from(r) + 0.7152 * f32::from(g) + 0.0722 * f32::from(b);
+                #[allow(clippy::cast_possible_truncation)]
+                #[allow(clippy::cast_sign_loss)]
+                let level = (luma / 256.0 * RAMP.len() as f32) as usize;
+                RAMP[level.min(RAMP.len() - 1)]
+            })
+            .collect();
+        w.write_all(&line)?;
+        writeln!(w)?;
+    }
+    Ok(())
+}
+
+//index into the 6x6x6 color cube.
+fn cube_index([r, g, b]: [u8; 3]) -> usize {
+    let level = |c: u8| (usize::from(c) * 5 + 127) / 255;
+    level(r) * 36 + level(g) * 6 + level(b)
+}
+
+fn write_sixel<W: Write>(w: &mut W, width: usize, height: usize, pixels: &[[u8; 3]]) -> io::Result<()> {
+    //enter sixel mode, with square pixels, then declare the image size.
+    write!(w, "\x1bP0;1;0q\"1;1;{width};{height}")?;
+    for i in 0..216 {
+        //palette channels are percentages.
+        let percent = |level: usize| level * 100 / 5;
+        write!(w, "#{i};2;{};{};{}", percent(i / 36), percent(i / 6 % 6), percent(i % 6))?;
+    }
+    let indices: Vec<usize> = pixels.iter().copied().map(cube_index).collect();
+    for band in indices.chunks(width * 6) {
+        let mut colors: Vec<usize> = band.to_vec();
+        colors.sort_unstable();
+        colors.dedup();
+        for (n, &color) in colors.iter().enumerate() {
+            if n > 0 {
+                write!(w, "$")?; //back to the start of the band for the next color
+            }
+            write!(w, "#{color}")?;
+            //each character holds the six vertical pixels of one column, run-length encoded.
+            let sixels = (0..width).map(|x| {
+                (0..6).fold(0u8, |bits, row| match band.get(row * width + x) {
+                    Some(&c) if c == color => bits | 1 << row,
+                    _ => bits,
+                })
+            });
+            let mut run: Option<(u8, usize)> = None;
+            for sixel in sixels.chain(std::iter::once(u8::MAX)) {
+                match run {
+                    Some((previous, count)) if previous == sixel => run = Some((previous, count + 1)),
+                    _ => {
+                        if let Some((previous, count)) = run {
+                            let c = char::from(63 + previous);
+                            if count > 3 {
+                                write!(w, "!{count}{c}")?;
+                            } else {
+                                write!(w, "{}", c.to_string().repeat(count))?;
+                            }
+                        }
+                        run = Some((sixel, 1));
+                    }
+                }
+            }
+        }
+        write!(w, "-")?; //next band
+    }
+    write!(w, "\x1b\\")?;
+    writeln!(w)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::preview::{cube_index, Preview};
+
+    //a 2x2 image: white, black / red, transparent white.
+    const DATA: [u8; 16] = [
+        255, 255, 255, 255, 0, 0, 0, 255, 255, 0, 0, 255, 255, 255, 255, 0,
+    ];
+
+    fn preview(mode: Preview, columns: usize) -> String {
+        let mut out = vec![];
+        mode.write(&mut out, 2, 2, &DATA, columns).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn half_blocks_pair_rows() {
+        let out = preview(Preview::Truecolor, 80);
+        assert_eq!(out.lines().count(), 1);
+        assert!(out.starts_with("\x1b[38;2;255;255;255m\x1b[48;2;255;0;0m\u{2580}"));
+        assert!(out.contains("\x1b[38;2;0;0;0m\x1b[48;2;0;0;0m\u{2580}"));
+    }
+
+    #[test]
+    fn ascii_halves_rows() {
+        //rows are blended: white over red is bright, black over transparent is dark.
+        assert_eq!(preview(Preview::Ascii, 80), "*:\n");
+    }
+
+    #[test]
+    fn shrinks_to_columns() {
+        let out = preview(Preview::Ascii, 1);
+        assert_eq!(out.lines().next().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn sixel_frame() {
+        let out = preview(Preview::Sixel, 80);
+        assert!(out.starts_with("\x1bP0;1;0q\"1;1;2;2"));
+        assert!(out.trim_end().ends_with("-\x1b\\"));
+        //white is drawn in the top-left pixel only: bit 0 of the first column.
+        assert_eq!(cube_index([255, 255, 255]), 215);
+        assert!(out.contains("#215@?"));
+    }
+}
@@ -0,0 +1,529 @@
+use crate::background::{parse_color, Background};
+use crate::camera::{normalize, Camera, Pose, Projection, Quat, Vec3};
+use crate::format::ImageFormat;
+use crate::io::Mode;
+use crate::shade::Light;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::io;
+use std::num::NonZeroU64;
+use std::path::Path;
+
+//a render described by a TOML or JSON file, so that it can be checked in and reproduced.
+#[derive(Debug, PartialEq)]
+pub(crate) struct Scene {
+    pub(crate) output: Output,
+    pub(crate) camera: Camera,
+    //each distinct mesh file once, however many instances there are of it.
+    pub(crate) meshes: Vec<String>,
+    pub(crate) instances: Vec<Instance>,
+    //in the camera's view space. Without any, pixels are shaded by depth as usual.
+    pub(crate) lights: Vec<Light>,
+    pub(crate) ambient: f32,
+}
+
+//where the image goes, and how big it is.
+#[derive(Debug, PartialEq)]
+pub(crate) struct Output {
+    pub(crate) file: String,
+    pub(crate) width: NonZeroU64,
+    pub(crate) height: NonZeroU64,
+    pub(crate) format: Option<ImageFormat>,
+    pub(crate) background: Background,
+    pub(crate) mode: Mode,
+}
+
+//one placement of a mesh, normalized to the [-1,1] cube like any other, then posed in world
+//space by its node and every ancestor of it.
+#[derive(Debug, PartialEq)]
+pub(crate) struct Instance {
+    //into the scene's meshes.
+    pub(crate) mesh: usize,
+    pub(crate) pose: Pose,
+    //the color of its scene material, or None to keep the materials of the mesh file.
+    pub(crate) color: Option<[u8; 4]>,
+}
+
+//the file layout, before names are resolved and values checked.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SceneFile {
+    output: OutputSection,
+    #[serde(default)]
+    camera: Vec<CameraSection>,
+    //nodes that must have a mesh.
+    #[serde(default)]
+    mesh: Vec<NodeSection>,
+    #[serde(default)]
+    node: Vec<NodeSection>,
+    #[serde(default)]
+    material: HashMap<String, MaterialSection>,
+    #[serde(default)]
+    light: Vec<LightSection>,
+    ambient: Option<f32>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct OutputSection {
+    file: String,
+    width: NonZeroU64,
+    height: NonZeroU64,
+    format: Option<String>,
+    background: Option<String>,
+    #[serde(default)]
+    wireframe: bool,
+    //the name of the camera to render through, if not the first.
+    camera: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CameraSection {
+    name: Option<String>,
+    position: Option<Vec3>,
+    target: Option<Vec3>,
+    rotation: Option<[f32; 4]>,
+    //"orthographic" or "perspective".
+    projection: Option<String>,
+    //vertical, in degrees.
+    fov: Option<f32>,
+    half_height: Option<f32>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct NodeSection {
+    //for children to name as their parent.
+    name: Option<String>,
+    //the node this one is placed relative to, if not the world.
+    parent: Option<String>,
+    file: Option<String>,
+    #[serde(default)]
+    position: Vec3,
+    rotation: Option<[f32; 4]>,
+    scale: Option<f32>,
+    material: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MaterialSection {
+    color: String,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LightSection {
+    //the way the light shines, in world space.
+    direction: Vec3,
+    color: Option<String>,
+    intensity: Option<f32>,
+}
+
+impl Scene {
+    //reads a .json scene as JSON and anything else as TOML. Mesh files are relative to the scene.
+    pub(crate) fn from_file(file: &str) -> io::Result<Scene> {
+        let text = std::fs::read_to_string(file)?;
+        let json = Path::new(file).extension().is_some_and(|e| e == "json");
+        let directory = Path::new(file).parent().unwrap_or(Path::new(""));
+        Scene::parse(&text, json, directory).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    pub(crate) fn parse(text: &str, json: bool, directory: &Path) -> Result<Scene, String> {
+        let scene: SceneFile = if json {
+            serde_json::from_str(text).map_err(|e| e.to_string())?
+        } else {
+            toml::from_str(text).map_err(|e| e.to_string())?
+        };
+        scene.check()?;
+        let output = &scene.output;
+        let camera = match &output.camera {
+            Some(name) => Some(
+                scene
+                    .camera
+                    .iter()
+                    .find(|camera| camera.name.as_ref() == Some(name))
+                    .ok_or_else(|| format!("there is no camera named {name}"))?,
+            ),
+            None => scene.camera.first(),
+        };
+        //without any, the camera that renders without a scene.
+        let camera = camera.map_or(Ok(Camera::default()), CameraSection::resolve)?;
+        let (meshes, instances) = resolve_nodes(&scene, directory)?;
+        let lights = scene
+            .light
+            .iter()
+            .map(|light| {
+                if light.direction == [0.0; 3] {
+                    return Err(String::from("a light needs a direction"));
+                }
+                let [r, g, b, _] = color(light.color.as_deref().unwrap_or("#ffffff"))?;
+                let intensity = light.intensity.unwrap_or(1.0);
+                let direction = camera.pose.rotation.conjugate().rotate(normalize(light.direction));
+                Ok(Light {
+                    direction,
+                    color: [r, g, b].map(|c| f32::from(c) / 255.0 * intensity),
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        let output = Output {
+            file: output.file.clone(),
+            width: output.width,
+            height: output.height,
+            format: match &output.format {
+                Some(name) => Some(ImageFormat::from_name(name).ok_or_else(|| format!("unknown format {name}"))?),
+                None => None,
+            },
+            background: match &output.background {
+                Some(spec) => Background::from_spec(spec).ok_or_else(|| format!("invalid background {spec}"))?,
+                None => Background::default(),
+            },
+            mode: if output.wireframe {
+                Mode::Wireframe
+            } else {
+                Mode::Depth
+            },
+        };
+        Ok(Scene {
+            output,
+            camera,
+            meshes,
+            instances,
+            lights,
+            ambient: scene.ambient.unwrap_or(0.1),
+        })
+    }
+}
+
+impl SceneFile {
+    //checks the numbers as the command line and camera paths do, naming the key of the first
+    //that fails: every number is finite, fields of view are between 0 and 180 degrees and half
+    //heights finite and above 0.
+    fn check(&self) -> Result<(), String> {
+        fn finite<'a>(key: String, mut values: impl Iterator<Item = &'a f32>) -> Result<(), String> {
+            if values.all(|value| value.is_finite()) {
+                Ok(())
+            } else {
+                Err(format!("{key} must be finite"))
+            }
+        }
+        for (i, camera) in self.camera.iter().enumerate() {
+            let key = |name: &str| format!("camera[{i}].{name}");
+            finite(key("position"), camera.position.iter().flatten())?;
+            finite(key("target"), camera.target.iter().flatten())?;
+            finite(key("rotation"), camera.rotation.iter().flatten())?;
+            if camera.fov.is_some_and(|fov| !(fov > 0.0 && fov < 180.0)) {
+                return Err(format!("{} must be between 0 and 180 degrees", key("fov")));
+            }
+            if camera.half_height.is_some_and(|half_height| !(half_height > 0.0 && half_height.is_finite())) {
+                return Err(format!("{} must be finite and above 0", key("half_height")));
+            }
+        }
+        for (section, nodes) in [("mesh", &self.mesh), ("node", &self.node)] {
+            for (i, node) in nodes.iter().enumerate() {
+                let key = |name: &str| format!("{section}[{i}].{name}");
+                finite(key("position"), node.position.iter())?;
+                finite(key("rotation"), node.rotation.iter().flatten())?;
+                finite(key("scale"), node.scale.iter())?;
+            }
+        }
+        for (i, light) in self.light.iter().enumerate() {
+            finite(format!("light[{i}].direction"), light.direction.iter())?;
+            finite(format!("light[{i}].intensity"), light.intensity.iter())?;
+        }
+        finite(String::from("ambient"), self.ambient.iter())
+    }
+}
+
+impl CameraSection {
+    fn resolve(&self) -> Result<Camera, String> {
+        let default = Camera::default();
+        let position = self.position.unwrap_or(default.pose.position);
+        let rotation = match (self.target, self.rotation) {
+            (Some(_), Some(_)) => return Err(String::from("a camera has either a target or a rotation")),
+            (Some(target), None) if target == position => {
+                return Err(String::from("the camera cannot look at its own position"))
+            }
+            (Some(target), None) => Quat::look_at(position, target),
+            (None, quat) => rotation(quat),
+        };
+        let projection = match self.projection.as_deref() {
+            None | Some("orthographic") => Projection::Orthographic(self.half_height.unwrap_or(1.0)),
+            Some("perspective") => Projection::Perspective(self.fov.unwrap_or(60.0).to_radians()),
+            Some(other) => return Err(format!("unknown projection {other}")),
+        };
+        Ok(Camera {
+            pose: Pose {
+                position,
+                rotation,
+                scale: 1.0,
+            },
+            projection,
+        })
+    }
+}
+
+//places every node under its parent, and gathers the nodes with meshes into instances of each
+//distinct mesh file. Nodes without a material take their parent's, and nodes without either keep
+//the mesh file's own.
+fn resolve_nodes(scene: &SceneFile, directory: &Path) -> Result<(Vec<String>, Vec<Instance>), String> {
+    if scene.mesh.iter().any(|mesh| mesh.file.is_none()) {
+        return Err(String::from("a mesh needs a file"));
+    }
+    let nodes: Vec<&NodeSection> = scene.mesh.iter().chain(&scene.node).collect();
+    let mut names = HashMap::new();
+    for (index, node) in nodes.iter().enumerate() {
+        if let Some(name) = &node.name {
+            if names.insert(name.as_str(), index).is_some() {
+                return Err(format!("there are two nodes named {name}"));
+            }
+        }
+    }
+    let parents = nodes
+        .iter()
+        .map(|node| match &node.parent {
+            Some(name) => match names.get(name.as_str()) {
+                Some(&parent) => Ok(Some(parent)),
+                None => Err(format!("there is no node named {name}")),
+            },
+            None => Ok(None),
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    let mut poses = vec![None; nodes.len()];
+    let mut meshes = vec![];
+    let mut mesh_indices = HashMap::new();
+    let mut instances = vec![];
+    for (index, node) in nodes.iter().enumerate() {
+        let pose = world_pose(index, &nodes, &parents, &mut poses)?;
+        let Some(file) = &node.file else {
+            continue;
+        };
+        let file = directory.join(file).to_string_lossy().into_owned();
+        let mesh = *mesh_indices.entry(file.clone()).or_insert_with(|| {
+            meshes.push(file);
+            meshes.len() - 1
+        });
+        //the nearest material up the tree; the ancestors are known to end at a root by now.
+        let mut material = None;
+        let mut ancestor = Some(index);
+        while let (None, Some(current)) = (material, ancestor) {
+            material = nodes[current].material.as_ref();
+            ancestor = parents[current];
+        }
+        let color = match material {
+            Some(name) => {
+                let material = scene
+                    .material
+                    .get(name)
+                    .ok_or_else(|| format!("there is no material named {name}"))?;
+                Some(color(&material.color)?)
+            }
+            None => None,
+        };
+        instances.push(Instance { mesh, pose, color });
+    }
+    if instances.is_empty() {
+        return Err(String::from("the scene has no meshes"));
+    }
+    Ok((meshes, instances))
+}
+
+//the pose of a node in world space, remembering it and every ancestor's along the way.
+fn world_pose(
+    index: usize,
+    nodes: &[&NodeSection],
+    parents: &[Option<usize>],
+    poses: &mut [Option<Pose>],
+) -> Result<Pose, String> {
+    if let Some(pose) = poses[index] {
+        return Ok(pose);
+    }
+    //the node and its ancestors without a pose yet, from the node upwards.
+    let mut chain = vec![index];
+    let mut pose = Pose::IDENTITY;
+    while let Some(parent) = parents[chain[chain.len() - 1]] {
+        if let Some(known) = poses[parent] {
+            pose = known;
+            break;
+        }
+        if chain.contains(&parent) {
+            let name = nodes[parent].name.as_deref().unwrap_or_default();
+            return Err(format!("{name} is its own ancestor"));
+        }
+        chain.push(parent);
+    }
+    for &node in chain.iter().rev() {
+        let node_pose = Pose {
+            position: nodes[node].position,
+            rotation: rotation(nodes[node].rotation),
+            scale: nodes[node].scale.unwrap_or(1.0),
+        };
+        pose = pose.compose(&node_pose);
+        poses[node] = Some(pose);
+    }
+    Ok(pose)
+}
+
+fn rotation(rotation: Option<[f32; 4]>) -> Quat {
+    rotation.map_or(Quat::IDENTITY, |[w, x, y, z]| Quat::new(w, x, y, z))
+}
+
+fn color(hex: &str) -> Result<[u8; 4], String> {
+    parse_color(hex).ok_or_else(|| format!("invalid color {hex}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::background::Background;
+    use crate::camera::{Camera, Projection};
+    use crate::io::Mode;
+    use crate::scene::Scene;
+    use std::path::Path;
+
+    const TOML: &str = r##"
+        ambient = 0.2
+
+        [output]
+        file = "out.png"
+        width = 320
+        height = 240
+        background = "transparent"
+        camera = "side"
+
+        [[camera]]
+        name = "front"
+
+        [[camera]]
+        name = "side"
+        position = [2, 0, 0]
+        target = [0, 0, 0]
+        projection = "perspective"
+        fov = 45
+
+        [[mesh]]
+        file = "teapot.obj"
+        position = [0.5, 0, 0]
+        material = "red"
+
+        [[mesh]]
+        file = "/models/bunny.obj"
+        scale = 0.5
+
+        [material.red]
+        color = "#ff0000"
+
+        [[light]]
+        direction = [-1, 0, 0]
+        intensity = 0.5
+    "##;
+
+    fn close(l: &[f32], r: &[f32]) -> bool {
+        l.iter().zip(r).all(|(l, r)| (l - r).abs() < 1e-5)
+    }
+
+    #[test]
+    fn parses_toml() {
+        let scene = Scene::parse(TOML, false, Path::new("scenes")).unwrap();
+        assert_eq!(scene.output.background, Background::Transparent);
+        assert_eq!(scene.camera.projection, Projection::Perspective(45f32.to_radians()));
+        assert_eq!(scene.meshes, ["scenes/teapot.obj", "/models/bunny.obj"]);
+        assert_eq!(scene.instances[0].color, Some([255, 0, 0, 255]));
+        assert_eq!(scene.instances[1].mesh, 1);
+        //and the bunny keeps the materials of its file.
+        assert_eq!(scene.instances[1].color, None);
+        assert_eq!(scene.instances[1].pose.scale, 0.5);
+        //the light shines along -x, straight into the side camera's view.
+        assert!(close(&scene.lights[0].direction, &[0.0, 0.0, -1.0]));
+        assert_eq!(scene.lights[0].color, [0.5; 3]);
+        assert_eq!(scene.ambient, 0.2);
+    }
+
+    #[test]
+    fn parses_json() {
+        let json = r#"{
+            "output": {"file": "out.ppm", "width": 8, "height": 8, "format": "ppm", "wireframe": true},
+            "mesh": [{"file": "tetra.obj"}]
+        }"#;
+        let scene = Scene::parse(json, true, Path::new("")).unwrap();
+        assert_eq!(scene.output.mode, Mode::Wireframe);
+        assert_eq!(scene.camera, Camera::default());
+        assert!(scene.lights.is_empty());
+    }
+
+    #[test]
+    fn rejects_unknown_names() {
+        let missing_material = TOML.replace("[material.red]", "[material.blue]");
+        let error = Scene::parse(&missing_material, false, Path::new("")).unwrap_err();
+        assert_eq!(error, "there is no material named red");
+        let typo = TOML.replace("intensity", "intensty");
+        assert!(Scene::parse(&typo, false, Path::new("")).is_err());
+    }
+
+    #[test]
+    fn rejects_numbers_the_command_line_would() {
+        let broken = [
+            ("fov = 45", "fov = 180", "camera[1].fov must be between 0 and 180 degrees"),
+            ("fov = 45", "fov = nan", "camera[1].fov must be between 0 and 180 degrees"),
+            ("position = [2, 0, 0]", "position = [inf, 0, 0]", "camera[1].position must be finite"),
+            ("name = \"front\"", "half_height = 0", "camera[0].half_height must be finite and above 0"),
+            ("scale = 0.5", "scale = -inf", "mesh[1].scale must be finite"),
+            ("position = [0.5, 0, 0]", "rotation = [nan, 0, 0, 0]", "mesh[0].rotation must be finite"),
+            ("intensity = 0.5", "intensity = inf", "light[0].intensity must be finite"),
+            ("ambient = 0.2", "ambient = nan", "ambient must be finite"),
+        ];
+        for (from, to, expected) in broken {
+            let toml = TOML.replace(from, to);
+            assert_eq!(Scene::parse(&toml, false, Path::new("")).unwrap_err(), expected, "{to}");
+        }
+    }
+
+    #[test]
+    fn nodes_place_shared_instances() {
+        let toml = r##"
+            [output]
+            file = "out.png"
+            width = 8
+            height = 8
+
+            [[node]]
+            name = "arm"
+            parent = "base"
+            position = [1, 0, 0]
+            material = "red"
+
+            [[node]]
+            name = "base"
+            position = [0, 1, 0]
+            scale = 2
+
+            [[node]]
+            parent = "arm"
+            file = "tetra.obj"
+            position = [0, 0, 1]
+
+            [[node]]
+            parent = "base"
+            file = "tetra.obj"
+
+            [material.red]
+            color = "#ff0000"
+        "##;
+        let scene = Scene::parse(toml, false, Path::new("")).unwrap();
+        assert_eq!(scene.meshes, ["tetra.obj"]);
+        let [hand, foot] = &scene.instances[..] else {
+            panic!("expected two instances");
+        };
+        assert_eq!((hand.mesh, foot.mesh), (0, 0));
+        //parents apply after children, whatever order they are listed in.
+        assert!(close(&hand.pose.position, &[2.0, 1.0, 2.0]));
+        assert_eq!(hand.pose.scale, 2.0);
+        assert_eq!(hand.color, Some([255, 0, 0, 255]));
+        assert!(close(&foot.pose.position, &[0.0, 1.0, 0.0]));
+        let cycle = toml.replace("position = [0, 1, 0]", "parent = \"arm\"");
+        assert_eq!(
+            Scene::parse(&cycle, false, Path::new("")).unwrap_err(),
+            "arm is its own ancestor"
+        );
+    }
+}
@@ -0,0 +1,272 @@
+use crate::framebuffer::FrameBuffer;
+use crate::material::Material;
+use crate::splat::SPLAT_COLORMAP;
+
+//the color of the front of the view volume, darkening linearly towards the back.
+pub(crate) const BASE_COLOR: [u8; 4] = [165, 255, 214, 255];
+
+//a light infinitely far away, shining along `direction` in view space.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub(crate) struct Light {
+    pub(crate) direction: [f32; 3],
+    //linear RGB, already scaled by the light's intensity.
+    pub(crate) color: [f32; 3],
+}
+
+//shades every covered pixel by its depth over a row-major RGBA background, returning the image.
+pub(crate) fn shade_depth(frame: &FrameBuffer, background: &[u8]) -> Vec<u8> {
+    shade_with(frame, background, |_, _| BASE_COLOR)
+}
+
+//like shade_depth, with each object in its own color, indexed by the frame's object ids.
+pub(crate) fn shade_objects(frame: &FrameBuffer, background: &[u8], colors: &[[u8; 4]]) -> Vec<u8> {
+    shade_with(frame, background, |object, _| colors[object])
+}
+
+//like shade_depth, with each object in the color of its material, or BASE_COLOR without one.
+//Textures are sampled at the g-buffer's texture coordinates, and show their base color alone in
+//frames without a g-buffer.
+pub(crate) fn shade_materials(frame: &FrameBuffer, background: &[u8], materials: &[Option<&Material>]) -> Vec<u8> {
+    let uvs: Vec<[f32; 2]> = match &frame.gbuffer {
+        Some(gbuffer) => gbuffer.uv.elements_column_major_iter().copied().collect(),
+        None => vec![],
+    };
+    shade_with(frame, background, |object, i| match materials.get(object) {
+        Some(Some(material)) => material.color(uvs.get(i).copied()),
+        _ => BASE_COLOR,
+    })
+}
+
+//darkens the color of every covered pixel by its depth, given its object and row-major index.
+fn shade_with(frame: &FrameBuffer, background: &[u8], colors: impl Fn(usize, usize) -> [u8; 4]) -> Vec<u8> {
+    let mut data = background.to_vec();
+    for (i, (z, object)) in frame.covered_depth().zip(frame.covered_objects()).enumerate() {
+        let (Some(z), Some(object)) = (z, object) else {
+            continue;
+        };
+        //z = [-1, 1]
+        // -    -> [1 ,-1]
+        // +1   -> [2 , 0]
+        // /2   -> [1 , 0]
+        // *base-> [base, 0]
+        let base = colors(object, i);
+        let color = darken(base, z);
+        data[i * 4..i * 4 + 3].copy_from_slice(&color[..3]);
+        data[i * 4 + 3] = base[3];
+    }
+    data
+}
+
+fn darken(base: [u8; 4], z: f32) -> [u8; 4] {
+    let brightness = ((-z + 1.0) / 2.0).max(0.0);
+    #[allow(clippy::cast_possible_truncation)]
+    #[allow(clippy::cast_sign_loss)]
+    base.map(|c| (brightness * f32::from(c)) as u8)
+}
+
+//shades splats in the colors rasterize_splats left in the g-buffer, or through SPLAT_COLORMAP
+//when given their normalized `depths`, blending each over the background by its weight. Splats
+//without a color are shaded like shade_depth.
+pub(crate) fn shade_splats(frame: &FrameBuffer, background: &[u8], depths: Option<&[f32]>) -> Vec<u8> {
+    let mut data = background.to_vec();
+    let Some(gbuffer) = &frame.gbuffer else {
+        return shade_depth(frame, background);
+    };
+    let attributes = gbuffer.color.elements_column_major_iter().zip(gbuffer.weight.elements_column_major_iter());
+    for (i, (z, (color, &weight))) in frame.covered_depth().zip(attributes).enumerate() {
+        let Some(z) = z else {
+            continue;
+        };
+        let color = match (depths, color) {
+            (Some(depths), _) => Some(SPLAT_COLORMAP.sample(depths[i])),
+            (None, color) => *color,
+        };
+        #[allow(clippy::cast_possible_truncation)]
+        #[allow(clippy::cast_sign_loss)]
+        let color = match color {
+            Some(color) => color.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8),
+            None => {
+                let [r, g, b, _] = darken(BASE_COLOR, z);
+                [r, g, b]
+            }
+        };
+        #[allow(clippy::cast_possible_truncation)]
+        #[allow(clippy::cast_sign_loss)]
+        let blend = |under: u8, over: u8| (f32::from(under) * (1.0 - weight) + f32::from(over) * weight).round() as u8;
+        for c in 0..3 {
+            data[i * 4 + c] = blend(data[i * 4 + c], color[c]);
+        }
+        data[i * 4 + 3] = blend(data[i * 4 + 3], 255);
+    }
+    data
+}
+
+//shades every covered pixel by how much each light falls on its g-buffer normal, plus an
+//ambient term, in the color of its object. Surfaces are lit from both sides.
+pub(crate) fn shade_lit(
+    frame: &FrameBuffer,
+    background: &[u8],
+    colors: &[[u8; 4]],
+    lights: &[Light],
+    ambient: f32,
+) -> Vec<u8> {
+    let mut data = background.to_vec();
+    let Some(gbuffer) = &frame.gbuffer else {
+        return shade_objects(frame, background, colors);
+    };
+    let normals = gbuffer.normal.elements_column_major_iter();
+    for (i, (object, normal)) in frame.covered_objects().zip(normals).enumerate() {
+        let Some(object) = object else {
+            continue;
+        };
+        let base = colors[object];
+        data[i * 4..i * 4 + 3].copy_from_slice(&light(base, lighting(*normal, lights, ambient)));
+        data[i * 4 + 3] = base[3];
+    }
+    data
+}
+
+//shades every covered pixel in its interpolated vertex color, or the color of its object for
+//meshes without vertex colors, as they are or lit like shade_lit when there are `lights`.
+pub(crate) fn shade_vertex_colors(
+    frame: &FrameBuffer,
+    background: &[u8],
+    colors: &[[u8; 4]],
+    lights: &[Light],
+    ambient: f32,
+) -> Vec<u8> {
+    let mut data = background.to_vec();
+    let Some(gbuffer) = &frame.gbuffer else {
+        return shade_objects(frame, background, colors);
+    };
+    let attributes = gbuffer.color.elements_column_major_iter().zip(gbuffer.normal.elements_column_major_iter());
+    for (i, (object, (color, normal))) in frame.covered_objects().zip(attributes).enumerate() {
+        let Some(object) = object else {
+            continue;
+        };
+        #[allow(clippy::cast_possible_truncation)]
+        #[allow(clippy::cast_sign_loss)]
+        let base = match color {
+            Some(color) => {
+                let [r, g, b] = color.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8);
+                [r, g, b, 255]
+            }
+            None => colors[object],
+        };
+        let color = match lights {
+            [] => [base[0], base[1], base[2]],
+            _ => light(base, lighting(*normal, lights, ambient)),
+        };
+        data[i * 4..i * 4 + 3].copy_from_slice(&color);
+        data[i * 4 + 3] = base[3];
+    }
+    data
+}
+
+//how much light falls on a surface of `normal`, per channel. Surfaces are lit from both sides.
+fn lighting(normal: [f32; 3], lights: &[Light], ambient: f32) -> [f32; 3] {
+    //the side of the surface facing the camera, which looks down -z.
+    let normal = if normal[2] < 0.0 { normal.map(|n| -n) } else { normal };
+    lights.iter().fold([ambient; 3], |sum, light| {
+        let d = light.direction;
+        let lambert = (-(normal[0] * d[0] + normal[1] * d[1] + normal[2] * d[2])).max(0.0);
+        [0, 1, 2].map(|c| sum[c] + light.color[c] * lambert)
+    })
+}
+
+#[allow(clippy::cast_possible_truncation)]
+#[allow(clippy::cast_sign_loss)]
+fn light(base: [u8; 4], light: [f32; 3]) -> [u8; 3] {
+    [0, 1, 2].map(|c| (f32::from(base[c]) * light[c]).round().clamp(0.0, 255.0) as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::framebuffer::FrameBuffer;
+    use crate::material::Material;
+    use crate::shade::{shade_depth, shade_lit, shade_materials, shade_splats, shade_vertex_colors, Light};
+
+    #[test]
+    fn background_shows_through_uncovered_pixels() {
+        let mut frame = FrameBuffer::new(2, 1, false);
+        frame.depth[(1, 0)] = -1.0;
+        frame.coverage[(1, 0)] = true;
+        let data = shade_depth(&frame, &[1, 2, 3, 0, 1, 2, 3, 0]);
+        assert_eq!(data, vec![1, 2, 3, 0, 165, 255, 214, 255]);
+    }
+
+    #[test]
+    fn lights_by_normal_and_object() {
+        let mut frame = FrameBuffer::new(2, 1, true);
+        for x in 0..2 {
+            frame.coverage[(x, 0)] = true;
+            frame.objects[(x, 0)] = x;
+        }
+        let gbuffer = frame.gbuffer.as_mut().unwrap();
+        gbuffer.normal[(0, 0)] = [0.0, 0.0, 1.0];
+        //facing away from the camera, so lit as if flipped.
+        gbuffer.normal[(1, 0)] = [0.0, 0.6, -0.8];
+        let light = Light {
+            direction: [0.0, 0.0, -1.0],
+            color: [1.0, 0.5, 1.0],
+        };
+        let colors = [[200, 200, 200, 255], [100, 100, 100, 128]];
+        let data = shade_lit(&frame, &[0; 8], &colors, &[light], 0.1);
+        assert_eq!(data, vec![220, 120, 220, 255, 90, 50, 90, 128]);
+    }
+
+    #[test]
+    fn materials_color_their_objects() {
+        let mut frame = FrameBuffer::new(3, 1, false);
+        for x in 0..3 {
+            frame.depth[(x, 0)] = -1.0;
+            frame.coverage[(x, 0)] = true;
+            frame.objects[(x, 0)] = x;
+        }
+        let red = Material {
+            color: [1.0, 0.0, 0.0, 1.0],
+            texture: None,
+        };
+        let data = shade_materials(&frame, &[0; 12], &[Some(&red), None]);
+        assert_eq!(data, vec![255, 0, 0, 255, 165, 255, 214, 255, 165, 255, 214, 255]);
+    }
+
+    #[test]
+    fn vertex_colors_as_they_are_or_lit() {
+        let mut frame = FrameBuffer::new(2, 1, true);
+        for x in 0..2 {
+            frame.coverage[(x, 0)] = true;
+            frame.objects[(x, 0)] = x;
+        }
+        let gbuffer = frame.gbuffer.as_mut().unwrap();
+        gbuffer.color[(0, 0)] = Some([1.0, 0.5, 0.0]);
+        gbuffer.normal[(0, 0)] = [0.0, 0.6, 0.8];
+        let colors = [[0, 0, 0, 255], [100, 100, 100, 128]];
+        let data = shade_vertex_colors(&frame, &[0; 8], &colors, &[], 0.0);
+        assert_eq!(data, vec![255, 128, 0, 255, 100, 100, 100, 128]);
+        let light = Light {
+            direction: [0.0, 0.0, -1.0],
+            color: [0.5; 3],
+        };
+        let data = shade_vertex_colors(&frame, &[0; 8], &colors, &[light], 0.1);
+        assert_eq!(data[..4], [128, 64, 0, 255]);
+    }
+
+    #[test]
+    fn splats_blend_by_weight() {
+        let mut frame = FrameBuffer::new(3, 1, true);
+        for x in 0..3 {
+            frame.depth[(x, 0)] = -1.0;
+            frame.coverage[(x, 0)] = true;
+        }
+        let gbuffer = frame.gbuffer.as_mut().unwrap();
+        gbuffer.color[(0, 0)] = Some([1.0, 0.0, 0.0]);
+        gbuffer.color[(1, 0)] = Some([1.0, 0.0, 0.0]);
+        gbuffer.weight[(1, 0)] = 0.5;
+        let data = shade_splats(&frame, &[0; 12], None);
+        assert_eq!(data, vec![255, 0, 0, 255, 128, 0, 0, 128, 165, 255, 214, 255]);
+        //depths replace the colors.
+        let data = shade_splats(&frame, &[0; 12], Some(&[0.0; 3]));
+        assert_eq!(data[..4], [71, 1, 85, 255]);
+    }
+}
@@ -6,20 +6,73 @@ use Error::Init;
 pub struct Space {
     x_transform: Transform,
     y_transform: Transform,
+    scissor: Rect,
 }
 #[derive(Debug)]
 pub enum Error {
     Init,
 }
 
+//pixel coordinates are kept within this many pixels of the viewport's corner, so that vertices far
+//outside it cannot overflow rasterize()'s integer arithmetic.
+const GUARD_BAND: f32 = (1 << 24) as f32;
+
+//a rectangle of pixels whose first pixel is at (x, y). It may reach outside of the image, or be
+//larger than it.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Rect {
+    pub(crate) x: i64,
+    pub(crate) y: i64,
+    pub(crate) width: u64,
+    pub(crate) height: u64,
+}
+
+impl Rect {
+    pub(crate) fn new(x: i64, y: i64, width: u64, height: u64) -> Rect {
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+    //the pixels in both rectangles, which may be none at all.
+    pub(crate) fn intersect(self, other: Rect) -> Rect {
+        let (x, y) = (self.x.max(other.x), self.y.max(other.y));
+        let right = (self.x + self.width as i64).min(other.x + other.width as i64);
+        let bottom = (self.y + self.height as i64).min(other.y + other.height as i64);
+        Rect::new(x, y, (right - x).max(0) as u64, (bottom - y).max(0) as u64)
+    }
+}
+
 impl Space {
     pub fn new(width: NonZeroU64, height: NonZeroU64) -> Result<Space, Error> {
+        Space::viewport(Rect::new(0, 0, width.get(), height.get()))
+    }
+    //maps the view volume onto the viewport rectangle of pixels, rather than onto the whole image.
+    //Only pixels within the viewport are written, until a scissor says otherwise.
+    pub fn viewport(viewport: Rect) -> Result<Space, Error> {
+        let width = NonZeroU64::new(viewport.width).ok_or(Init)?;
+        let height = NonZeroU64::new(viewport.height).ok_or(Init)?;
         let vv = ViewVolume::new(width, height);
+        let mut x_transform = Transform::new(width, vv.left, vv.right).map_err(|_| Init)?;
+        let mut y_transform = Transform::new(height, vv.bottom, vv.top).map_err(|_| Init)?;
+        x_transform.offset = viewport.x;
+        y_transform.offset = viewport.y;
         Ok(Space {
-            x_transform: Transform::new(width, vv.left, vv.right).map_err(|_| Init)?,
-            y_transform: Transform::new(height, vv.bottom, vv.top).map_err(|_| Init)?,
+            x_transform,
+            y_transform,
+            scissor: viewport,
         })
     }
+    //limits the pixels rasterize() writes to the scissor rectangle, which need not lie within the
+    //viewport: with a viewport larger than the image, scissors render it a tile at a time.
+    pub fn with_scissor(self, scissor: Rect) -> Space {
+        Space { scissor, ..self }
+    }
+    pub(crate) fn scissor(&self) -> Rect {
+        self.scissor
+    }
     pub fn window_to_pixel(&self, point_window: Point) -> Fragment {
         Fragment {
             x: self.x_transform.window_to_pixel(point_window.x),
@@ -27,6 +80,13 @@ impl Space {
             z: -point_window.z,
         }
     }
+    //inverse of window_to_pixel, sampling the center of the pixel.
+    pub fn pixel_to_window(&self, x: u64, y: u64) -> (f32, f32) {
+        (
+            self.x_transform.pixel_to_window(x),
+            self.y_transform.pixel_to_window(y),
+        )
+    }
 }
 
 //a pixel with depth
@@ -92,6 +152,8 @@ pub struct Transform {
     pub(crate) shift: f32,
     pub(crate) scale: f32,
     pub extent: u64,
+    //the first pixel of the extent.
+    pub(crate) offset: i64,
 }
 #[derive(Debug, PartialEq)]
 pub(crate) enum PixelTransformError {
@@ -112,13 +174,18 @@ impl Transform {
                 extent: pixel_extent.get(),
                 shift: vv_max * (pixel_extent.get() as f32) / vv_diff,
                 scale: (pixel_extent.get() as f32) / vv_diff,
+                offset: 0,
             })
         }
     }
 
-    //flooring operation.
+    //flooring operation. Coordinates outside of the extent map to pixels outside of it.
     fn window_to_pixel(&self, window_coord: f32) -> i64 {
-        (((self.scale * window_coord) + self.shift) as i64).clamp(0, self.extent as i64 - 1)
+        ((self.scale * window_coord) + self.shift).floor().clamp(-GUARD_BAND, GUARD_BAND) as i64 + self.offset
+    }
+
+    fn pixel_to_window(&self, pixel_coord: u64) -> f32 {
+        ((pixel_coord as i64 - self.offset) as f32 + 0.5 - self.shift) / self.scale
     }
 }
 
@@ -130,7 +197,7 @@ mod tests {
     mod pixel_transform {
         use super::{NonZeroU64, Transform};
         use crate::space::PixelTransformError::BadViewVolume;
-        use crate::space::{Space, ViewVolume};
+        use crate::space::{Rect, Space, ViewVolume};
 
         #[test]
         fn pixel_bigger() {
@@ -207,7 +274,8 @@ mod tests {
                 space.x_transform.window_to_pixel(max_vv),
                 150
             );
-            assert_eq!(space.x_transform.window_to_pixel(max_pic), 199);
+            //the right edge of the last pixel is the left edge of the next, past the image.
+            assert_eq!(space.x_transform.window_to_pixel(max_pic), 200);
             assert_eq!(
                 space.y_transform.window_to_pixel(min_vv),
                 0
@@ -215,9 +283,35 @@ mod tests {
 
             assert_eq!(
                 space.y_transform.window_to_pixel(max_vv),
-                99
+                100
             );
-            assert_eq!(space.y_transform.window_to_pixel(1.0), 99);
+            assert_eq!(space.y_transform.window_to_pixel(0.999), 99);
+        }
+        #[test]
+        fn pixel_to_window_round_trip() {
+            let space =
+                Space::new(NonZeroU64::new(200).unwrap(), NonZeroU64::new(100).unwrap()).unwrap();
+            for pixel in [0, 37, 99] {
+                let (x, y) = space.pixel_to_window(pixel * 2, pixel);
+                assert_eq!(space.x_transform.window_to_pixel(x), (pixel * 2) as i64);
+                assert_eq!(space.y_transform.window_to_pixel(y), pixel as i64);
+            }
+        }
+        #[test]
+        fn viewport_offsets_pixels() {
+            let space = Space::viewport(Rect::new(200, -50, 100, 100)).unwrap();
+            assert_eq!(space.x_transform.window_to_pixel(-1.0), 200);
+            assert_eq!(space.x_transform.window_to_pixel(0.999), 299);
+            //not clamped to the viewport, or the image.
+            assert_eq!(space.y_transform.window_to_pixel(-5.0), -250);
+            assert_eq!(space.y_transform.window_to_pixel(f32::MAX), (1 << 24) - 50);
+            let (x, y) = space.pixel_to_window(250, 0);
+            assert_eq!((x, y), (0.01, 0.01));
+            assert_eq!(space.scissor(), Rect::new(200, -50, 100, 100));
+            assert!(Space::viewport(Rect::new(0, 0, 0, 10)).is_err());
+            let scissor = Rect::new(250, 0, 100, 20).intersect(space.scissor());
+            assert_eq!(scissor, Rect::new(250, 0, 50, 20));
+            assert_eq!(scissor.intersect(Rect::new(0, 0, 10, 10)).width, 0);
         }
     }
 }
@@ -0,0 +1,219 @@
+use crate::camera::ModelView;
+use crate::depthmap::Colormap;
+use crate::framebuffer::FrameBuffer;
+use crate::gbuffer::Surface;
+use crate::point::{color_depth, Point};
+use crate::space::{Fragment, Rect, Space};
+use tobj::{Mesh, Model};
+
+//the shapes that --mode splats stamps every vertex as.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum SplatShape {
+    Square,
+    Circle,
+    //a disc that fades out from its center, as far as the radius.
+    Gaussian,
+}
+
+impl SplatShape {
+    pub(crate) fn from_name(name: &str) -> Option<SplatShape> {
+        match name {
+            "square" => Some(SplatShape::Square),
+            "circle" => Some(SplatShape::Circle),
+            "gaussian" => Some(SplatShape::Gaussian),
+            _ => None,
+        }
+    }
+}
+
+//what splats are colored by.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum SplatColor {
+    //their vertex colors, or shaded by depth like --mode depth without them.
+    Vertex,
+    //the height of their vertices within the mesh, through a colormap.
+    Height,
+    //their depth within the image, through a colormap.
+    Depth,
+}
+
+impl SplatColor {
+    pub(crate) fn from_name(name: &str) -> Option<SplatColor> {
+        match name {
+            "vertex" => Some(SplatColor::Vertex),
+            "height" => Some(SplatColor::Height),
+            "depth" => Some(SplatColor::Depth),
+            _ => None,
+        }
+    }
+}
+
+//how splats are drawn. The radius is in pixels, or in the units of the mesh, fitted to the view,
+//when `world` is set.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub(crate) struct Splats {
+    pub(crate) shape: SplatShape,
+    pub(crate) radius: f32,
+    pub(crate) world: bool,
+    pub(crate) color: SplatColor,
+}
+
+impl Default for Splats {
+    fn default() -> Self {
+        Splats {
+            shape: SplatShape::Circle,
+            radius: 2.0,
+            world: false,
+            color: SplatColor::Vertex,
+        }
+    }
+}
+
+//the largest --splat-radius, in pixels or units of the fitted mesh, well past any image's size.
+pub(crate) const MAX_RADIUS: f32 = 10_000.0;
+
+//the colormap that heights and depths are shown through.
+pub(crate) const SPLAT_COLORMAP: Colormap = Colormap::Viridis;
+
+//the lowest and highest y of every vertex of the models, which height colors span.
+pub(crate) fn height_range(models: &[Model]) -> (f32, f32) {
+    models
+        .iter()
+        .flat_map(|model| model.mesh.positions.iter().skip(1).step_by(3))
+        .fold((f32::MAX, -f32::MAX), |(low, high), &y| (low.min(y), high.max(y)))
+}
+
+//rasterizes every vertex of the mesh as a splat, whatever faces, lines or points it has, depth
+//tested like any other fragment. Within a Gaussian splat, fragments lie further back the more
+//they fade, so that the centers of neighbouring splats win over its edges.
+pub(crate) fn rasterize_splats(
+    frame: &mut FrameBuffer,
+    space: &Space,
+    mesh: &Mesh,
+    view: &ModelView,
+    splats: &Splats,
+    heights: (f32, f32),
+) {
+    let frame_rect = Rect::new(0, 0, frame.depth.num_rows() as u64, frame.depth.num_columns() as u64);
+    let clip = space.scissor().intersect(frame_rect);
+    //window units per pixel, which pixel radii are turned into depth with.
+    let pixel = {
+        let ((x0, _), (x1, _)) = (space.pixel_to_window(0, 0), space.pixel_to_window(1, 0));
+        (x1 - x0).abs()
+    };
+    for (index, position) in (0u32..).zip(mesh.positions.chunks_exact(3)) {
+        let position = [position[0], position[1], position[2]];
+        let Some([x, y, z]) = view.position(position) else {
+            continue;
+        };
+        let radius = match splats.world {
+            false => splats.radius,
+            true => match view.scale_at(position) {
+                Some(scale) => splats.radius * scale / pixel,
+                None => continue,
+            },
+        };
+        let center = space.window_to_pixel(Point { x, y, z });
+        //splats reaching past the whole clip rectangle cover no more of it, however large, and world
+        //radii grow without bound close to the camera.
+        #[allow(clippy::cast_possible_truncation)]
+        let reach = (radius.floor() as i64).min(clip.width.max(clip.height) as i64);
+        let x_range = (center.x - reach).max(clip.x)..=(center.x + reach).min(clip.x + clip.width as i64 - 1);
+        let y_range = (center.y - reach).max(clip.y)..=(center.y + reach).min(clip.y + clip.height as i64 - 1);
+        if x_range.is_empty() || y_range.is_empty() {
+            continue;
+        }
+        let surface = frame.gbuffer.is_some().then(|| {
+            let surface = Surface::new(mesh, &[index; 3]).place([[x, y, z]; 3], |n| view.normal(n));
+            match splats.color {
+                SplatColor::Height => {
+                    let (low, high) = heights;
+                    let t = if high > low { (position[1] - low) / (high - low) } else { 0.0 };
+                    surface.colored(SPLAT_COLORMAP.sample(t))
+                }
+                SplatColor::Vertex | SplatColor::Depth => surface,
+            }
+        });
+        for py in y_range {
+            for px in x_range.clone() {
+                let offset = ((px - center.x) as f32, (py - center.y) as f32);
+                let Some(weight) = weight(splats.shape, offset, radius) else {
+                    continue;
+                };
+                let z = center.z + (1.0 - weight) * radius * pixel;
+                let visible = color_depth(frame, Fragment { x: px, y: py, z });
+                if let (true, Some(gbuffer), Some(surface)) = (visible, frame.gbuffer.as_mut(), &surface) {
+                    let (px, py) = (px as usize, py as usize);
+                    gbuffer.write(px, py, surface, [1.0, 0.0, 0.0]);
+                    gbuffer.weight[(px, py)] = weight;
+                }
+            }
+        }
+    }
+}
+
+//how opaque a splat of `radius` pixels is at the pixel `offset` from its center, or None outside it.
+fn weight(shape: SplatShape, (dx, dy): (f32, f32), radius: f32) -> Option<f32> {
+    let distance = dx * dx + dy * dy;
+    match shape {
+        SplatShape::Square => Some(1.0),
+        SplatShape::Circle if distance <= radius * radius => Some(1.0),
+        //a standard deviation of half the radius fades to about 14% at the rim.
+        SplatShape::Gaussian if distance <= radius * radius => {
+            let sigma = (radius / 2.0).max(0.5);
+            Some((-distance / (2.0 * sigma * sigma)).exp())
+        }
+        SplatShape::Circle | SplatShape::Gaussian => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::camera::{Camera, ModelView, Pose};
+    use crate::framebuffer::FrameBuffer;
+    use crate::space::{Rect, Space};
+    use crate::splat::{rasterize_splats, SplatColor, SplatShape, Splats};
+    use tobj::Mesh;
+
+    fn splat(shape: SplatShape, radius: f32, world: bool) -> FrameBuffer {
+        let view = ModelView::new(&Camera::default(), &Pose::IDENTITY);
+        let space = Space::viewport(Rect::new(0, 0, 20, 20)).unwrap();
+        let mut frame = FrameBuffer::new(20, 20, true);
+        //a point cloud of one vertex, in the middle of the view.
+        let mesh = Mesh {
+            positions: vec![0.0; 3],
+            vertex_color: vec![1.0, 0.0, 0.0],
+            ..Mesh::default()
+        };
+        let splats = Splats {
+            shape,
+            radius,
+            world,
+            color: SplatColor::Vertex,
+        };
+        rasterize_splats(&mut frame, &space, &mesh, &view, &splats, (0.0, 0.0));
+        frame
+    }
+
+    fn count(frame: &FrameBuffer) -> usize {
+        frame.coverage.elements_row_major_iter().filter(|&&covered| covered).count()
+    }
+
+    #[test]
+    fn shapes_and_radii() {
+        assert_eq!(count(&splat(SplatShape::Square, 2.0, false)), 25);
+        assert_eq!(count(&splat(SplatShape::Circle, 2.0, false)), 13);
+        assert_eq!(count(&splat(SplatShape::Circle, 0.0, false)), 1);
+        assert_eq!(count(&splat(SplatShape::Square, 1e30, false)), 400);
+        //a tenth of the view's height of 2 is two of its 20 pixels.
+        assert_eq!(count(&splat(SplatShape::Square, 0.2, true)), 25);
+
+        let frame = splat(SplatShape::Gaussian, 3.0, false);
+        let gbuffer = frame.gbuffer.as_ref().unwrap();
+        assert_eq!(gbuffer.color[(10, 10)], Some([1.0, 0.0, 0.0]));
+        assert_eq!(gbuffer.weight[(10, 10)], 1.0);
+        assert!(gbuffer.weight[(13, 10)] < gbuffer.weight[(11, 10)]);
+        //fainter fragments lie further back.
+        assert!(frame.depth[(13, 10)] > frame.depth[(10, 10)]);
+    }
+}
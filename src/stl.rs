@@ -0,0 +1,121 @@
+use crate::mesh::{invalid, model};
+use std::io;
+use tobj::Model;
+
+//STL files are unindexed triangles, so every triangle gets its own three vertices.
+
+//binary STLs are an 80 byte header, a triangle count and 50 bytes per triangle. Some of their
+//headers start with "solid" like ASCII STLs do, but ASCII files practically never add up.
+pub(crate) fn is_binary(bytes: &[u8]) -> bool {
+    match bytes.get(80..84) {
+        Some(count) => {
+            let count = u32::from_le_bytes([count[0], count[1], count[2], count[3]]);
+            84 + 50 * u64::from(count) == bytes.len() as u64
+        }
+        None => false,
+    }
+}
+
+pub(crate) fn load(bytes: &[u8]) -> io::Result<Vec<Model>> {
+    if is_binary(bytes) {
+        Ok(vec![load_binary(bytes)?])
+    } else {
+        load_ascii(bytes)
+    }
+}
+
+//each triangle is a normal, three corners and an attribute count, in little endian floats. The
+//normals are left out, as the corners' winding says the same.
+fn load_binary(bytes: &[u8]) -> io::Result<Model> {
+    let triangles = &bytes[84..];
+    let mut positions = Vec::with_capacity(triangles.len() / 50 * 9);
+    for triangle in triangles.chunks_exact(50) {
+        let corners = &triangle[12..48];
+        positions.extend(corners.chunks_exact(4).map(|f| f32::from_le_bytes([f[0], f[1], f[2], f[3]])));
+    }
+    unindexed(positions)
+}
+
+//"solid name", then facets of "facet normal", "outer loop", three "vertex x y z", "endloop" and
+//"endfacet", then "endsolid". A file may hold several solids, each becoming a model.
+fn load_ascii(bytes: &[u8]) -> io::Result<Vec<Model>> {
+    let text = std::str::from_utf8(bytes).map_err(|e| invalid(format!("not a binary STL, nor ASCII: {e}")))?;
+    let mut models = vec![];
+    let mut solid: Option<(String, Vec<f32>)> = None;
+    let mut corners = 0;
+    for (number, line) in text.lines().enumerate() {
+        let error = |message: &str| invalid(format!("line {}: {message}", number + 1));
+        let mut words = line.split_whitespace();
+        match (words.next(), &mut solid) {
+            (None, _) => {}
+            (Some("solid"), None) => solid = Some((words.collect::<Vec<_>>().join(" "), vec![])),
+            (Some("facet" | "outer"), Some(_)) => {}
+            (Some("vertex"), Some((_, positions))) => {
+                for _ in 0..3 {
+                    let value = words.next().and_then(|word| word.parse().ok());
+                    positions.push(value.ok_or_else(|| error("expected vertex x y z"))?);
+                }
+                corners += 1;
+            }
+            (Some("endloop"), Some(_)) if corners != 3 => return Err(error("a facet needs 3 vertices")),
+            (Some("endloop"), Some(_)) => corners = 0,
+            (Some("endfacet"), Some(_)) => {}
+            (Some("endsolid"), Some(_)) => {
+                let (name, positions) = solid.take().unwrap_or_default();
+                let mut model = unindexed(positions)?;
+                if !name.is_empty() {
+                    model.name = name;
+                }
+                models.push(model);
+            }
+            (Some(word), _) => return Err(error(&format!("unexpected {word}"))),
+        }
+    }
+    if solid.is_some() {
+        return Err(invalid(String::from("the file ends inside a solid")));
+    }
+    Ok(models)
+}
+
+//a model of triangles that share no vertices, each corner indexed in order.
+fn unindexed(positions: Vec<f32>) -> io::Result<Model> {
+    let vertices = u32::try_from(positions.len() / 3).map_err(|_| invalid(String::from("too many triangles")))?;
+    Ok(model(positions, (0..vertices).collect(), vec![]))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::stl::load;
+
+    #[test]
+    fn ascii_solids() {
+        let text = "solid part one\n\
+            facet normal 0 0 1\n outer loop\n  vertex 0 0 0\n  vertex 1 0 0\n  vertex 0 1 0\n endloop\nendfacet\n\
+            endsolid part one\nsolid\nendsolid\n";
+        let models = load(text.as_bytes()).unwrap();
+        assert_eq!(models.len(), 2);
+        assert_eq!(models[0].name, "part one");
+        assert_eq!(models[0].mesh.positions, [0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0]);
+        assert_eq!(models[0].mesh.indices, [0, 1, 2]);
+        assert!(models[1].mesh.indices.is_empty());
+
+        let text = "solid\nfacet normal 0 0 1\nouter loop\nvertex 0 0 0\nvertex 1 0\n";
+        assert_eq!(load(text.as_bytes()).unwrap_err().to_string(), "line 5: expected vertex x y z");
+    }
+
+    #[test]
+    fn binary_triangles() {
+        let mut bytes = b"solid binary after all".to_vec();
+        bytes.resize(80, 0);
+        bytes.extend_from_slice(&2u32.to_le_bytes());
+        for triangle in 0..2 {
+            let floats = [0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, triangle as f32];
+            bytes.extend(floats.iter().flat_map(|f: &f32| f.to_le_bytes()));
+            bytes.extend_from_slice(&[0, 0]);
+        }
+        let models = load(&bytes).unwrap();
+        assert_eq!(models[0].mesh.indices, [0, 1, 2, 3, 4, 5]);
+        assert_eq!(models[0].mesh.positions[3..6], [1.0, 0.0, 0.0]);
+        assert_eq!(models[0].mesh.positions[17], 1.0);
+    }
+}
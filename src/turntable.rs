@@ -0,0 +1,75 @@
+use crate::camera::{Pose, Quat, Vec3};
+use std::f32::consts::{FRAC_1_SQRT_2, TAU};
+
+//the axis a turntable spins the models around, through the origin.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Axis {
+    X,
+    Y,
+    Z,
+}
+
+impl Axis {
+    pub(crate) fn from_name(name: &str) -> Option<Axis> {
+        match name {
+            "x" => Some(Axis::X),
+            "y" => Some(Axis::Y),
+            "z" => Some(Axis::Z),
+            _ => None,
+        }
+    }
+
+    fn vector(self) -> Vec3 {
+        match self {
+            Axis::X => [1.0, 0.0, 0.0],
+            Axis::Y => [0.0, 1.0, 0.0],
+            Axis::Z => [0.0, 0.0, 1.0],
+        }
+    }
+}
+
+//the pose of the models in each of `frames` evenly spaced steps of one full turn.
+//models are shrunk so that the corners of the [-1,1] cube stay in view at every angle.
+pub(crate) fn turntable(axis: Axis, frames: usize) -> impl ExactSizeIterator<Item = Pose> {
+    (0..frames).map(move |frame| {
+        let angle = TAU * frame as f32 / frames as f32;
+        rotate(axis, angle, FRAC_1_SQRT_2)
+    })
+}
+
+//turns counter-clockwise by `angle` radians, and scales.
+pub(crate) fn rotate(axis: Axis, angle: f32, scale: f32) -> Pose {
+    Pose {
+        position: [0.0; 3],
+        rotation: Quat::from_axis_angle(axis.vector(), angle),
+        scale,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::turntable::{rotate, turntable, Axis};
+    use std::f32::consts::FRAC_PI_2;
+
+    fn close(l: &[f32], r: &[f32]) -> bool {
+        l.iter().zip(r).all(|(l, r)| (l - r).abs() < 1e-6)
+    }
+
+    #[test]
+    fn quarter_turns() {
+        let point = [1.0, 0.0, 0.0];
+        let y = rotate(Axis::Y, FRAC_PI_2, 1.0);
+        assert!(close(&y.apply(point), &[0.0, 0.0, -1.0]));
+        let z = rotate(Axis::Z, FRAC_PI_2, 2.0);
+        assert!(close(&z.apply(point), &[0.0, 2.0, 0.0]));
+        let x = rotate(Axis::X, FRAC_PI_2, 1.0);
+        assert!(close(&x.apply(point), &[1.0, 0.0, 0.0]));
+    }
+
+    #[test]
+    fn corners_stay_in_view() {
+        for pose in turntable(Axis::Y, 12) {
+            assert!(pose.apply([1.0, 1.0, 1.0]).iter().all(|p| p.abs() <= 1.0));
+        }
+    }
+}
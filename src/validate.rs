@@ -0,0 +1,356 @@
+use crate::mesh::{OutOfRange, Primitive};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use tobj::Model;
+
+//how many examples of each problem a report lists.
+const EXAMPLES: usize = 10;
+
+//how a --validate report is printed.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum ReportFormat {
+    Text,
+    Json,
+}
+
+impl ReportFormat {
+    pub(crate) fn from_name(name: &str) -> Option<ReportFormat> {
+        match name {
+            "text" => Some(ReportFormat::Text),
+            "json" => Some(ReportFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+//how often a problem occurs, and its first few occurrences.
+#[derive(Debug, PartialEq, Serialize)]
+pub(crate) struct Finding<T> {
+    pub(crate) count: usize,
+    pub(crate) examples: Vec<T>,
+}
+
+impl<T> Finding<T> {
+    fn add(&mut self, example: T) {
+        self.count += 1;
+        if self.examples.len() < EXAMPLES {
+            self.examples.push(example);
+        }
+    }
+}
+
+impl<T> Default for Finding<T> {
+    fn default() -> Self {
+        Finding {
+            count: 0,
+            examples: vec![],
+        }
+    }
+}
+
+//the problems of one model of a mesh file. Triangles and vertices are numbered from 0, and edges
+//are pairs of vertices, where vertices at the same position count as one: the loader splits
+//vertices wherever their normals or texture coordinates differ, which is not a crack.
+#[derive(Debug, PartialEq, Default, Serialize)]
+pub(crate) struct ModelReport {
+    pub(crate) name: String,
+    pub(crate) vertices: usize,
+    pub(crate) triangles: usize,
+    //vertices with a NaN or infinite coordinate.
+    pub(crate) non_finite_positions: Finding<usize>,
+    //triangles without area.
+    pub(crate) degenerate_triangles: Finding<usize>,
+    //edges of more than two triangles.
+    pub(crate) non_manifold_edges: Finding<[usize; 2]>,
+    //edges that both of their triangles run along in the same direction.
+    pub(crate) inconsistent_winding: Finding<[usize; 2]>,
+    //vertices at the position of an earlier vertex, with that vertex.
+    pub(crate) duplicate_vertices: Finding<[usize; 2]>,
+    //the groups of triangles that share no vertex with each other.
+    pub(crate) components: usize,
+}
+
+impl ModelReport {
+    //how many kinds of problems the model has. Duplicate vertices and several components are
+    //only noted, as the loader and many sound models have them.
+    pub(crate) fn problems(&self) -> usize {
+        [
+            self.non_finite_positions.count,
+            self.degenerate_triangles.count,
+            self.non_manifold_edges.count,
+            self.inconsistent_winding.count,
+        ]
+        .iter()
+        .filter(|&&count| count > 0)
+        .count()
+    }
+}
+
+//the reports of every model of a mesh file, with the faces that the loader left out for
+//referring to a vertex the file does not have.
+#[derive(Debug, PartialEq, Serialize)]
+pub(crate) struct Report {
+    pub(crate) mesh: String,
+    pub(crate) out_of_range_indices: Finding<OutOfRange>,
+    pub(crate) models: Vec<ModelReport>,
+}
+
+impl Report {
+    //only models of triangles are checked, as lines and points have no surface to break. The
+    //models should be as the file has them, before any fitting, which would turn one infinite
+    //position into every position being NaN.
+    pub(crate) fn new(mesh: &str, models: &[Model], out_of_range: &[OutOfRange]) -> Report {
+        let triangles = models.iter().filter(|model| Primitive::of(&model.mesh) == Primitive::Triangles);
+        let mut out_of_range_indices = Finding::default();
+        out_of_range.iter().for_each(|&face| out_of_range_indices.add(face));
+        Report {
+            mesh: String::from(mesh),
+            out_of_range_indices,
+            models: triangles.map(validate).collect(),
+        }
+    }
+
+    pub(crate) fn problems(&self) -> usize {
+        usize::from(self.out_of_range_indices.count > 0) + self.models.iter().map(ModelReport::problems).sum::<usize>()
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}: {}", self.mesh, count(self.models.len(), "model"))?;
+        let out_of_range = |face: &OutOfRange| format!("face {} to vertex {}", face.face, face.index);
+        line(f, "faces with out of range indices", &self.out_of_range_indices, out_of_range)?;
+        for model in &self.models {
+            writeln!(
+                f,
+                "model \"{}\": {}, {}, {}",
+                model.name,
+                count(model.vertices, "vertex"),
+                count(model.triangles, "triangle"),
+                count(model.components, "component")
+            )?;
+            line(f, "vertices with NaN or infinite positions", &model.non_finite_positions, |v| format!("{v}"))?;
+            line(f, "degenerate triangles", &model.degenerate_triangles, |t| format!("{t}"))?;
+            line(f, "non-manifold edges", &model.non_manifold_edges, |[a, b]| format!("{a}-{b}"))?;
+            line(f, "edges with inconsistent winding", &model.inconsistent_winding, |[a, b]| format!("{a}-{b}"))?;
+            line(f, "duplicate vertices", &model.duplicate_vertices, |[v, first]| format!("{v}={first}"))?;
+        }
+        match self.problems() {
+            0 => writeln!(f, "no problems found"),
+            1 => writeln!(f, "1 kind of problem found"),
+            problems => writeln!(f, "{problems} kinds of problems found"),
+        }
+    }
+}
+
+fn count(n: usize, noun: &str) -> String {
+    match (n, noun) {
+        (1, _) => format!("1 {noun}"),
+        (_, "vertex") => format!("{n} vertices"),
+        _ => format!("{n} {noun}s"),
+    }
+}
+
+//one kind of problem, if the model has it, with its examples.
+fn line<T>(f: &mut fmt::Formatter<'_>, what: &str, finding: &Finding<T>, show: impl Fn(&T) -> String) -> fmt::Result {
+    if finding.count == 0 {
+        return Ok(());
+    }
+    let examples: Vec<String> = finding.examples.iter().map(show).collect();
+    let more = if finding.count > examples.len() { ", ..." } else { "" };
+    writeln!(f, "  {what}: {} ({}{more})", finding.count, examples.join(", "))
+}
+
+fn validate(model: &Model) -> ModelReport {
+    let positions: Vec<[f32; 3]> = model.mesh.positions.chunks_exact(3).map(|p| [p[0], p[1], p[2]]).collect();
+    let mut report = ModelReport {
+        name: model.name.clone(),
+        vertices: positions.len(),
+        triangles: model.mesh.indices.len() / 3,
+        ..ModelReport::default()
+    };
+    //every vertex stands for the first one at its position, for the topology checks.
+    let mut firsts: HashMap<[u32; 3], usize> = HashMap::new();
+    let mut welded = Vec::with_capacity(positions.len());
+    for (vertex, position) in positions.iter().enumerate() {
+        if !position.iter().all(|p| p.is_finite()) {
+            report.non_finite_positions.add(vertex);
+        }
+        let first = *firsts.entry(position.map(f32::to_bits)).or_insert(vertex);
+        if first != vertex {
+            report.duplicate_vertices.add([vertex, first]);
+        }
+        welded.push(first);
+    }
+    //the triangles running along each edge, in the direction each one does.
+    let mut edges: HashMap<[usize; 2], Vec<bool>> = HashMap::new();
+    let mut components = Components::new(positions.len());
+    for (triangle, indices) in model.mesh.indices.chunks_exact(3).enumerate() {
+        //the loaders leave out faces with out of range indices.
+        let [a, b, c] = [indices[0], indices[1], indices[2]].map(|i| welded[i as usize]);
+        if a == b || b == c || c == a || !has_area([a, b, c].map(|v| positions[v])) {
+            report.degenerate_triangles.add(triangle);
+            continue;
+        }
+        for (from, to) in [(a, b), (b, c), (c, a)] {
+            edges.entry([from.min(to), from.max(to)]).or_default().push(from < to);
+            components.join(from, to);
+        }
+    }
+    let mut sorted: Vec<_> = edges.into_iter().collect();
+    sorted.sort_unstable_by_key(|(edge, _)| *edge);
+    for (edge, directions) in sorted {
+        if directions.len() > 2 {
+            report.non_manifold_edges.add(edge);
+        } else if directions.len() == 2 && directions[0] == directions[1] {
+            report.inconsistent_winding.add(edge);
+        }
+    }
+    report.components = components.count();
+    report
+}
+
+//whether a triangle's corners span some area, relative to its size. Triangles with non-finite
+//corners have none.
+fn has_area([a, b, c]: [[f32; 3]; 3]) -> bool {
+    let u = [b[0] - a[0], b[1] - a[1], b[2] - a[2]];
+    let v = [c[0] - a[0], c[1] - a[1], c[2] - a[2]];
+    let cross = [u[1] * v[2] - u[2] * v[1], u[2] * v[0] - u[0] * v[2], u[0] * v[1] - u[1] * v[0]];
+    let area = cross.iter().map(|c| c * c).sum::<f32>().sqrt();
+    let size = u.iter().chain(&v).map(|c| c * c).sum::<f32>();
+    area > size * f32::EPSILON
+}
+
+//disjoint sets of the vertices that triangles connect.
+struct Components {
+    parents: Vec<usize>,
+    //vertices that any triangle uses, as the others belong to no component.
+    used: Vec<bool>,
+}
+
+impl Components {
+    fn new(vertices: usize) -> Self {
+        Components {
+            parents: (0..vertices).collect(),
+            used: vec![false; vertices],
+        }
+    }
+
+    fn root(&mut self, mut vertex: usize) -> usize {
+        while self.parents[vertex] != vertex {
+            self.parents[vertex] = self.parents[self.parents[vertex]];
+            vertex = self.parents[vertex];
+        }
+        vertex
+    }
+
+    fn join(&mut self, a: usize, b: usize) {
+        self.used[a] = true;
+        self.used[b] = true;
+        let (a, b) = (self.root(a), self.root(b));
+        self.parents[a] = b;
+    }
+
+    fn count(&mut self) -> usize {
+        (0..self.parents.len()).filter(|&v| self.used[v] && self.root(v) == v).count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mesh::OutOfRange;
+    use crate::obj::{get_lenient_mesh_data, get_mesh_data};
+    use crate::validate::{validate, Report};
+    use tobj::{Mesh, Model};
+
+    fn model(positions: Vec<f32>, indices: Vec<u32>) -> Model {
+        let mesh = Mesh {
+            positions,
+            indices,
+            ..Mesh::default()
+        };
+        Model::new(mesh, String::from("test"))
+    }
+
+    //the report of a mesh file of `contents`, loaded as --validate does.
+    fn report_of(name: &str, contents: &str) -> Report {
+        let file = std::env::temp_dir().join(format!("rusterizer-{}-{name}", std::process::id()));
+        std::fs::write(&file, contents).unwrap();
+        let (models, out_of_range) = get_lenient_mesh_data(file.to_str().unwrap()).unwrap();
+        std::fs::remove_file(&file).unwrap();
+        Report::new(name, &models, &out_of_range)
+    }
+
+    #[test]
+    fn sound_meshes_have_no_problems() {
+        let report = Report::new("tetra.obj", &get_mesh_data("tests/resources/tetra.obj").unwrap().0, &[]);
+        assert_eq!(report.problems(), 0, "{report}");
+        assert_eq!(report.models[0].components, 1);
+        assert!(report.to_string().ends_with("no problems found\n"));
+    }
+
+    #[test]
+    fn finds_broken_geometry() {
+        #[rustfmt::skip]
+        let positions = vec![
+            0.0, 0.0, 0.0,  1.0, 0.0, 0.0,  0.0, 1.0, 0.0,  0.0, 0.0, 1.0,
+            2.0, 2.0, 2.0,  f32::NAN, 0.0, 0.0,  1.0, 0.0, 0.0,  0.0, -1.0, 0.0,
+        ];
+        let indices = vec![
+            0, 1, 2, //
+            1, 0, 3, //
+            0, 1, 7, //a third triangle on edge 0-1, which it also runs along as 0, 1 does.
+            0, 1, 6, //6 is 1 again.
+            2, 5, 3, //
+            4, 4, 4, //
+        ];
+        let report = validate(&model(positions, indices));
+        assert_eq!(report.non_finite_positions.examples, [5]);
+        assert_eq!(report.degenerate_triangles.examples, [3, 4, 5]);
+        assert_eq!(report.non_manifold_edges.examples, [[0, 1]]);
+        assert_eq!(report.duplicate_vertices.examples, [[6, 1]]);
+        assert_eq!(report.components, 1);
+        assert_eq!(report.problems(), 3);
+    }
+
+    #[test]
+    fn positions_are_checked_as_loaded() {
+        //fitting the models would spread the infinite x over every position.
+        let report = report_of("infinite.obj", "v inf 0 0\nv 1 0 0\nv 0 1 0\nv 1 1 0\nf 1 2 3\nf 2 4 3\n");
+        let model = &report.models[0];
+        assert_eq!(model.non_finite_positions.examples, [0]);
+        assert_eq!(model.duplicate_vertices.count, 0);
+        assert_eq!(model.degenerate_triangles.examples, [0]);
+        assert_eq!(report.problems(), 2, "{report}");
+    }
+
+    #[test]
+    fn out_of_range_faces_are_reported() {
+        let obj = "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\nf 1 2 4\nf -1 -2 -4\n";
+        let report = report_of("range.obj", obj);
+        let expected = [OutOfRange { face: 1, index: 3 }, OutOfRange { face: 2, index: -1 }];
+        assert_eq!(report.out_of_range_indices.examples, expected);
+        assert_eq!(report.models[0].triangles, 1);
+        assert!(report.to_string().contains("faces with out of range indices: 2 (face 1 to vertex 3, face 2 to vertex -1)"));
+        let off = "OFF\n3 2 0\n0 0 0\n1 0 0\n0 1 0\n3 0 1 5\n3 0 1 2\n";
+        let report = report_of("range.off", off);
+        assert_eq!(report.out_of_range_indices.examples, [OutOfRange { face: 0, index: 5 }]);
+        assert_eq!(report.models[0].triangles, 1);
+    }
+
+    #[test]
+    fn finds_flipped_triangles_and_islands() {
+        #[rustfmt::skip]
+        let positions = vec![
+            0.0, 0.0, 0.0,  1.0, 0.0, 0.0,  0.0, 1.0, 0.0,  1.0, 1.0, 0.0,
+            5.0, 0.0, 0.0,  6.0, 0.0, 0.0,  5.0, 1.0, 0.0,
+        ];
+        //the second triangle runs along 1-2 the same way as the first.
+        let report = validate(&model(positions, vec![0, 1, 2, 1, 2, 3, 4, 5, 6]));
+        assert_eq!(report.inconsistent_winding.examples, [[1, 2]]);
+        assert_eq!(report.components, 2);
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["inconsistent_winding"]["count"], 1);
+    }
+}
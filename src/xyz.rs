@@ -0,0 +1,64 @@
+use crate::mesh::{invalid, parse_color, point_cloud, scale_colors};
+use std::io;
+use tobj::Model;
+
+//one vertex per line, as "x y z" and, for every vertex or none, "r g b" after it, separated by
+//whitespace or commas. Colors are all in [0, 255] if any is above 1, or else all in [0, 1], as in
+//OFF files, and any further values, like intensities, are ignored. Anything after a '#' is a comment. The
+//vertices become a point cloud.
+pub(crate) fn load(bytes: &[u8]) -> io::Result<Vec<Model>> {
+    let text = std::str::from_utf8(bytes).map_err(|_| invalid(String::from("an XYZ file must be text")))?;
+    let mut positions = vec![];
+    let mut colors = vec![];
+    //whether the vertices have colors, as the first one says.
+    let mut colored = None;
+    for (number, line) in text.lines().enumerate() {
+        let error = |message: &str| invalid(format!("line {}: {message}", number + 1));
+        let line = line.split('#').next().unwrap_or_default();
+        let values: Vec<&str> = line.split(|c: char| c.is_whitespace() || c == ',').filter(|v| !v.is_empty()).collect();
+        if values.is_empty() {
+            continue;
+        }
+        let parse = |values: &[&str]| values.iter().map(|v| v.parse::<f32>()).collect::<Result<Vec<_>, _>>();
+        match values.get(..3).map(parse) {
+            Some(Ok(position)) => positions.extend(position),
+            _ => return Err(error("expected x y z")),
+        }
+        if !*colored.get_or_insert(values.len() >= 6) {
+            continue;
+        }
+        let Some(color) = values.get(3..6).and_then(parse_color) else {
+            return Err(error("expected x y z r g b, as the first vertex has a color"));
+        };
+        colors.extend(color);
+    }
+    scale_colors(&mut colors);
+    Ok(vec![point_cloud(positions, colors)?])
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mesh::Primitive;
+    use crate::xyz::load;
+
+    #[test]
+    fn points_with_and_without_colors() {
+        let text = "# a scan\n0 0 0\n1,2,3\n\n0.5\t0.5 0.5 # the middle\n";
+        let mesh = &load(text.as_bytes()).unwrap()[0].mesh;
+        assert_eq!(mesh.positions[3..6], [1.0, 2.0, 3.0]);
+        assert_eq!(mesh.indices, [0, 1, 2]);
+        assert_eq!(Primitive::of(mesh), Primitive::Points);
+        assert!(mesh.vertex_color.is_empty());
+
+        let text = "0 0 0 255 0 51 7\n1 0 0 0 255.0 0 7\n";
+        let mesh = &load(text.as_bytes()).unwrap()[0].mesh;
+        assert_eq!(mesh.vertex_color, [1.0, 0.0, 0.2, 0.0, 1.0, 0.0]);
+        let text = "0 0 0 1 0 0\n1 0 0 0.5 1 0\n";
+        let mesh = &load(text.as_bytes()).unwrap()[0].mesh;
+        assert_eq!(mesh.vertex_color, [1.0, 0.0, 0.0, 0.5, 1.0, 0.0]);
+
+        let text = "0 0 0 255 0 0\n1 0 0\n";
+        let error = load(text.as_bytes()).unwrap_err().to_string();
+        assert_eq!(error, "line 2: expected x y z r g b, as the first vertex has a color");
+    }
+}